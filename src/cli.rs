@@ -0,0 +1,170 @@
+//! Command line interface definition.
+
+use crate::runtime::RUNTIME_ENV_VAR;
+use clap::{Parser, Subcommand};
+
+/// Environment variable holding the config file path
+pub const CONFIG_ENV_VAR: &str = "PODMAN_DEPLOY_CONFIG";
+
+/// Configuration-driven deployment of Podman pods and containers
+#[derive(Debug, Parser)]
+#[command(name = "podman_deploy", version, after_help = CONFIG_SEARCH_HELP)]
+pub struct Cli {
+    /// Path to the config file instead of the default search locations
+    #[arg(long, global = true, value_name = "PATH", env = CONFIG_ENV_VAR)]
+    pub config: Option<String>,
+
+    /// Print the podman, sudo and filesystem actions without performing them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Runtime backend: 'cli' or 'api'
+    #[arg(long, global = true, value_name = "BACKEND", env = RUNTIME_ENV_VAR, default_value = "cli")]
+    pub runtime: String,
+
+    #[command(subcommand)]
+    pub mode: Mode,
+}
+
+const CONFIG_SEARCH_HELP: &str = "\
+Config file search locations (in order):
+  1. --config <PATH> or $PODMAN_DEPLOY_CONFIG
+  2. ~/.config/podman_deploy/config.yaml
+  3. /etc/podman_deploy/config.yaml
+  4. ./config.yaml (current directory)";
+
+#[derive(Debug, Subcommand)]
+pub enum Mode {
+    /// Install podman, create directories, create pods, pull images, and stop containers/pods
+    Setup {
+        /// Create the pods with `podman kube play` from generated Kubernetes manifests
+        #[arg(long)]
+        kube: bool,
+    },
+    /// List all pods with their containers, status, and images
+    List,
+    /// Prune unused and untagged images
+    Prune,
+    /// Pin images to their locked digests and recreate containers running other images
+    Upgrade {
+        /// Only check and upgrade this container
+        container: Option<String>,
+        /// Wait for each container's probe or healthcheck and halt at the first that fails
+        #[arg(long)]
+        rolling: bool,
+        /// How long each container may take to pass its gate during --rolling (default: 2m)
+        #[arg(long, value_name = "DURATION", requires = "rolling")]
+        timeout: Option<String>,
+        /// How long a replaced container must keep running before it counts as started (default: 5s)
+        #[arg(long, value_name = "DURATION")]
+        start_gate: Option<String>,
+    },
+    /// Restore the image a container ran before its last upgrade
+    Rollback {
+        /// Container to roll back
+        container: String,
+        /// How long the restored container must keep running before it counts as started (default: 5s)
+        #[arg(long, value_name = "DURATION")]
+        start_gate: Option<String>,
+    },
+    /// Start all pods, or a specific pod
+    Start {
+        /// Only start this pod
+        pod: Option<String>,
+    },
+    /// Stop all pods, or a specific pod
+    Stop {
+        /// Only stop this pod
+        pod: Option<String>,
+    },
+    /// Show what setup/upgrade would create, recreate or remove
+    #[command(visible_alias = "diff")]
+    Plan {
+        /// Print the plan as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create, recreate or remove pods and containers whose spec changed
+    Apply,
+    /// Check the config file and report every problem with its line and column
+    Validate,
+    /// Convert files from other tools into a podman_deploy config
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    /// Generate deployment files from the config
+    Generate {
+        #[command(subcommand)]
+        target: GenerateTarget,
+    },
+    /// Manage the podman_deploy.lock file pinning images to digests
+    Lock {
+        #[command(subcommand)]
+        action: LockAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LockAction {
+    /// Resolve every image to its current digest and rewrite the lock file
+    Update,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GenerateTarget {
+    /// systemd Quadlet units (.pod and .container) so pods start at boot
+    Quadlet {
+        /// Write the unit files into this directory instead of printing them
+        #[arg(long, value_name = "DIR", conflicts_with = "install")]
+        output: Option<String>,
+
+        /// Install the units into the Quadlet directory and run `systemctl daemon-reload`
+        /// (~/.config/containers/systemd, or /etc/containers/systemd when run as root)
+        #[arg(long)]
+        install: bool,
+    },
+    /// Kubernetes Pod manifests for `podman kube play` or a cluster
+    Kube {
+        /// Write the manifests into this file instead of printing them
+        #[arg(long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportSource {
+    /// docker-compose file; all services become containers of one pod
+    Compose {
+        /// Path to the compose file
+        #[arg(default_value = "docker-compose.yml")]
+        file: String,
+
+        /// Name of the generated pod (default: "<project>-pod")
+        #[arg(long)]
+        pod: Option<String>,
+
+        /// Write the config into this file instead of printing it
+        #[arg(long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+impl Mode {
+    /// Returns true if the mode prints machine-readable output that must not
+    /// be mixed with progress messages
+    pub fn is_machine_readable(&self) -> bool {
+        matches!(
+            self,
+            Mode::Plan { json: true }
+                | Mode::Generate { target: GenerateTarget::Quadlet { output: None, install: false } }
+                | Mode::Generate { target: GenerateTarget::Kube { output: None } }
+                | Mode::Import { source: ImportSource::Compose { output: None, .. } }
+        )
+    }
+
+    /// Returns true if the mode reads the config file
+    pub fn needs_config(&self) -> bool {
+        !matches!(self, Mode::Prune | Mode::Import { .. })
+    }
+}
@@ -0,0 +1,904 @@
+//! docker-compose import.
+//!
+//! Translates the services of a compose file into containers of a single
+//! pod. Containers in a pod share one network namespace, so services reach
+//! each other on `localhost` instead of by service name. Every compose
+//! feature that has no equivalent in the configuration is reported rather
+//! than silently dropped.
+
+use crate::runtime::PortMapping;
+use crate::{
+    AppResult, Config, Container, ContainerSecret, Healthcheck, Mount, MountSpec, MountType, Pod, Resources, RestartPolicy, Secret,
+    SecretSpec, SecretType, Volume,
+};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// Top-level keys that need no mapping: `version` is obsolete
+const IGNORED_TOP_LEVEL_KEYS: &[&str] = &["version"];
+
+#[derive(Debug, Deserialize)]
+struct ComposeFile {
+    name: Option<String>,
+    /// Kept as a mapping so services stay in file order
+    #[serde(default)]
+    services: serde_yaml::Mapping,
+    /// Named volumes; a volume with default settings has no value
+    #[serde(default)]
+    volumes: BTreeMap<String, Option<ComposeVolume>>,
+    #[serde(default)]
+    secrets: BTreeMap<String, ComposeSecret>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComposeSecret {
+    file: Option<String>,
+    environment: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComposeVolume {
+    driver: Option<String>,
+    #[serde(default)]
+    driver_opts: BTreeMap<String, Value>,
+    labels: Option<Environment>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Service {
+    image: Option<String>,
+    container_name: Option<String>,
+    #[serde(default)]
+    ports: Vec<Value>,
+    #[serde(default)]
+    volumes: Vec<Value>,
+    environment: Option<Environment>,
+    env_file: Option<Value>,
+    restart: Option<String>,
+    healthcheck: Option<ComposeHealthcheck>,
+    mem_limit: Option<Value>,
+    cpus: Option<Value>,
+    pids_limit: Option<i64>,
+    command: Option<Value>,
+    entrypoint: Option<Value>,
+    working_dir: Option<String>,
+    user: Option<Value>,
+    tmpfs: Option<Value>,
+    #[serde(default)]
+    secrets: Vec<Value>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComposeHealthcheck {
+    test: Option<Value>,
+    interval: Option<String>,
+    timeout: Option<String>,
+    retries: Option<u32>,
+    start_period: Option<String>,
+    #[serde(default)]
+    disable: bool,
+}
+
+/// `environment:` or `labels:` as either a mapping or a list of "KEY=VALUE" entries
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Environment {
+    Map(BTreeMap<String, Option<Value>>),
+    List(Vec<String>),
+}
+
+/// Result of an import: the generated configuration and what could not be mapped
+#[derive(Debug)]
+pub struct Import {
+    pub config: Config,
+    /// One human readable line per compose feature that was dropped or approximated
+    pub unmapped: Vec<String>,
+}
+
+impl Import {
+    /// Serializes the configuration, listing the unmapped features as a comment header
+    pub fn to_yaml(&self) -> AppResult<String> {
+        let mut out = String::new();
+        if !self.unmapped.is_empty() {
+            out.push_str("# Compose features that could not be mapped:\n");
+            for line in &self.unmapped {
+                out.push_str(&format!("#   - {}\n", line));
+            }
+            out.push('\n');
+        }
+        out.push_str(&serde_yaml::to_string(&self.config)?);
+        Ok(out)
+    }
+}
+
+/// Renders a scalar YAML value (string, number or bool) as a string
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn map_environment(service: &str, environment: &Environment, unmapped: &mut Vec<String>) -> HashMap<String, String> {
+    let mut env_vars = HashMap::new();
+    let inherited = |key: &str, unmapped: &mut Vec<String>| {
+        unmapped.push(format!("service '{}': environment variable '{}' has no value (inherited from the host)", service, key));
+    };
+
+    match environment {
+        Environment::Map(map) => {
+            for (key, value) in map {
+                match value.as_ref().and_then(scalar) {
+                    Some(value) => { env_vars.insert(key.clone(), value); }
+                    None => inherited(key, unmapped),
+                }
+            }
+        }
+        Environment::List(list) => {
+            for entry in list {
+                match entry.split_once('=') {
+                    Some((key, value)) => { env_vars.insert(key.to_string(), value.to_string()); }
+                    None => inherited(entry, unmapped),
+                }
+            }
+        }
+    }
+    env_vars
+}
+
+/// Maps a short ("[ip:]host:container[/proto]") or long port definition
+fn map_port(service: &str, port: &Value, unmapped: &mut Vec<String>) -> Option<String> {
+    let mapped = match port {
+        Value::Mapping(long) => {
+            let field = |name: &str| long.get(name).and_then(scalar);
+            let target = field("target")?;
+            let published = field("published").unwrap_or_else(|| target.clone());
+            let mut spec = match field("host_ip") {
+                Some(ip) => format!("{}:{}:{}", ip, published, target),
+                None => format!("{}:{}", published, target),
+            };
+            if let Some(protocol) = field("protocol") {
+                spec.push_str(&format!("/{}", protocol));
+            }
+            Some(spec)
+        }
+        other => scalar(other),
+    };
+
+    match mapped {
+        Some(spec) if spec.contains('-') => {
+            unmapped.push(format!("service '{}': port range '{}' is not supported", service, spec));
+            None
+        }
+        Some(spec) if !spec.contains(':') => {
+            // Compose publishes a bare container port on a random host port;
+            // a pod needs a fixed one, so reuse the container port
+            unmapped.push(format!("service '{}': port '{}' published on the same host port", service, spec));
+            Some(format!("{}:{}", spec, spec))
+        }
+        Some(spec) => Some(spec),
+        None => {
+            unmapped.push(format!("service '{}': unrecognised port definition {:?}", service, port));
+            None
+        }
+    }
+}
+
+/// Maps a bind mount, named volume or tmpfs mount onto a container mount.
+///
+/// Configured host paths are relative to the data path, which is the
+/// compose file's directory, so `./data` becomes `/data`. Named volumes
+/// are added to `named` so they can be declared.
+fn map_volume(service: &str, volume: &Value, named: &mut BTreeSet<String>, unmapped: &mut Vec<String>) -> Option<Mount> {
+    let (source, target, options) = match volume {
+        Value::String(short) => {
+            let mut parts = short.splitn(3, ':');
+            let first = parts.next().unwrap_or_default();
+            match parts.next() {
+                Some(target) => {
+                    let mut options = Vec::new();
+                    for option in parts.next().unwrap_or_default().split(',').filter(|option| !option.is_empty()) {
+                        match option {
+                            "ro" | "rw" | "z" | "Z" => options.push(option.to_string()),
+                            other => unmapped.push(format!(
+                                "service '{}': volume option '{}' on {} is not supported", service, other, target
+                            )),
+                        }
+                    }
+                    (first.to_string(), target.to_string(), options)
+                }
+                None => {
+                    unmapped.push(format!("service '{}': anonymous volume '{}' is not supported", service, short));
+                    return None;
+                }
+            }
+        }
+        Value::Mapping(long) => {
+            let field = |name: &str| long.get(name).and_then(scalar);
+            let kind = field("type").unwrap_or_else(|| "volume".to_string());
+            if kind == "tmpfs" {
+                let target = field("target")?;
+                let size = long.get("tmpfs").and_then(|tmpfs| tmpfs.get("size")).and_then(scalar);
+                return Some(tmpfs_mount(target, size));
+            }
+            let (Some(source), Some(target)) = (field("source"), field("target")) else {
+                unmapped.push(format!("service '{}': {} mount without source and target is not supported", service, kind));
+                return None;
+            };
+            if kind != "bind" && kind != "volume" {
+                unmapped.push(format!("service '{}': {} mount '{}' is not supported", service, kind, target));
+                return None;
+            }
+            let mut options = Vec::new();
+            if long.get("read_only").and_then(Value::as_bool).unwrap_or(false) {
+                options.push("ro".to_string());
+            }
+            match long.get("bind").and_then(|bind| bind.get("selinux")).and_then(scalar) {
+                Some(label) if label == "z" || label == "Z" => options.push(label),
+                Some(label) => unmapped.push(format!("service '{}': selinux label '{}' on {} is not supported", service, label, target)),
+                None => {}
+            }
+            (source, target, options)
+        }
+        other => {
+            unmapped.push(format!("service '{}': unrecognised volume definition {:?}", service, other));
+            return None;
+        }
+    };
+
+    let host_path = if let Some(relative) = source.strip_prefix("./") {
+        format!("/{}", relative)
+    } else if source == "." {
+        "/".to_string()
+    } else if source.starts_with("../") || source.starts_with('~') {
+        unmapped.push(format!("service '{}': host path '{}' is outside the compose directory", service, source));
+        return None;
+    } else if source.starts_with('/') {
+        unmapped.push(format!(
+            "service '{}': absolute host path '{}' is now relative to data_path", service, source
+        ));
+        source
+    } else {
+        named.insert(source.clone());
+        source
+    };
+
+    Some(Mount::Short(if options.is_empty() {
+        format!("{}:{}", host_path, target)
+    } else {
+        format!("{}:{}:{}", host_path, target, options.join(","))
+    }))
+}
+
+fn tmpfs_mount(target: String, size: Option<String>) -> Mount {
+    Mount::Long(MountSpec {
+        kind: MountType::Tmpfs,
+        source: None,
+        target,
+        read_only: false,
+        size,
+        selinux: None,
+        owner: None,
+        mode: None,
+        template: None,
+        content: None,
+        overwrite: false,
+    })
+}
+
+/// Maps `tmpfs:` (one path or a list of paths, optionally with ":size=...") onto tmpfs mounts
+fn map_tmpfs(service: &str, tmpfs: &Value, unmapped: &mut Vec<String>) -> Vec<Mount> {
+    let paths: Vec<String> = match tmpfs {
+        Value::Sequence(paths) => paths.iter().filter_map(scalar).collect(),
+        other => scalar(other).into_iter().collect(),
+    };
+    let mut mounts = Vec::new();
+    for path in paths {
+        let (target, options) = path.split_once(':').unwrap_or((&path, ""));
+        let mut size = None;
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            match option.strip_prefix("size=") {
+                Some(value) => size = Some(value.to_string()),
+                None => unmapped.push(format!("service '{}': tmpfs option '{}' on {} is not supported", service, option, target)),
+            }
+        }
+        mounts.push(tmpfs_mount(target.to_string(), size));
+    }
+    mounts
+}
+
+/// Maps a top-level volume definition onto a named volume
+fn map_named_volume(name: &str, volume: Option<&ComposeVolume>, unmapped: &mut Vec<String>) -> Volume {
+    let mut mapped = Volume { name: name.to_string(), driver: None, options: BTreeMap::new(), labels: BTreeMap::new() };
+    let Some(volume) = volume else { return mapped };
+
+    for key in volume.other.keys() {
+        unmapped.push(format!("volume '{}': '{}' is not supported", name, key));
+    }
+    mapped.driver = volume.driver.clone();
+    mapped.options = volume.driver_opts.iter()
+        .filter_map(|(key, value)| scalar(value).map(|value| (key.clone(), value)))
+        .collect();
+    mapped.labels = match &volume.labels {
+        Some(Environment::Map(map)) => map.iter()
+            .map(|(key, value)| (key.clone(), value.as_ref().and_then(scalar).unwrap_or_default()))
+            .collect(),
+        Some(Environment::List(list)) => list.iter()
+            .map(|entry| entry.split_once('=').unwrap_or((entry, "")))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        None => BTreeMap::new(),
+    };
+    mapped
+}
+
+/// Splits a command string into words the way a POSIX shell would, without
+/// expanding anything. Returns None for unterminated quotes.
+fn split_words(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => current.push(c),
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).push(chars.next()?),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Maps `command:` or `entrypoint:` in string or list form onto separate arguments
+fn map_argv(service: &str, key: &str, value: &Value, unmapped: &mut Vec<String>) -> Option<Vec<String>> {
+    let argv = match value {
+        Value::String(command) => split_words(command),
+        Value::Sequence(words) => words.iter().map(scalar).collect(),
+        Value::Null => Some(Vec::new()),
+        _ => None,
+    };
+    if argv.is_none() {
+        unmapped.push(format!("service '{}': unrecognised {} {:?}", service, key, value));
+    }
+    argv
+}
+
+/// Maps `test:` ("CMD-SHELL", "CMD" or plain string form) onto a shell command
+fn map_healthcheck(service: &str, health: &ComposeHealthcheck, unmapped: &mut Vec<String>) -> Option<Healthcheck> {
+    if health.disable {
+        return None;
+    }
+    let command = match &health.test {
+        Some(Value::String(command)) => command.clone(),
+        Some(Value::Sequence(test)) => {
+            let words: Vec<String> = test.iter().filter_map(scalar).collect();
+            match words.split_first() {
+                Some((kind, rest)) if kind == "CMD-SHELL" || kind == "CMD" => rest.join(" "),
+                Some((kind, _)) if kind == "NONE" => return None,
+                _ => words.join(" "),
+            }
+        }
+        _ => {
+            unmapped.push(format!("service '{}': healthcheck without a test is not supported", service));
+            return None;
+        }
+    };
+
+    Some(Healthcheck {
+        command,
+        interval: health.interval.clone(),
+        timeout: health.timeout.clone(),
+        retries: health.retries,
+        start_period: health.start_period.clone(),
+    })
+}
+
+/// Maps `env_file:` (one path, a list of paths or a list of `{path, required}`) onto
+/// paths made absolute against the compose file's directory
+fn map_env_file(service: &str, env_file: &Value, directory: &Path, unmapped: &mut Vec<String>) -> Vec<String> {
+    let entries = match env_file {
+        Value::Sequence(entries) => entries.clone(),
+        other => vec![other.clone()],
+    };
+    let mut paths = Vec::new();
+    for entry in &entries {
+        let path = match entry {
+            Value::Mapping(long) => {
+                if long.get("required").and_then(Value::as_bool) == Some(false) {
+                    unmapped.push(format!("service '{}': optional env_file is treated as required", service));
+                }
+                long.get("path").and_then(scalar)
+            }
+            other => scalar(other),
+        };
+        match path {
+            Some(path) => paths.push(directory.join(path.trim_start_matches("./")).to_string_lossy().into_owned()),
+            None => unmapped.push(format!("service '{}': unrecognised env_file entry {:?}", service, entry)),
+        }
+    }
+    paths
+}
+
+/// Maps a service's short (name) or long (`source`, `target`) secret reference
+fn map_service_secret(service: &str, secret: &Value, unmapped: &mut Vec<String>) -> Option<ContainerSecret> {
+    let long = match secret {
+        Value::Mapping(long) => long,
+        other => return scalar(other).map(ContainerSecret::Short),
+    };
+    let source = long.get("source").and_then(scalar)?;
+    for key in ["uid", "gid", "mode"] {
+        if long.contains_key(key) {
+            unmapped.push(format!("service '{}': secret '{}': '{}' is not supported", service, source, key));
+        }
+    }
+    match long.get("target").and_then(scalar) {
+        Some(target) => Some(ContainerSecret::Long(SecretSpec { name: source, kind: SecretType::Mount, target: Some(target) })),
+        None => Some(ContainerSecret::Short(source)),
+    }
+}
+
+/// Maps a top-level secret onto a Podman secret read from a file or environment variable
+fn map_secret(name: &str, secret: &ComposeSecret, directory: &Path, unmapped: &mut Vec<String>) -> Option<Secret> {
+    for key in secret.other.keys() {
+        unmapped.push(format!("secret '{}': '{}' is not supported", name, key));
+    }
+    let file = secret.file.as_ref()
+        .map(|file| directory.join(file.trim_start_matches("./")).to_string_lossy().into_owned());
+    if file.is_none() && secret.environment.is_none() {
+        unmapped.push(format!("secret '{}': only file and environment secrets are supported, skipped", name));
+        return None;
+    }
+    Some(Secret { name: name.to_string(), file, env: secret.environment.clone() })
+}
+
+fn map_service(name: &str, service: &Service, directory: &Path, named: &mut BTreeSet<String>, unmapped: &mut Vec<String>) -> Option<Container> {
+    let Some(image) = &service.image else {
+        unmapped.push(format!("service '{}': no image (build-only services are not supported), skipped", name));
+        return None;
+    };
+
+    for key in service.other.keys() {
+        unmapped.push(format!("service '{}': '{}' is not supported", name, key));
+    }
+
+    let restart_policy = service.restart.as_ref().and_then(|restart| {
+        match RestartPolicy::try_from(restart.clone()) {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                unmapped.push(format!("service '{}': {}", name, e));
+                None
+            }
+        }
+    });
+
+    let resources = Resources {
+        memory: service.mem_limit.as_ref().and_then(scalar),
+        cpus: service.cpus.as_ref().and_then(scalar).and_then(|cpus| cpus.parse().ok()),
+        pids_limit: service.pids_limit,
+    };
+
+    let mut mounts: Vec<Mount> = service.volumes.iter()
+        .filter_map(|volume| map_volume(name, volume, named, unmapped))
+        .collect();
+    if let Some(tmpfs) = &service.tmpfs {
+        mounts.extend(map_tmpfs(name, tmpfs, unmapped));
+    }
+
+    Some(Container {
+        name: service.container_name.clone().unwrap_or_else(|| name.to_string()),
+        image: image.clone(),
+        mounts,
+        env_vars: service.environment.as_ref()
+            .map(|environment| map_environment(name, environment, unmapped))
+            .unwrap_or_default(),
+        env_file: service.env_file.as_ref()
+            .map(|env_file| map_env_file(name, env_file, directory, unmapped))
+            .unwrap_or_default(),
+        ports: service.ports.iter().filter_map(|port| map_port(name, port, unmapped)).collect(),
+        restart_policy,
+        healthcheck: service.healthcheck.as_ref().and_then(|health| map_healthcheck(name, health, unmapped)),
+        probe: None,
+        resources: (resources != Resources::default()).then_some(resources),
+        entrypoint: service.entrypoint.as_ref().and_then(|entrypoint| map_argv(name, "entrypoint", entrypoint, unmapped)),
+        command: service.command.as_ref().and_then(|command| map_argv(name, "command", command, unmapped)),
+        args: Vec::new(),
+        workdir: service.working_dir.clone(),
+        user: service.user.as_ref().and_then(scalar),
+        secrets: service.secrets.iter()
+            .filter_map(|secret| map_service_secret(name, secret, unmapped))
+            .collect(),
+    })
+}
+
+/// Reads a compose file and groups all of its services into one pod.
+///
+/// The application name defaults to the compose project name, or the
+/// name of the directory holding the compose file.
+pub fn import(compose_path: &str, pod_name: Option<&str>) -> AppResult<Import> {
+    let content = std::fs::read_to_string(compose_path)
+        .map_err(|e| format!("Failed to read compose file {}: {}", compose_path, e))?;
+    let compose: ComposeFile = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Failed to parse compose file {}: {}", compose_path, e))?;
+
+    let directory = std::fs::canonicalize(compose_path)?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let application_name = compose.name.clone().unwrap_or_else(|| {
+        directory.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "app".to_string())
+    });
+
+    let mut unmapped = Vec::new();
+    for key in compose.other.keys() {
+        if !IGNORED_TOP_LEVEL_KEYS.contains(&key.as_str()) {
+            unmapped.push(format!("top-level '{}' is not supported", key));
+        }
+    }
+
+    let mut containers = Vec::new();
+    let mut named = BTreeSet::new();
+    for (name, service) in compose.services {
+        let name = scalar(&name).ok_or("Compose service names must be strings")?;
+        let service: Service = serde_yaml::from_value(service)
+            .map_err(|e| format!("Invalid compose service '{}': {}", name, e))?;
+        containers.extend(map_service(&name, &service, &directory, &mut named, &mut unmapped));
+    }
+
+    // Compose requires named volumes to be declared; declare any that are not
+    named.extend(compose.volumes.keys().cloned());
+    let volumes = named.iter()
+        .map(|name| map_named_volume(name, compose.volumes.get(name).and_then(Option::as_ref), &mut unmapped))
+        .collect();
+    let secrets = compose.secrets.iter()
+        .filter_map(|(name, secret)| map_secret(name, secret, &directory, &mut unmapped))
+        .collect();
+    if containers.is_empty() {
+        return Err(format!("Compose file {} has no services with an image", compose_path).into());
+    }
+
+    // Services share the pod's network namespace, so two of them cannot
+    // listen on the same container port
+    let mut listeners: BTreeMap<(u16, String), &str> = BTreeMap::new();
+    for container in &containers {
+        for mapping in container.ports.iter().filter_map(|port| PortMapping::parse(port)) {
+            let key = (mapping.container_port, mapping.protocol);
+            match listeners.get(&key) {
+                Some(other) if *other != container.name => unmapped.push(format!(
+                    "services '{}' and '{}' both use container port {}/{}, which conflicts inside one pod",
+                    other, container.name, key.0, key.1
+                )),
+                Some(_) => {}
+                None => { listeners.insert(key, &container.name); }
+            }
+        }
+    }
+
+    let pod = Pod {
+        name: pod_name.map(str::to_string).unwrap_or_else(|| format!("{}-pod", application_name)),
+        containers,
+    };
+
+    Ok(Import {
+        config: Config {
+            application_name,
+            is_podman_installed: false,
+            data_path: directory.to_string_lossy().into_owned(),
+            pods: vec![pod],
+            volumes,
+            secrets,
+            registries: Vec::new(),
+            unqualified_search_registries: Vec::new(),
+            private_registry: None,
+            registry_username: None,
+            registry_password: None,
+            registry_password_from: None,
+        },
+        unmapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn words(command: &str) -> Vec<String> {
+        split_words(command).unwrap()
+    }
+
+    #[test]
+    fn splits_on_any_whitespace() {
+        assert_eq!(words("  nginx -g\t'daemon off;'\n"), ["nginx", "-g", "daemon off;"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn single_quotes_keep_everything_literal() {
+        assert_eq!(words(r#"echo 'a "b" \n $HOME'"#), ["echo", r#"a "b" \n $HOME"#]);
+    }
+
+    #[test]
+    fn double_quotes_unescape_only_special_characters() {
+        assert_eq!(words(r#"echo "say \"hi\" \$x \\ \n""#), ["echo", r#"say "hi" $x \ \n"#]);
+    }
+
+    #[test]
+    fn backslash_escapes_the_next_character_outside_quotes() {
+        assert_eq!(words(r"touch my\ file \'"), ["touch", "my file", "'"]);
+    }
+
+    #[test]
+    fn adjacent_quoted_parts_form_one_word() {
+        assert_eq!(words(r#"--name='a b'"c d"e"#), [r"--name=a bc de"]);
+    }
+
+    #[test]
+    fn empty_quotes_are_an_empty_word() {
+        assert_eq!(words(r#"printf '' """#), ["printf", "", ""]);
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes_and_trailing_backslash() {
+        assert_eq!(split_words("echo 'open"), None);
+        assert_eq!(split_words(r#"echo "open"#), None);
+        assert_eq!(split_words(r#"echo "open\"#), None);
+        assert_eq!(split_words(r"echo \"), None);
+    }
+
+    /// Imports `compose` from a scratch directory, returning the import and the
+    /// canonical directory, which the data path and absolute paths start with
+    fn import_compose(name: &str, compose: &str, pod_name: Option<&str>) -> (Import, PathBuf) {
+        let dir = env::temp_dir().join(format!("podman_deploy-compose-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let compose_path = dir.join("compose.yaml");
+        fs::write(&compose_path, compose).unwrap();
+        let import = import(&compose_path.to_string_lossy(), pod_name);
+        let dir = fs::canonicalize(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        (import.unwrap(), dir)
+    }
+
+    fn mounts(container: &Container) -> Vec<String> {
+        container.mounts.iter().map(Mount::to_string).collect()
+    }
+
+    #[test]
+    fn maps_services_to_containers_of_one_pod() {
+        let (import, dir) = import_compose("services", r#"
+name: shop
+services:
+  web:
+    image: nginx:1.25
+    container_name: shop-web
+    environment:
+      NGINX_HOST: example.com
+    restart: unless-stopped
+  worker:
+    image: busybox
+    environment:
+      - MODE=worker
+    command: sh -c 'work --forever'
+"#, None);
+        let config = &import.config;
+        assert_eq!(config.application_name, "shop");
+        assert_eq!(config.data_path, dir.to_string_lossy());
+        assert_eq!(config.pods.len(), 1);
+        assert_eq!(config.pods[0].name, "shop-pod");
+
+        let [web, worker] = &config.pods[0].containers[..] else { panic!("expected two containers") };
+        assert_eq!((web.name.as_str(), web.image.as_str()), ("shop-web", "nginx:1.25"));
+        assert_eq!(web.env_vars["NGINX_HOST"], "example.com");
+        assert_eq!(web.restart_policy, Some(RestartPolicy::UnlessStopped));
+        assert_eq!((worker.name.as_str(), worker.image.as_str()), ("worker", "busybox"));
+        assert_eq!(worker.env_vars["MODE"], "worker");
+        assert_eq!(worker.command.as_deref(), Some(&["sh".to_string(), "-c".to_string(), "work --forever".to_string()][..]));
+        assert!(import.unmapped.is_empty());
+    }
+
+    #[test]
+    fn names_the_application_after_the_directory_and_accepts_a_pod_name() {
+        let (import, dir) = import_compose("naming", "services:\n  web:\n    image: nginx\n", Some("frontend"));
+        assert_eq!(import.config.application_name, dir.file_name().unwrap().to_string_lossy());
+        assert_eq!(import.config.pods[0].name, "frontend");
+    }
+
+    #[test]
+    fn maps_short_and_long_ports() {
+        let (import, _) = import_compose("ports", r#"
+services:
+  web:
+    image: nginx
+    ports:
+      - "8080:80"
+      - 443
+      - "127.0.0.1:8443:443/tcp"
+      - target: 53
+        published: 5353
+        host_ip: 127.0.0.1
+        protocol: udp
+      - target: 9000
+      - "3000-3001:3000-3001"
+"#, None);
+        assert_eq!(import.config.pods[0].containers[0].ports, [
+            "8080:80", "443:443", "127.0.0.1:8443:443/tcp", "127.0.0.1:5353:53/udp", "9000:9000",
+        ]);
+        assert_eq!(import.unmapped, [
+            "service 'web': port '443' published on the same host port",
+            "service 'web': port range '3000-3001:3000-3001' is not supported",
+        ]);
+    }
+
+    #[test]
+    fn maps_volumes_to_mounts_relative_to_the_data_path() {
+        let (import, _) = import_compose("volumes", r#"
+services:
+  web:
+    image: nginx
+    volumes:
+      - ./html:/usr/share/nginx/html:ro
+      - .:/srv
+      - /var/log/web:/var/log/nginx:Z
+      - cache:/var/cache/nginx
+      - type: bind
+        source: ./conf
+        target: /etc/nginx/conf.d
+        read_only: true
+      - type: tmpfs
+        target: /run
+        tmpfs:
+          size: 64m
+      - ../outside:/outside
+      - /anonymous
+    tmpfs: /tmp:size=16m
+volumes:
+  pgdata:
+    driver: local
+    driver_opts:
+      type: none
+"#, None);
+        let config = &import.config;
+        assert_eq!(mounts(&config.pods[0].containers[0]), [
+            "/html:/usr/share/nginx/html:ro",
+            "/:/srv",
+            "/var/log/web:/var/log/nginx:Z",
+            "cache:/var/cache/nginx",
+            "/conf:/etc/nginx/conf.d:ro",
+            "tmpfs /run",
+            "tmpfs /tmp",
+        ]);
+        assert_eq!(config.pods[0].containers[0].mounts[5].spec().unwrap().size.as_deref(), Some("64m"));
+        // Used and declared named volumes are both declared
+        let volumes: Vec<(&str, Option<&str>)> = config.volumes.iter().map(|volume| (volume.name.as_str(), volume.driver.as_deref())).collect();
+        assert_eq!(volumes, [("cache", None), ("pgdata", Some("local"))]);
+        assert_eq!(config.volumes[1].options["type"], "none");
+        assert_eq!(import.unmapped, [
+            "service 'web': absolute host path '/var/log/web' is now relative to data_path",
+            "service 'web': host path '../outside' is outside the compose directory",
+            "service 'web': anonymous volume '/anonymous' is not supported",
+        ]);
+    }
+
+    #[test]
+    fn makes_env_files_and_secret_files_absolute() {
+        let (import, dir) = import_compose("files", r#"
+services:
+  web:
+    image: nginx
+    env_file:
+      - ./web.env
+      - path: shared.env
+        required: false
+    secrets:
+      - api_key
+      - source: tls_cert
+        target: /run/certs/cert.pem
+secrets:
+  api_key:
+    file: ./secrets/api_key.txt
+  tls_cert:
+    environment: TLS_CERT
+"#, None);
+        let config = &import.config;
+        let web = &config.pods[0].containers[0];
+        assert_eq!(web.env_file, [
+            dir.join("web.env").to_string_lossy().into_owned(),
+            dir.join("shared.env").to_string_lossy().into_owned(),
+        ]);
+        assert_eq!(config.secrets, [
+            Secret { name: "api_key".to_string(), file: Some(dir.join("secrets/api_key.txt").to_string_lossy().into_owned()), env: None },
+            Secret { name: "tls_cert".to_string(), file: None, env: Some("TLS_CERT".to_string()) },
+        ]);
+        let secrets: Vec<SecretSpec> = web.secrets.iter().map(ContainerSecret::spec).collect();
+        assert_eq!(secrets, [
+            SecretSpec { name: "api_key".to_string(), kind: SecretType::Mount, target: None },
+            SecretSpec { name: "tls_cert".to_string(), kind: SecretType::Mount, target: Some("/run/certs/cert.pem".to_string()) },
+        ]);
+        assert_eq!(import.unmapped, ["service 'web': optional env_file is treated as required"]);
+    }
+
+    #[test]
+    fn reports_unsupported_keys() {
+        let (import, _) = import_compose("unsupported", r#"
+version: "3.8"
+services:
+  web:
+    image: nginx
+    depends_on: [db]
+    environment:
+      HOME:
+    ports:
+      - "8080:80"
+    secrets:
+      - source: token
+        mode: 0400
+  api:
+    image: api
+    ports:
+      - "8081:80"
+  builder:
+    build: .
+networks:
+  default: {}
+volumes:
+  data:
+    external: true
+secrets:
+  token:
+    external: true
+"#, None);
+        assert_eq!(import.unmapped, [
+            "top-level 'networks' is not supported",
+            "service 'web': 'depends_on' is not supported",
+            "service 'web': environment variable 'HOME' has no value (inherited from the host)",
+            "service 'web': secret 'token': 'mode' is not supported",
+            "service 'builder': no image (build-only services are not supported), skipped",
+            "volume 'data': 'external' is not supported",
+            "secret 'token': 'external' is not supported",
+            "secret 'token': only file and environment secrets are supported, skipped",
+            "services 'web' and 'api' both use container port 80/tcp, which conflicts inside one pod",
+        ]);
+        assert!(import.to_yaml().unwrap().starts_with("# Compose features that could not be mapped:\n#   - top-level 'networks' is not supported\n"));
+    }
+}
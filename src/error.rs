@@ -0,0 +1,210 @@
+//! Application error type and process exit codes.
+//!
+//! Failures that automation needs to tell apart have their own variant and
+//! exit code. Everything else exits with 1. Wrapping variants keep the
+//! underlying error as their [`source`](std::error::Error::source), so
+//! `main` can print the whole chain.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Exit code for failures without a dedicated code
+pub const EXIT_FAILURE: i32 = 1;
+// 2 is used by the argument parser for usage errors
+pub const EXIT_CONFIG_NOT_FOUND: i32 = 3;
+pub const EXIT_CONFIG_INVALID: i32 = 4;
+pub const EXIT_PODMAN_MISSING: i32 = 5;
+pub const EXIT_REGISTRY_LOGIN: i32 = 6;
+pub const EXIT_PULL: i32 = 7;
+pub const EXIT_CONTAINER_CREATE: i32 = 8;
+
+#[derive(Debug)]
+pub enum AppError {
+    /// No config file at the given path or in any search location
+    ConfigNotFound(String),
+    /// The config file exists but cannot be parsed
+    ConfigInvalid { path: String, source: Box<AppError> },
+    /// Podman is not installed and could not be installed
+    PodmanMissing(String),
+    RegistryLogin { registry: String, source: Box<AppError> },
+    Pull { image: String, source: Box<AppError> },
+    ContainerCreate { container: String, pod: String, source: Box<AppError> },
+    /// A failed external command, e.g. `podman pod start web-pod`
+    CommandFailed { command: String, code: Option<i32> },
+    /// Describes what was being done when the source error happened
+    Context { message: String, source: Box<AppError> },
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+    Json(serde_json::Error),
+    Message(String),
+}
+
+impl AppError {
+    pub fn context(message: impl Into<String>, source: impl Into<AppError>) -> Self {
+        AppError::Context { message: message.into(), source: Box::new(source.into()) }
+    }
+
+    pub fn config_invalid(path: &str, source: impl Into<AppError>) -> Self {
+        AppError::ConfigInvalid { path: path.to_string(), source: Box::new(source.into()) }
+    }
+
+    pub fn registry_login(registry: &str, source: impl Into<AppError>) -> Self {
+        AppError::RegistryLogin { registry: registry.to_string(), source: Box::new(source.into()) }
+    }
+
+    pub fn pull(image: &str, source: impl Into<AppError>) -> Self {
+        AppError::Pull { image: image.to_string(), source: Box::new(source.into()) }
+    }
+
+    pub fn container_create(container: &str, pod: &str, source: impl Into<AppError>) -> Self {
+        AppError::ContainerCreate {
+            container: container.to_string(),
+            pod: pod.to_string(),
+            source: Box::new(source.into()),
+        }
+    }
+
+    /// Returns the process exit code for this error.
+    ///
+    /// Context wrappers report the code of the error they wrap, so a pull
+    /// failure during `setup` still exits with [`EXIT_PULL`].
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::ConfigNotFound(_) => EXIT_CONFIG_NOT_FOUND,
+            AppError::ConfigInvalid { .. } => EXIT_CONFIG_INVALID,
+            AppError::PodmanMissing(_) => EXIT_PODMAN_MISSING,
+            AppError::RegistryLogin { .. } => EXIT_REGISTRY_LOGIN,
+            AppError::Pull { .. } => EXIT_PULL,
+            AppError::ContainerCreate { .. } => EXIT_CONTAINER_CREATE,
+            AppError::Context { source, .. } => source.exit_code(),
+            AppError::CommandFailed { .. }
+            | AppError::Io(_)
+            | AppError::Yaml(_)
+            | AppError::Json(_)
+            | AppError::Message(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::ConfigNotFound(message) | AppError::PodmanMissing(message) | AppError::Message(message) => {
+                write!(f, "{}", message)
+            }
+            AppError::ConfigInvalid { path, .. } => write!(f, "Invalid config file: {}", path),
+            AppError::RegistryLogin { registry, .. } => write!(f, "Failed to login to registry: {}", registry),
+            AppError::Pull { image, .. } => write!(f, "Failed to pull image: {}", image),
+            AppError::ContainerCreate { container, pod, .. } => {
+                write!(f, "Failed to create container '{}' in pod '{}'", container, pod)
+            }
+            AppError::CommandFailed { command, code: Some(code) } => {
+                write!(f, "`{}` exited with status {}", command, code)
+            }
+            AppError::CommandFailed { command, code: None } => write!(f, "`{}` was terminated by a signal", command),
+            AppError::Context { message, .. } => write!(f, "{}", message),
+            AppError::Io(e) => write!(f, "{}", e),
+            AppError::Yaml(e) => write!(f, "{}", e),
+            AppError::Json(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppError::ConfigInvalid { source, .. }
+            | AppError::RegistryLogin { source, .. }
+            | AppError::Pull { source, .. }
+            | AppError::ContainerCreate { source, .. }
+            | AppError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+impl From<serde_yaml::Error> for AppError {
+    fn from(e: serde_yaml::Error) -> Self {
+        AppError::Yaml(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Json(e)
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Message(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Message(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> AppError {
+        AppError::Message(text.to_string())
+    }
+
+    #[test]
+    fn dedicated_variants_have_their_own_exit_code() {
+        assert_eq!(AppError::ConfigNotFound("missing".to_string()).exit_code(), EXIT_CONFIG_NOT_FOUND);
+        assert_eq!(AppError::config_invalid("config.yaml", message("bad")).exit_code(), EXIT_CONFIG_INVALID);
+        assert_eq!(AppError::PodmanMissing("not found".to_string()).exit_code(), EXIT_PODMAN_MISSING);
+        assert_eq!(AppError::registry_login("ghcr.io", message("denied")).exit_code(), EXIT_REGISTRY_LOGIN);
+        assert_eq!(AppError::pull("nginx", message("not found")).exit_code(), EXIT_PULL);
+        assert_eq!(AppError::container_create("web", "web-pod", message("in use")).exit_code(), EXIT_CONTAINER_CREATE);
+    }
+
+    #[test]
+    fn other_variants_exit_with_the_generic_code() {
+        let command = AppError::CommandFailed { command: "podman pod start web-pod".to_string(), code: Some(125) };
+        let io = AppError::from(io::Error::other("broken pipe"));
+        let yaml = AppError::from(serde_yaml::from_str::<u32>("text").unwrap_err());
+        let json = AppError::from(serde_json::from_str::<u32>("text").unwrap_err());
+        for error in [command, io, yaml, json, message("failed")] {
+            assert_eq!(error.exit_code(), EXIT_FAILURE, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn context_reports_the_code_of_the_wrapped_error() {
+        let pull = AppError::context("Setup failed", AppError::context("Pulling images", AppError::pull("nginx", message("timeout"))));
+        assert_eq!(pull.exit_code(), EXIT_PULL);
+        assert_eq!(AppError::context("Setup failed", message("failed")).exit_code(), EXIT_FAILURE);
+        // A dedicated variant decides the code, whatever it wraps
+        assert_eq!(AppError::registry_login("ghcr.io", AppError::pull("nginx", message("timeout"))).exit_code(), EXIT_REGISTRY_LOGIN);
+    }
+
+    #[test]
+    fn codes_are_distinct_and_leave_two_for_usage_errors() {
+        let codes = [EXIT_FAILURE, EXIT_CONFIG_NOT_FOUND, EXIT_CONFIG_INVALID, EXIT_PODMAN_MISSING, EXIT_REGISTRY_LOGIN, EXIT_PULL, EXIT_CONTAINER_CREATE];
+        let unique: std::collections::HashSet<i32> = codes.iter().copied().collect();
+        assert_eq!(unique.len(), codes.len());
+        assert!(!unique.contains(&0) && !unique.contains(&2));
+    }
+
+    #[test]
+    fn describes_failed_commands_by_exit_status_or_signal() {
+        let exited = AppError::CommandFailed { command: "podman pod start web-pod".to_string(), code: Some(125) };
+        let killed = AppError::CommandFailed { command: "podman pod start web-pod".to_string(), code: None };
+        assert_eq!(exited.to_string(), "`podman pod start web-pod` exited with status 125");
+        assert_eq!(killed.to_string(), "`podman pod start web-pod` was terminated by a signal");
+    }
+}
@@ -0,0 +1,59 @@
+//! The record of the last known good image of each container.
+//!
+//! Before `upgrade` replaces a container it notes the image the container
+//! was running, by name, image ID and manifest digest. `rollback` restores
+//! that image, from the local store while it is still there or by digest
+//! otherwise. The record lives in the data path, since it describes the
+//! host rather than the config.
+
+use crate::error::AppError;
+use crate::AppResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = ".podman_deploy_history.yaml";
+
+const HEADER: &str = "# Generated by podman_deploy, used by `podman_deploy rollback`\n";
+
+/// Image a container ran before it was last upgraded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownGood {
+    pub image: String,
+    /// Local image ID, empty if unknown
+    #[serde(default)]
+    pub id: String,
+    /// Manifest digest (e.g., "sha256:..."), empty if unknown
+    #[serde(default)]
+    pub digest: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    /// Container name -> last known good image
+    #[serde(default)]
+    pub containers: BTreeMap<String, KnownGood>,
+}
+
+impl History {
+    /// Returns the history file path for a data path
+    pub fn path(data_path: &Path) -> PathBuf {
+        data_path.join(FILE_NAME)
+    }
+
+    /// Reads the history, returning an empty one if it does not exist yet
+    pub fn load(path: &Path) -> AppResult<History> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_yaml::from_str(&contents)
+                .map_err(|e| AppError::context(format!("Failed to parse upgrade history {}", path.display()), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(History::default()),
+            Err(e) => Err(AppError::context(format!("Failed to read upgrade history {}", path.display()), e)),
+        }
+    }
+
+    pub fn to_yaml(&self) -> AppResult<String> {
+        Ok(format!("{}{}", HEADER, serde_yaml::to_string(self)?))
+    }
+}
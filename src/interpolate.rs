@@ -0,0 +1,152 @@
+//! Variable interpolation in the config file.
+//!
+//! Every string value may reference environment variables as `${VAR}`, or
+//! `${VAR:-default}` to fall back to a default when `VAR` is unset or
+//! empty. `$$` is a literal `$`. Other uses of `$`, such as `$HOME` or a
+//! `${` without a closing brace, are left unchanged. Mapping keys are never
+//! interpolated.
+
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+
+/// Returns true for names of the form `[A-Za-z_][A-Za-z0-9_]*`
+pub fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Interpolates one string, recording variables that are unset and have no default
+fn interpolate_str(text: &str, lookup: &dyn Fn(&str) -> Option<String>, unresolved: &mut BTreeSet<String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        rest = &rest[dollar..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let expression = rest.strip_prefix("${").and_then(|inner| inner.find('}').map(|end| &inner[..end]));
+        let Some(expression) = expression else {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if !is_variable_name(name) {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        }
+
+        match (lookup(name).filter(|value| default.is_none() || !value.is_empty()), default) {
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => {
+                unresolved.insert(name.to_string());
+            }
+        }
+        rest = &rest[expression.len() + 3..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Interpolates every string in a YAML document in place.
+///
+/// Returns the sorted names of all variables that could not be resolved.
+pub fn interpolate(value: &mut Value, lookup: &dyn Fn(&str) -> Option<String>) -> Result<(), Vec<String>> {
+    fn walk(value: &mut Value, lookup: &dyn Fn(&str) -> Option<String>, unresolved: &mut BTreeSet<String>) {
+        match value {
+            Value::String(text) => *text = interpolate_str(text, lookup, unresolved),
+            Value::Sequence(items) => items.iter_mut().for_each(|item| walk(item, lookup, unresolved)),
+            Value::Mapping(mapping) => mapping.values_mut().for_each(|item| walk(item, lookup, unresolved)),
+            Value::Tagged(tagged) => walk(&mut tagged.value, lookup, unresolved),
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+
+    let mut unresolved = BTreeSet::new();
+    walk(value, lookup, &mut unresolved);
+    if unresolved.is_empty() { Ok(()) } else { Err(unresolved.into_iter().collect()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    fn render(text: &str) -> (String, BTreeSet<String>) {
+        let mut unresolved = BTreeSet::new();
+        let result = interpolate_str(text, &lookup, &mut unresolved);
+        (result, unresolved)
+    }
+
+    #[test]
+    fn replaces_set_variables() {
+        assert_eq!(render("https://${HOST}/api").0, "https://example.com/api");
+    }
+
+    #[test]
+    fn falls_back_to_default_when_unset_or_empty() {
+        assert_eq!(render("${MISSING:-8080}").0, "8080");
+        assert_eq!(render("${EMPTY:-fallback}").0, "fallback");
+        assert_eq!(render("${HOST:-fallback}").0, "example.com");
+        assert_eq!(render("${MISSING:-}").0, "");
+    }
+
+    #[test]
+    fn empty_value_without_default_is_kept() {
+        let (result, unresolved) = render("[${EMPTY}]");
+        assert_eq!(result, "[]");
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn records_unset_variables_without_default() {
+        let (_, unresolved) = render("${B} ${A} ${B}");
+        assert_eq!(unresolved.into_iter().collect::<Vec<_>>(), ["A", "B"]);
+    }
+
+    #[test]
+    fn double_dollar_is_a_literal_dollar() {
+        assert_eq!(render("$${HOST} costs $$5").0, "${HOST} costs $5");
+        assert_eq!(render("$$$$").0, "$$");
+    }
+
+    #[test]
+    fn leaves_other_dollar_uses_unchanged() {
+        assert_eq!(render("$HOME and $host").0, "$HOME and $host");
+        assert_eq!(render("${unclosed").0, "${unclosed");
+        assert_eq!(render("${1INVALID}").0, "${1INVALID}");
+        assert_eq!(render("trailing $").0, "trailing $");
+    }
+
+    #[test]
+    fn interpolates_values_but_not_keys() {
+        let mut value: Value = serde_yaml::from_str("${HOST}:\n  - ${HOST}\n  - port: ${PORT:-80}\n").unwrap();
+        interpolate(&mut value, &lookup).unwrap();
+        let expected: Value = serde_yaml::from_str("${HOST}:\n  - example.com\n  - port: '80'\n").unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn reports_unresolved_variables_sorted() {
+        let mut value: Value = serde_yaml::from_str("a: ${ZETA}\nb: ['${ALPHA}']\n").unwrap();
+        assert_eq!(interpolate(&mut value, &lookup), Err(vec!["ALPHA".to_string(), "ZETA".to_string()]));
+    }
+}
@@ -0,0 +1,567 @@
+//! Kubernetes Pod manifest export.
+//!
+//! Each configured pod becomes a `v1/Pod` manifest that `podman kube play`
+//! or a real cluster can run. Bind mounts become `hostPath` volumes under
+//! the data path, named volumes become persistent volume claims (which
+//! `podman kube play` backs with the Podman volume of the same name) and
+//! tmpfs mounts become memory-backed `emptyDir` volumes. Secrets refer to a
+//! Kubernetes Secret of the same name holding the value under a key of the
+//! same name, through `secretKeyRef` for env secrets and `secret` volumes
+//! for mounted ones.
+
+use crate::quadlet::absolute_data_path;
+use crate::runtime::PortMapping;
+use crate::units::{parse_duration, parse_memory};
+use crate::{AppResult, Config, Container, MountType, Pod, SecretType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Annotation recording which application a manifest belongs to
+const APPLICATION_ANNOTATION: &str = "podman-deploy/application";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KubePod {
+    pub api_version: String,
+    pub kind: String,
+    pub metadata: ObjectMeta,
+    pub spec: PodSpec,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectMeta {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PodSpec {
+    pub containers: Vec<KubeContainer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<Volume>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KubeContainer {
+    pub name: String,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<EnvVar>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<ContainerPort>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volume_mounts: Vec<VolumeMount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceRequirements>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liveness_probe: Option<Probe>,
+    /// Replaces the image's ENTRYPOINT
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// Replaces the image's CMD
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_context: Option<SecurityContext>,
+}
+
+/// Numeric user and group the container process runs as
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as_user: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as_group: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceRequirements {
+    /// "memory" in bytes and "cpu" in CPUs
+    #[serde(default)]
+    pub limits: BTreeMap<String, String>,
+}
+
+/// Exec probe, which `podman kube play` turns into a container healthcheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Probe {
+    pub exec: ExecAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_threshold: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_delay_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecAction {
+    pub command: Vec<String>,
+}
+
+/// Environment variable; exactly one of `value` and `value_from` is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvVar {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_from: Option<EnvVarSource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvVarSource {
+    pub secret_key_ref: SecretKeySelector,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretKeySelector {
+    /// Name of the Secret
+    pub name: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerPort {
+    pub container_port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_port: Option<u16>,
+    #[serde(rename = "hostIP", default, skip_serializing_if = "Option::is_none")]
+    pub host_ip: Option<String>,
+    pub protocol: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeMount {
+    pub name: String,
+    pub mount_path: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    /// Mounts a single file of the volume instead of all of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_path: Option<String>,
+}
+
+/// Pod volume; exactly one of the sources is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Volume {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_path: Option<HostPathVolume>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_volume_claim: Option<PersistentVolumeClaimVolume>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub empty_dir: Option<EmptyDirVolume>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<SecretVolume>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostPathVolume {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistentVolumeClaimVolume {
+    pub claim_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretVolume {
+    pub secret_name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<KeyToPath>,
+}
+
+/// Secret key projected into a file of a secret volume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyToPath {
+    pub key: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmptyDirVolume {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub medium: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_limit: Option<String>,
+}
+
+/// Turns an arbitrary name into a DNS-1123 label usable as a volume name
+fn dns_label(name: &str) -> String {
+    let label: String = name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    label.trim_matches('-').chars().take(63).collect()
+}
+
+fn kube_container(container: &Container, data_path: &str, volumes: &mut Vec<Volume>) -> AppResult<KubeContainer> {
+    let mut env: Vec<EnvVar> = container.env_vars.iter()
+        .map(|(name, value)| EnvVar { name: name.clone(), value: Some(value.clone()), value_from: None })
+        .collect();
+    for spec in container.secrets.iter().map(|secret| secret.spec()).filter(|spec| spec.kind == SecretType::Env) {
+        env.push(EnvVar {
+            name: spec.target.clone().unwrap_or_else(|| spec.name.clone()),
+            value: None,
+            value_from: Some(EnvVarSource {
+                secret_key_ref: SecretKeySelector { name: spec.name.clone(), key: spec.name.clone() },
+            }),
+        });
+    }
+    env.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut ports = Vec::new();
+    for port in &container.ports {
+        let mapping = PortMapping::parse(port)
+            .ok_or_else(|| format!("Invalid port mapping '{}' in container '{}'", port, container.name))?;
+        ports.push(ContainerPort {
+            container_port: mapping.container_port,
+            host_port: Some(mapping.host_port),
+            host_ip: if mapping.host_ip.is_empty() { None } else { Some(mapping.host_ip) },
+            protocol: mapping.protocol.to_uppercase(),
+        });
+    }
+
+    let mut volume_mounts = Vec::new();
+    for (index, mount) in container.mounts.iter().enumerate() {
+        let Some(spec) = mount.spec() else {
+            return Err(format!("Invalid mount '{}' in container '{}'", mount, container.name).into());
+        };
+
+        let name = dns_label(&format!("{}-mount-{}", container.name, index));
+        let mut volume = Volume { name: name.clone(), host_path: None, persistent_volume_claim: None, empty_dir: None, secret: None };
+        match spec.kind {
+            MountType::Bind | MountType::File | MountType::Dir => {
+                volume.host_path = Some(HostPathVolume {
+                    path: spec.host_path(data_path),
+                    kind: if spec.is_file() { "FileOrCreate" } else { "DirectoryOrCreate" }.to_string(),
+                });
+            }
+            MountType::Volume => {
+                volume.persistent_volume_claim = Some(PersistentVolumeClaimVolume {
+                    claim_name: spec.source.clone().unwrap_or_default(),
+                });
+            }
+            MountType::Tmpfs => {
+                volume.empty_dir = Some(EmptyDirVolume {
+                    medium: Some("Memory".to_string()),
+                    size_limit: spec.size.as_deref().and_then(parse_memory).map(|bytes| bytes.to_string()),
+                });
+            }
+        }
+        volumes.push(volume);
+        volume_mounts.push(VolumeMount {
+            name,
+            mount_path: spec.target.clone(),
+            read_only: spec.read_only,
+            sub_path: None,
+        });
+    }
+
+    // Like `podman run --secret`, a mounted secret is a single file under /run/secrets by default
+    let mounted_secrets = container.secrets.iter().map(|secret| secret.spec()).filter(|spec| spec.kind == SecretType::Mount);
+    for (index, spec) in mounted_secrets.enumerate() {
+        let name = dns_label(&format!("{}-secret-{}", container.name, index));
+        let mount_path = match &spec.target {
+            Some(target) if target.starts_with('/') => target.clone(),
+            Some(target) => format!("/run/secrets/{}", target),
+            None => format!("/run/secrets/{}", spec.name),
+        };
+        volumes.push(Volume {
+            name: name.clone(),
+            host_path: None,
+            persistent_volume_claim: None,
+            empty_dir: None,
+            secret: Some(SecretVolume {
+                secret_name: spec.name.clone(),
+                items: vec![KeyToPath { key: spec.name.clone(), path: spec.name.clone() }],
+            }),
+        });
+        volume_mounts.push(VolumeMount { name, mount_path, read_only: true, sub_path: Some(spec.name) });
+    }
+
+    // Kubernetes has no per-container process limit, so pids_limit is not exported
+    let resources = container.resources.as_ref().map(|resources| {
+        let mut limits = BTreeMap::new();
+        if let Some(memory) = resources.memory.as_deref().and_then(parse_memory) {
+            limits.insert("memory".to_string(), memory.to_string());
+        }
+        if let Some(cpus) = resources.cpus {
+            limits.insert("cpu".to_string(), cpus.to_string());
+        }
+        ResourceRequirements { limits }
+    }).filter(|resources| !resources.limits.is_empty());
+
+    let liveness_probe = container.healthcheck.as_ref().map(|health| {
+        let seconds = |value: &Option<String>| value.as_deref().and_then(parse_duration).map(|d| d.as_secs());
+        Probe {
+            exec: ExecAction { command: vec!["/bin/sh".to_string(), "-c".to_string(), health.command.clone()] },
+            period_seconds: seconds(&health.interval),
+            timeout_seconds: seconds(&health.timeout),
+            failure_threshold: health.retries,
+            initial_delay_seconds: seconds(&health.start_period),
+        }
+    });
+
+    // Kubernetes only accepts numeric ids, so user names cannot be exported
+    let security_context = match container.user.as_deref() {
+        None => None,
+        Some(user) => {
+            let (uid, gid) = match user.split_once(':') {
+                Some((uid, gid)) => (uid, Some(gid)),
+                None => (user, None),
+            };
+            let invalid = || format!(
+                "User '{}' of container '{}' must be numeric (uid[:gid]) in a Kubernetes manifest", user, container.name
+            );
+            Some(SecurityContext {
+                run_as_user: Some(uid.parse().map_err(|_| invalid())?),
+                run_as_group: gid.map(|gid| gid.parse().map_err(|_| invalid())).transpose()?,
+            })
+        }
+    };
+
+    // An explicit entrypoint also drops the image's CMD, as with `podman run --entrypoint`
+    Ok(KubeContainer {
+        name: container.name.clone(),
+        image: container.image.clone(),
+        env,
+        ports,
+        volume_mounts,
+        resources,
+        liveness_probe,
+        command: container.entrypoint.clone().unwrap_or_default(),
+        args: container.command_line().unwrap_or_default(),
+        working_dir: container.workdir.clone(),
+        security_context,
+    })
+}
+
+/// Converts one configured pod into a Kubernetes Pod manifest
+pub fn kube_pod(config: &Config, pod: &Pod, data_path: &str) -> AppResult<KubePod> {
+    let mut volumes = Vec::new();
+    let containers = pod.containers.iter()
+        .map(|container| kube_container(container, data_path, &mut volumes))
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let mut labels = BTreeMap::new();
+    labels.insert("app".to_string(), dns_label(&pod.name));
+    labels.insert("app.kubernetes.io/managed-by".to_string(), "podman_deploy".to_string());
+    let mut annotations = BTreeMap::new();
+    annotations.insert(APPLICATION_ANNOTATION.to_string(), config.application_name.clone());
+
+    Ok(KubePod {
+        api_version: "v1".to_string(),
+        kind: "Pod".to_string(),
+        metadata: ObjectMeta { name: pod.name.clone(), labels, annotations },
+        spec: PodSpec { containers, volumes },
+    })
+}
+
+/// Converts every pod in the configuration into manifests
+pub fn generate(config: &Config) -> AppResult<Vec<KubePod>> {
+    let data_path = absolute_data_path(&config.data_path)?;
+    config.pods.iter()
+        .map(|pod| kube_pod(config, pod, &data_path))
+        .collect()
+}
+
+/// Serializes manifests as a multi-document YAML stream
+pub fn to_yaml(pods: &[KubePod]) -> AppResult<String> {
+    let mut out = String::new();
+    for pod in pods {
+        out.push_str("---\n");
+        out.push_str(&serde_yaml::to_string(pod)?);
+    }
+    Ok(out)
+}
+
+/// Parses a multi-document YAML stream back into manifests, as the in-memory
+/// test backend plays them
+#[cfg(test)]
+pub fn from_yaml(yaml: &str) -> AppResult<Vec<KubePod>> {
+    let mut pods = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(yaml) {
+        pods.push(KubePod::deserialize(document)?);
+    }
+    Ok(pods)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONFIG: &str = r#"
+application_name: shop
+is_podman_installed: true
+data_path: /srv/shop
+secrets:
+  - name: db_password
+    env: DB_PASSWORD
+  - name: tls_cert
+    file: /etc/tls/cert.pem
+pods:
+  - name: web-pod
+    containers:
+      - name: web
+        image: nginx:1.25
+        env_vars:
+          NGINX_HOST: example.com
+        ports:
+          - "127.0.0.1:8080:80"
+          - "5353:53/udp"
+        mounts:
+          - /html:/usr/share/nginx/html:ro
+          - /conf/nginx.conf:/etc/nginx/nginx.conf
+          - cache:/var/cache/nginx
+          - type: tmpfs
+            target: /tmp
+            size: 64m
+        secrets:
+          - name: db_password
+            type: env
+            target: PASSWORD
+          - name: tls_cert
+            target: cert.pem
+        entrypoint: ["nginx"]
+        command: ["-g", "daemon off;"]
+        workdir: /srv
+        user: "101:101"
+        healthcheck:
+          command: curl -f http://localhost/
+          interval: 30s
+          timeout: 5s
+          retries: 3
+          start_period: 1m
+        resources:
+          memory: 512m
+          cpus: 1.5
+          pids_limit: 100
+"#;
+
+    fn config() -> Config {
+        serde_yaml::from_str(CONFIG).unwrap()
+    }
+
+    #[test]
+    fn generates_a_pod_manifest_for_every_configured_pod() {
+        let pods = generate(&config()).unwrap();
+        assert_eq!(pods.len(), 1);
+        assert_eq!(serde_json::to_value(&pods[0]).unwrap(), json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": "web-pod",
+                "labels": { "app": "web-pod", "app.kubernetes.io/managed-by": "podman_deploy" },
+                "annotations": { "podman-deploy/application": "shop" },
+            },
+            "spec": {
+                "containers": [{
+                    "name": "web",
+                    "image": "nginx:1.25",
+                    "env": [
+                        { "name": "NGINX_HOST", "value": "example.com" },
+                        { "name": "PASSWORD", "valueFrom": { "secretKeyRef": { "name": "db_password", "key": "db_password" } } },
+                    ],
+                    "ports": [
+                        { "containerPort": 80, "hostPort": 8080, "hostIP": "127.0.0.1", "protocol": "TCP" },
+                        { "containerPort": 53, "hostPort": 5353, "protocol": "UDP" },
+                    ],
+                    "volumeMounts": [
+                        { "name": "web-mount-0", "mountPath": "/usr/share/nginx/html", "readOnly": true },
+                        { "name": "web-mount-1", "mountPath": "/etc/nginx/nginx.conf" },
+                        { "name": "web-mount-2", "mountPath": "/var/cache/nginx" },
+                        { "name": "web-mount-3", "mountPath": "/tmp" },
+                        { "name": "web-secret-0", "mountPath": "/run/secrets/cert.pem", "readOnly": true, "subPath": "tls_cert" },
+                    ],
+                    "resources": { "limits": { "cpu": "1.5", "memory": "536870912" } },
+                    "livenessProbe": {
+                        "exec": { "command": ["/bin/sh", "-c", "curl -f http://localhost/"] },
+                        "periodSeconds": 30,
+                        "timeoutSeconds": 5,
+                        "failureThreshold": 3,
+                        "initialDelaySeconds": 60,
+                    },
+                    "command": ["nginx"],
+                    "args": ["-g", "daemon off;"],
+                    "workingDir": "/srv",
+                    "securityContext": { "runAsUser": 101, "runAsGroup": 101 },
+                }],
+                "volumes": [
+                    { "name": "web-mount-0", "hostPath": { "path": "/srv/shop/html", "type": "DirectoryOrCreate" } },
+                    { "name": "web-mount-1", "hostPath": { "path": "/srv/shop/conf/nginx.conf", "type": "FileOrCreate" } },
+                    { "name": "web-mount-2", "persistentVolumeClaim": { "claimName": "cache" } },
+                    { "name": "web-mount-3", "emptyDir": { "medium": "Memory", "sizeLimit": "67108864" } },
+                    { "name": "web-secret-0", "secret": { "secretName": "tls_cert", "items": [{ "key": "tls_cert", "path": "tls_cert" }] } },
+                ],
+            },
+        }));
+    }
+
+    #[test]
+    fn leaves_out_what_is_not_configured() {
+        let config: Config = serde_yaml::from_str(
+            "application_name: shop\nis_podman_installed: true\ndata_path: /srv/shop\npods:\n  - name: api\n    containers:\n      - name: api\n        image: api\n        env_vars: {}\n        ports: []\n        mounts: []\n"
+        ).unwrap();
+        let pod = kube_pod(&config, &config.pods[0], "/srv/shop").unwrap();
+        assert_eq!(serde_json::to_value(&pod.spec).unwrap(), json!({ "containers": [{ "name": "api", "image": "api" }] }));
+    }
+
+    #[test]
+    fn rejects_user_names_and_invalid_ports() {
+        let mut config = config();
+        config.pods[0].containers[0].user = Some("nginx".to_string());
+        let error = generate(&config).unwrap_err().to_string();
+        assert_eq!(error, "User 'nginx' of container 'web' must be numeric (uid[:gid]) in a Kubernetes manifest");
+
+        let mut config = self::config();
+        config.pods[0].containers[0].ports = vec!["http".to_string()];
+        assert_eq!(generate(&config).unwrap_err().to_string(), "Invalid port mapping 'http' in container 'web'");
+    }
+
+    #[test]
+    fn turns_names_into_dns_labels() {
+        assert_eq!(dns_label("Web_Pod-mount-0"), "web-pod-mount-0");
+        assert_eq!(dns_label("-edge-"), "edge");
+        assert_eq!(dns_label(&"a".repeat(70)).len(), 63);
+    }
+
+    #[test]
+    fn round_trips_a_multi_document_stream() {
+        let mut config = config();
+        let mut second = config.pods[0].clone();
+        second.name = "worker-pod".to_string();
+        second.containers[0].name = "worker".to_string();
+        second.containers[0].ports.clear();
+        config.pods.push(second);
+
+        let yaml = to_yaml(&generate(&config).unwrap()).unwrap();
+        assert!(yaml.starts_with("---\napiVersion: v1\nkind: Pod\n"));
+        let pods = from_yaml(&yaml).unwrap();
+        let names: Vec<&str> = pods.iter().map(|pod| pod.metadata.name.as_str()).collect();
+        assert_eq!(names, ["web-pod", "worker-pod"]);
+        assert_eq!(to_yaml(&pods).unwrap(), yaml);
+    }
+}
@@ -0,0 +1,92 @@
+//! The `podman_deploy.lock` file pinning images to digests.
+//!
+//! A tag such as `nginx:1.21` or `:latest` can be re-pushed, so the tag
+//! alone does not say which bits a host runs. The lock file records the
+//! manifest digest each image of the config resolved to. `upgrade` pins
+//! containers to these digests and only resolves images missing from the
+//! file, while `lock update` refreshes all of them deliberately. It lives
+//! next to the config file and is meant to be committed with it.
+
+use crate::error::AppError;
+use crate::AppResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "podman_deploy.lock";
+
+const HEADER: &str = "# Generated by podman_deploy, refresh with `podman_deploy lock update`\n";
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockFile {
+    /// Image as written in the config -> manifest digest (e.g., "sha256:...")
+    #[serde(default)]
+    pub images: BTreeMap<String, String>,
+}
+
+impl LockFile {
+    /// Returns the lock file path for a config file in `config_dir`
+    pub fn path(config_dir: &Path) -> PathBuf {
+        config_dir.join(FILE_NAME)
+    }
+
+    /// Reads a lock file, returning an empty one if it does not exist yet
+    pub fn load(path: &Path) -> AppResult<LockFile> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_yaml::from_str(&contents)
+                .map_err(|e| AppError::context(format!("Failed to parse lock file {}", path.display()), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(LockFile::default()),
+            Err(e) => Err(AppError::context(format!("Failed to read lock file {}", path.display()), e)),
+        }
+    }
+
+    pub fn to_yaml(&self) -> AppResult<String> {
+        Ok(format!("{}{}", HEADER, serde_yaml::to_string(self)?))
+    }
+}
+
+/// Returns the image pinned to a digest, e.g. "docker.io/library/nginx@sha256:..."
+pub fn pinned_reference(image: &str, digest: &str) -> String {
+    format!("{}@{}", repository(image), digest)
+}
+
+/// Strips the tag and digest from an image, keeping a registry port
+fn repository(image: &str) -> &str {
+    let image = image.split_once('@').map_or(image, |(name, _)| name);
+    match image.rsplit_once(':') {
+        Some((name, tag)) if !tag.contains('/') => name,
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_the_tag() {
+        assert_eq!(repository("nginx:1.21"), "nginx");
+        assert_eq!(repository("ghcr.io/org/app:latest"), "ghcr.io/org/app");
+        assert_eq!(repository("nginx"), "nginx");
+    }
+
+    #[test]
+    fn keeps_a_registry_port() {
+        assert_eq!(repository("harbor.local:5000/team/app"), "harbor.local:5000/team/app");
+        assert_eq!(repository("harbor.local:5000/team/app:2.0"), "harbor.local:5000/team/app");
+    }
+
+    #[test]
+    fn strips_a_digest_with_or_without_a_tag() {
+        assert_eq!(repository("nginx@sha256:abc"), "nginx");
+        assert_eq!(repository("harbor.local:5000/app:2.0@sha256:abc"), "harbor.local:5000/app");
+    }
+
+    #[test]
+    fn pins_a_tagged_image_to_a_digest() {
+        assert_eq!(pinned_reference("harbor.local:5000/app:2.0", "sha256:abc"), "harbor.local:5000/app@sha256:abc");
+        assert_eq!(pinned_reference("nginx@sha256:old", "sha256:new"), "nginx@sha256:new");
+    }
+}
@@ -1,21 +1,45 @@
+mod cli;
+mod compose;
+mod error;
+mod history;
+mod interpolate;
+mod kube;
+mod lock;
+mod plan;
+mod probe;
+mod quadlet;
+mod redact;
+mod registries_conf;
+mod runtime;
+mod template;
+mod units;
+mod validate;
+
+use clap::Parser;
+use cli::{Cli, GenerateTarget, ImportSource, LockAction, Mode};
+use error::AppError;
+use history::{History, KnownGood};
+use lock::LockFile;
+use runtime::ContainerRuntime;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
+use std::fmt;
 use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Command;
-
-/// Helper function to execute podman commands with consistent error handling
-fn execute_podman_command(args: &[&str]) -> Result<bool, Box<dyn std::error::Error>> {
-    let status = Command::new("podman")
-        .args(args)
-        .status()?;
-    
-    Ok(status.success())
-}
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Helper function to execute system commands with consistent error handling
-fn execute_system_command(cmd: &str, args: &[&str]) -> Result<bool, Box<dyn std::error::Error>> {
+fn execute_system_command(cmd: &str, args: &[&str], dry_run: bool) -> AppResult<bool> {
+    if dry_run {
+        println!("[dry-run] Would run: {} {}", cmd, args.join(" "));
+        return Ok(true);
+    }
+    
     let status = Command::new(cmd)
         .args(args)
         .status()?;
@@ -23,8 +47,28 @@ fn execute_system_command(cmd: &str, args: &[&str]) -> Result<bool, Box<dyn std:
     Ok(status.success())
 }
 
-/// Error type for the application
-type AppResult<T> = Result<T, Box<dyn std::error::Error>>;
+/// Creates a directory and its parents, or only reports it in dry-run mode
+fn create_dir(path: &Path, dry_run: bool) -> AppResult<()> {
+    if dry_run {
+        println!("[dry-run] Would create directory: {}", path.display());
+    } else {
+        fs::create_dir_all(path)?;
+    }
+    Ok(())
+}
+
+/// Writes a file, or only reports it in dry-run mode
+fn write_file(path: &Path, contents: &str, dry_run: bool) -> AppResult<()> {
+    if dry_run {
+        println!("[dry-run] Would write file: {} ({} bytes)", path.display(), contents.len());
+    } else {
+        fs::write(path, contents)?;
+    }
+    Ok(())
+}
+
+/// Result type for the application
+type AppResult<T> = Result<T, AppError>;
 
 /// Supported Linux distributions for automatic Podman installation
 #[derive(Debug)]
@@ -44,12 +88,463 @@ struct Container {
     name: String,
     /// Container image (e.g., "nginx:1.21")
     image: String,
-    /// Mount points in format "host_path:container_path"
-    mounts: Vec<String>,
+    /// Bind mounts, named volumes and tmpfs mounts
+    mounts: Vec<Mount>,
     /// Environment variables as key-value pairs
     env_vars: HashMap<String, String>,
+    /// Files of KEY=VALUE lines, relative to the config file's directory, merged into
+    /// `env_vars` at load time (variables set in `env_vars` take precedence)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    env_file: Vec<String>,
     /// Port mappings in format "host_port:container_port"
     ports: Vec<String>,
+    /// Restart policy ("no", "always", "on-failure[:max_retries]" or "unless-stopped")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    restart_policy: Option<RestartPolicy>,
+    /// Healthcheck run by Podman inside the container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    healthcheck: Option<Healthcheck>,
+    /// Readiness check run from the host during `upgrade --rolling`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    probe: Option<Probe>,
+    /// Memory, CPU and process limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resources: Option<Resources>,
+    /// Replaces the image's ENTRYPOINT (e.g., ["/bin/sh", "-c"])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entrypoint: Option<Vec<String>>,
+    /// Replaces the image's CMD (e.g., ["redis-server"])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    /// Arguments appended to `command`, or to the image's entrypoint if `command` is unset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    /// Working directory inside the container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workdir: Option<String>,
+    /// User (and optionally group) the container process runs as (e.g., "999:999")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    /// Secrets from the top-level `secrets` section exposed to the container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    secrets: Vec<ContainerSecret>,
+}
+
+impl Container {
+    /// Arguments passed after the image: `command` followed by `args`.
+    ///
+    /// Returns None if neither is configured, so the image's CMD is kept.
+    fn command_line(&self) -> Option<Vec<String>> {
+        if self.command.is_none() && self.args.is_empty() {
+            return None;
+        }
+        let mut line = self.command.clone().unwrap_or_default();
+        line.extend(self.args.iter().cloned());
+        Some(line)
+    }
+
+    /// Value for `podman run --entrypoint`: a JSON array unless it is a single word
+    fn entrypoint_arg(&self) -> Option<String> {
+        self.entrypoint.as_ref().map(|entrypoint| match entrypoint.as_slice() {
+            [] => String::new(),
+            [program] => program.clone(),
+            _ => serde_json::to_string(entrypoint).unwrap_or_default(),
+        })
+    }
+}
+
+/// Kind of storage behind a container mount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MountType {
+    /// Host path under `data_path`, a file or directory depending on its name
+    #[default]
+    Bind,
+    /// Host file under `data_path`
+    File,
+    /// Host directory under `data_path`
+    Dir,
+    /// Podman-managed named volume
+    Volume,
+    /// In-memory filesystem, empty on every start
+    Tmpfs,
+}
+
+impl MountType {
+    /// Returns true for mounts of a host path
+    fn is_bind(self) -> bool {
+        matches!(self, MountType::Bind | MountType::File | MountType::Dir)
+    }
+}
+
+impl fmt::Display for MountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MountType::Bind => write!(f, "bind"),
+            MountType::File => write!(f, "file"),
+            MountType::Dir => write!(f, "dir"),
+            MountType::Volume => write!(f, "volume"),
+            MountType::Tmpfs => write!(f, "tmpfs"),
+        }
+    }
+}
+
+/// SELinux relabeling of a mount source, so the container may access it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SelinuxLabel {
+    /// Label shared by all containers ("z")
+    #[serde(alias = "z")]
+    Shared,
+    /// Label private to this container ("Z")
+    #[serde(alias = "Z")]
+    Private,
+}
+
+impl SelinuxLabel {
+    /// Podman mount option for the label
+    fn option(self) -> &'static str {
+        match self {
+            SelinuxLabel::Shared => "z",
+            SelinuxLabel::Private => "Z",
+        }
+    }
+}
+
+/// Structured mount definition
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct MountSpec {
+    #[serde(rename = "type", default)]
+    kind: MountType,
+    /// Host path relative to `data_path` for bind mounts, volume name for volumes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Path inside the container
+    target: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    read_only: bool,
+    /// Size limit of a tmpfs mount (e.g., "64m")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selinux: Option<SelinuxLabel>,
+    /// "uid[:gid]" the host path of a bind mount is chowned to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    /// Octal permissions of the host path of a bind mount (e.g., "0750")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+    /// Template the host file is seeded from, relative to the config file's directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<String>,
+    /// Inline template the host file is seeded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    /// Rewrite the host file whenever the rendered template differs from it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    overwrite: bool,
+}
+
+impl MountSpec {
+    /// Host path of a bind mount, resolved against the data path
+    fn host_path(&self, data_path: &str) -> String {
+        format!("{}{}", data_path, self.source.as_deref().unwrap_or_default())
+    }
+
+    /// Mount options in Podman's comma-separated form (e.g., "ro", "Z", "size=64m")
+    fn podman_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if self.read_only {
+            options.push("ro".to_string());
+        }
+        if let Some(label) = self.selinux {
+            options.push(label.option().to_string());
+        }
+        if let Some(size) = &self.size {
+            options.push(format!("size={}", size));
+        }
+        options
+    }
+
+    /// Returns true if the host path of a bind mount is a file rather than a directory
+    fn is_file(&self) -> bool {
+        match self.kind {
+            MountType::File => true,
+            MountType::Dir => false,
+            _ if self.template.is_some() || self.content.is_some() => true,
+            _ => self.source.as_deref().is_some_and(is_file_mount),
+        }
+    }
+
+    /// Permission bits of `mode`, or None if unset or not an octal mode
+    fn mode_bits(&self) -> Option<u32> {
+        self.mode.as_deref()
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+            .filter(|bits| *bits <= 0o7777)
+    }
+}
+
+/// A container mount in string or structured form
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+enum Mount {
+    /// "host_path:container_path[:options]" bind mount, or a named volume
+    /// when the source is not an absolute path ("pgdata:/var/lib/postgresql/data")
+    Short(String),
+    Long(MountSpec),
+}
+
+impl Mount {
+    /// Returns the structured form, or None if the string form is malformed
+    fn spec(&self) -> Option<MountSpec> {
+        self.parse().ok()
+    }
+
+    /// Parses the string form, describing why it is malformed on failure
+    fn parse(&self) -> Result<MountSpec, String> {
+        let mount = match self {
+            Mount::Long(spec) => return Ok(spec.clone()),
+            Mount::Short(mount) => mount,
+        };
+        let mut parts = mount.splitn(3, ':');
+        let (Some(source), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(format!("mount '{}' must be in the format source:container_path[:options]", mount));
+        };
+        let mut read_only = None;
+        let mut selinux = None;
+        for option in parts.next().unwrap_or_default().split(',').filter(|option| !option.is_empty()) {
+            let conflict = match option {
+                "ro" | "rw" => read_only.replace(option == "ro").is_some(),
+                "z" => selinux.replace(SelinuxLabel::Shared).is_some(),
+                "Z" => selinux.replace(SelinuxLabel::Private).is_some(),
+                other => {
+                    return Err(format!("unknown option '{}' in mount '{}', expected ro, rw, z or Z", other, mount));
+                }
+            };
+            if conflict {
+                return Err(format!("mount '{}' has conflicting options", mount));
+            }
+        }
+        Ok(MountSpec {
+            kind: if source.starts_with('/') { MountType::Bind } else { MountType::Volume },
+            source: Some(source.to_string()),
+            target: target.to_string(),
+            read_only: read_only.unwrap_or(false),
+            size: None,
+            selinux,
+            owner: None,
+            mode: None,
+            template: None,
+            content: None,
+            overwrite: false,
+        })
+    }
+}
+
+impl fmt::Display for Mount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mount::Short(mount) => write!(f, "{}", mount),
+            Mount::Long(spec) => match &spec.source {
+                Some(source) => write!(f, "{} {}:{}", spec.kind, source, spec.target),
+                None => write!(f, "{} {}", spec.kind, spec.target),
+            },
+        }
+    }
+}
+
+/// Podman-managed named volume, created during setup
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Volume {
+    /// Volume name, referenced by volume mounts
+    name: String,
+    /// Volume driver (Podman uses "local" if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    driver: Option<String>,
+    /// Driver options (e.g., "type", "device" and "o" for the local driver)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    options: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+}
+
+/// Podman secret created during setup from a file or an environment variable
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Secret {
+    /// Secret name, referenced by containers
+    name: String,
+    /// File holding the value, relative to the config file's directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// Environment variable holding the value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<String>,
+}
+
+/// How a secret is exposed inside a container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SecretType {
+    /// File under /run/secrets
+    #[default]
+    Mount,
+    /// Environment variable
+    Env,
+}
+
+/// Structured secret reference
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct SecretSpec {
+    /// Name of the secret in the top-level `secrets` section
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: SecretType,
+    /// File path for mounts (default /run/secrets/<name>), variable name for env (default <name>)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+impl SecretSpec {
+    /// Value of Podman's `--secret` option (e.g., "db-password,type=env,target=DB_PASSWORD")
+    fn podman_arg(&self) -> String {
+        let mut arg = self.name.clone();
+        if self.kind == SecretType::Env {
+            arg.push_str(",type=env");
+        }
+        if let Some(target) = &self.target {
+            arg.push_str(&format!(",target={}", target));
+        }
+        arg
+    }
+}
+
+/// A container's secret, by name (mounted as a file) or in structured form
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+enum ContainerSecret {
+    Short(String),
+    Long(SecretSpec),
+}
+
+impl ContainerSecret {
+    fn spec(&self) -> SecretSpec {
+        match self {
+            ContainerSecret::Short(name) => SecretSpec { name: name.clone(), kind: SecretType::Mount, target: None },
+            ContainerSecret::Long(spec) => spec.clone(),
+        }
+    }
+}
+
+/// What Podman does when a container exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+enum RestartPolicy {
+    No,
+    Always,
+    /// Restart on a non-zero exit code, optionally at most this many times
+    OnFailure(Option<u32>),
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    /// Policy name without the retry count, as used by Podman
+    fn name(&self) -> &'static str {
+        match self {
+            RestartPolicy::No => "no",
+            RestartPolicy::Always => "always",
+            RestartPolicy::OnFailure(_) => "on-failure",
+            RestartPolicy::UnlessStopped => "unless-stopped",
+        }
+    }
+
+    fn max_retries(&self) -> Option<u32> {
+        match self {
+            RestartPolicy::OnFailure(retries) => *retries,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max_retries() {
+            Some(retries) => write!(f, "{}:{}", self.name(), retries),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl TryFrom<String> for RestartPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (name, retries) = match value.split_once(':') {
+            Some((name, retries)) => (name, Some(retries)),
+            None => (value.as_str(), None),
+        };
+        match (name, retries) {
+            ("no", None) => Ok(RestartPolicy::No),
+            ("always", None) => Ok(RestartPolicy::Always),
+            ("unless-stopped", None) => Ok(RestartPolicy::UnlessStopped),
+            ("on-failure", None) => Ok(RestartPolicy::OnFailure(None)),
+            ("on-failure", Some(retries)) => retries.parse()
+                .map(|retries| RestartPolicy::OnFailure(Some(retries)))
+                .map_err(|_| format!("invalid retry count in restart policy '{}'", value)),
+            _ => Err(format!(
+                "invalid restart policy '{}', expected no, always, on-failure[:max_retries] or unless-stopped", value
+            )),
+        }
+    }
+}
+
+impl From<RestartPolicy> for String {
+    fn from(policy: RestartPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+/// Container healthcheck; unset timings use Podman's defaults
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Healthcheck {
+    /// Command run with the container's shell; exit code 0 means healthy
+    command: String,
+    /// Time between checks (e.g., "30s")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    /// Time after which a check counts as failed (e.g., "5s")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>,
+    /// Consecutive failures before the container is unhealthy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+    /// Grace period after start during which failures are not counted (e.g., "10s")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_period: Option<String>,
+}
+
+/// Readiness check run from the host; exactly one field is set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Probe {
+    /// Shell command that exits with 0 once the container is ready (e.g., "pg_isready -h 127.0.0.1")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    /// Plain HTTP URL that answers with a 2xx or 3xx status once the container is ready
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http: Option<String>,
+}
+
+/// Container resource limits
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+struct Resources {
+    /// Memory limit (e.g., "512m", "1g")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory: Option<String>,
+    /// Number of CPUs (e.g., 1.5)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cpus: Option<f64>,
+    /// Maximum number of processes (-1 for unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pids_limit: Option<i64>,
 }
 
 /// Pod configuration structure
@@ -72,12 +567,161 @@ struct Config {
     data_path: String,
     /// List of pods to manage
     pods: Vec<Pod>,
-    /// Optional private registry URL
+    /// Named volumes used by volume mounts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    volumes: Vec<Volume>,
+    /// Podman secrets used by containers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    secrets: Vec<Secret>,
+    /// Registries to log into and pull from, matched against the host of each image
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    registries: Vec<Registry>,
+    /// Registries searched, in order, for image names without a registry host
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unqualified_search_registries: Vec<String>,
+    /// Optional private registry URL, shorthand for a single entry in `registries`
     private_registry: Option<String>,
     /// Optional registry username
     registry_username: Option<String>,
-    /// Optional registry password
+    /// Deprecated plain text registry password, use `registry_password_from`
     registry_password: Option<String>,
+    /// Where the registry password is read from when logging in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    registry_password_from: Option<PasswordSource>,
+}
+
+impl Config {
+    /// Returns the `registries` followed by the one described by `private_registry`, if set
+    fn registries(&self) -> Vec<Registry> {
+        let mut registries = self.registries.clone();
+        if let Some(host) = &self.private_registry {
+            registries.push(Registry {
+                host: host.clone(),
+                username: self.registry_username.clone(),
+                password_from: self.registry_password_from.clone(),
+                password: self.registry_password.clone(),
+                tls_verify: None,
+                mirrors: Vec::new(),
+                blocked: false,
+            });
+        }
+        registries
+    }
+}
+
+/// Container registry with its credentials and pull settings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Registry {
+    /// Registry host with an optional port, as it appears in image names (e.g., "ghcr.io", "harbor.example.com:5000")
+    host: String,
+    /// Optional username; logging in also needs `password_from`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    /// Where the password or token is read from when logging in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password_from: Option<PasswordSource>,
+    /// Plain text password of the deprecated `registry_password`; never read from `registries`
+    #[serde(skip)]
+    password: Option<String>,
+    /// Whether to verify the registry's TLS certificate (default: true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tls_verify: Option<bool>,
+    /// Mirrors tried in order before the registry itself (e.g., "mirror.example.com/harbor")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mirrors: Vec<String>,
+    /// Forbid pulling from the registry on this host
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    blocked: bool,
+}
+
+impl Registry {
+    fn tls_verify(&self) -> bool {
+        self.tls_verify != Some(false)
+    }
+}
+
+/// Returns the registry host of an image, `docker.io` for images without one
+fn image_registry(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains(['.', ':']) || first == "localhost" => first,
+        _ => "docker.io",
+    }
+}
+
+/// Returns the image with its registry host, since Podman tags short names as localhost/<name>
+fn qualified_image(image: &str) -> String {
+    if image_registry(image) == "docker.io" { mirror_image("docker.io", image) } else { image.to_string() }
+}
+
+/// Rewrites an image to be pulled from a mirror of its registry
+fn mirror_image(mirror: &str, image: &str) -> String {
+    let registry = image_registry(image);
+    let path = image.strip_prefix(registry).and_then(|rest| rest.strip_prefix('/')).unwrap_or(image);
+    let mirror = mirror.trim_end_matches('/');
+    // Docker Hub images without a namespace live under library/
+    if registry == "docker.io" && !path.contains('/') {
+        format!("{}/library/{}", mirror, path)
+    } else {
+        format!("{}/{}", mirror, path)
+    }
+}
+
+/// Where a registry password is read from at login time; exactly one field is set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct PasswordSource {
+    /// Environment variable holding the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<String>,
+    /// File holding the password, relative to the config file's directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// Shell command printing the password (e.g., "pass show registry.example.com")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+}
+
+impl PasswordSource {
+    /// Reads the password, without the trailing newline files and commands usually end with
+    fn read(&self, config_dir: &Path) -> AppResult<String> {
+        let password = match (&self.env, &self.file, &self.command) {
+            (Some(variable), _, _) => env::var(variable)
+                .map_err(|_| format!("Environment variable {} is not set", variable))?,
+            (None, Some(file), _) => {
+                let path = config_dir.join(file);
+                fs::read_to_string(&path)
+                    .map_err(|e| AppError::context(format!("Failed to read password file {}", path.display()), e))?
+            }
+            (None, None, Some(command)) => {
+                let output = Command::new("sh")
+                    .args(["-c", command])
+                    .stderr(std::process::Stdio::inherit())
+                    .output()
+                    .map_err(|e| AppError::context("Failed to run password command", e))?;
+                if !output.status.success() {
+                    return Err(AppError::CommandFailed { command: command.clone(), code: output.status.code() });
+                }
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+            (None, None, None) => return Err("No registry password source configured".into()),
+        };
+        let password = password.trim_end_matches(['\n', '\r']);
+        if password.is_empty() {
+            return Err("Registry password is empty".into());
+        }
+        Ok(password.to_string())
+    }
+}
+
+/// Returns true if the process runs with root privileges
+fn is_root() -> bool {
+    fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status.lines()
+                .find(|line| line.starts_with("Uid:"))
+                .and_then(|line| line.split_whitespace().nth(2).map(|euid| euid == "0"))
+        })
+        .unwrap_or(false)
 }
 
 fn detect_os() -> OSType {
@@ -102,35 +746,30 @@ fn detect_os() -> OSType {
     }
 }
 
-fn is_podman_installed() -> bool {
-    execute_podman_command(&["--version"])
-        .unwrap_or(false)
-}
-
-fn install_podman(os_type: &OSType) -> AppResult<()> {
+fn install_podman(os_type: &OSType, dry_run: bool) -> AppResult<()> {
     println!("Installing podman for {:?}...", os_type);
     
     let success = match os_type {
         OSType::Ubuntu | OSType::Debian => {
             // First update package list
-            if !execute_system_command("sudo", &["apt", "update"])? {
-                return Err("Failed to update package list".into());
+            if !execute_system_command("sudo", &["apt", "update"], dry_run)? {
+                return Err(AppError::PodmanMissing("Failed to update package list".to_string()));
             }
             
             // Then install podman
-            execute_system_command("sudo", &["apt", "install", "-y", "podman"])?
+            execute_system_command("sudo", &["apt", "install", "-y", "podman"], dry_run)?
         }
         OSType::Fedora => {
-            execute_system_command("sudo", &["dnf", "install", "-y", "podman"])?
+            execute_system_command("sudo", &["dnf", "install", "-y", "podman"], dry_run)?
         }
         OSType::RedHat => {
-            execute_system_command("sudo", &["yum", "install", "-y", "podman"])?
+            execute_system_command("sudo", &["yum", "install", "-y", "podman"], dry_run)?
         }
         OSType::ArchLinux => {
-            execute_system_command("sudo", &["pacman", "-S", "--noconfirm", "podman"])?
+            execute_system_command("sudo", &["pacman", "-S", "--noconfirm", "podman"], dry_run)?
         }
         OSType::Unknown => {
-            return Err("Unsupported OS for automatic podman installation".into());
+            return Err(AppError::PodmanMissing("Unsupported OS for automatic podman installation".to_string()));
         }
     };
     
@@ -138,27 +777,32 @@ fn install_podman(os_type: &OSType) -> AppResult<()> {
         println!("Podman installed successfully!");
         Ok(())
     } else {
-        Err("Failed to install podman".into())
+        Err(AppError::PodmanMissing("Failed to install podman".to_string()))
     }
 }
 
-fn update_config_podman_status(config_path: &str, mut config: Config) -> AppResult<()> {
-    config.is_podman_installed = true;
-    let updated_yaml = serde_yaml::to_string(&config)?;
-    fs::write(config_path, updated_yaml)?;
+/// Sets `is_podman_installed` in the config file, leaving variables uninterpolated
+/// and env files unmerged
+fn update_config_podman_status(config_path: &str, dry_run: bool) -> AppResult<()> {
+    let mut document: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(config_path)?)?;
+    if let Some(mapping) = document.as_mapping_mut() {
+        mapping.insert("is_podman_installed".into(), true.into());
+    }
+    let updated_yaml = serde_yaml::to_string(&document)?;
+    write_file(Path::new(config_path), &updated_yaml, dry_run)?;
     println!("Config file updated: is_podman_installed set to true");
     Ok(())
 }
 
-fn check_and_install_podman(config_path: &str, config: &mut Config) -> AppResult<()> {
+fn check_and_install_podman(rt: &dyn ContainerRuntime, config_path: &str, config: &mut Config, dry_run: bool) -> AppResult<()> {
     if config.is_podman_installed {
         println!("Config indicates podman is installed, skipping installation check.");
         return Ok(());
     }
     
-    if is_podman_installed() {
+    if rt.is_available() {
         println!("Podman is already installed.");
-        update_config_podman_status(config_path, config.clone())?;
+        update_config_podman_status(config_path, dry_run)?;
         config.is_podman_installed = true;
         return Ok(());
     }
@@ -167,78 +811,243 @@ fn check_and_install_podman(config_path: &str, config: &mut Config) -> AppResult
     let os_type = detect_os();
     println!("Detected OS: {:?}", os_type);
     
-    install_podman(&os_type)?;
-    update_config_podman_status(config_path, config.clone())?;
+    install_podman(&os_type, dry_run)?;
+    update_config_podman_status(config_path, dry_run)?;
     config.is_podman_installed = true;
     Ok(())
 }
 
-fn load_config(config_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
+fn load_config(config_path: &str) -> AppResult<Config> {
     println!("Loading configuration from {}...", config_path);
-    let config_content = fs::read_to_string(config_path)?;
-    let config: Config = serde_yaml::from_str(&config_content)?;
+    let config = read_config(config_path)?;
+    if config.registry_password.is_some() {
+        eprintln!("Warning: registry_password is deprecated and keeps the password in plain text; use registry_password_from with env, file or command instead");
+    }
     println!("Configuration loaded successfully.");
     Ok(config)
 }
 
-fn check_and_create_data_path(data_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Reads, parses and validates the config file without printing progress messages
+fn read_config(config_path: &str) -> AppResult<Config> {
+    let config_content = fs::read_to_string(config_path)
+        .map_err(|e| AppError::context(format!("Failed to read config file: {}", config_path), e))?;
+    // Checked as written first, so that type errors point at a line and column
+    serde_yaml::from_str::<Config>(&config_content)
+        .map_err(|e| AppError::config_invalid(config_path, e))?;
+    let mut document: serde_yaml::Value = serde_yaml::from_str(&config_content)
+        .map_err(|e| AppError::config_invalid(config_path, e))?;
+    interpolate::interpolate(&mut document, &|name| env::var(name).ok())
+        .map_err(|names| AppError::config_invalid(config_path, format!("Unresolved variables: {}", names.join(", "))))?;
+    let mut config: Config = serde_yaml::from_value(document)
+        .map_err(|e| AppError::config_invalid(config_path, e))?;
+    load_env_files(&mut config, config_dir(config_path))?;
+    register_secret_values(&config, config_dir(config_path));
+    
+    let diagnostics = validate::validate(&config, &config_content);
+    if !diagnostics.is_empty() {
+        return Err(AppError::config_invalid(config_path, validate::report(config_path, &diagnostics)));
+    }
+    Ok(config)
+}
+
+/// Merges each container's env files into its `env_vars`; later files override earlier
+/// ones and variables set in the config override both
+fn load_env_files(config: &mut Config, config_dir: &Path) -> AppResult<()> {
+    for container in config.pods.iter_mut().flat_map(|pod| pod.containers.iter_mut()) {
+        let mut from_files = HashMap::new();
+        for env_file in &container.env_file {
+            let path = config_dir.join(env_file);
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AppError::context(format!("Failed to read env file {} of container {}", path.display(), container.name), e)
+            })?;
+            for (index, line) in content.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let line = line.strip_prefix("export ").unwrap_or(line);
+                let Some((key, value)) = line.split_once('=') else {
+                    return Err(format!("Invalid line {} in env file {}: expected KEY=VALUE", index + 1, path.display()).into());
+                };
+                let value = value.trim();
+                let unquoted = ['"', '\'']
+                    .iter()
+                    .find_map(|quote| value.strip_prefix(*quote).and_then(|inner| inner.strip_suffix(*quote)))
+                    .unwrap_or(value);
+                from_files.insert(key.trim().to_string(), unquoted.to_string());
+            }
+        }
+        for (key, value) in from_files {
+            container.env_vars.entry(key).or_insert(value);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the value of a secret from its file or environment variable
+fn secret_value(secret: &Secret, config_dir: &Path) -> AppResult<String> {
+    match (&secret.file, &secret.env) {
+        (Some(file), _) => {
+            let path = config_dir.join(file);
+            fs::read_to_string(&path)
+                .map_err(|e| AppError::context(format!("Failed to read secret {} from {}", secret.name, path.display()), e))
+        }
+        (None, Some(variable)) => env::var(variable)
+            .map_err(|_| format!("Failed to read secret {}: environment variable {} is not set", secret.name, variable).into()),
+        (None, None) => Err(format!("Secret {} has neither a file nor an env source", secret.name).into()),
+    }
+}
+
+/// Registers secret values and the registry password for redaction, skipping
+/// secrets that cannot be read yet (setup reports those when creating them)
+fn register_secret_values(config: &Config, config_dir: &Path) {
+    for secret in &config.secrets {
+        if let Ok(value) = secret_value(secret, config_dir) {
+            redact::register(&value);
+        }
+    }
+    if let Some(password) = &config.registry_password {
+        redact::register(password);
+    }
+}
+
+fn check_and_create_data_path(data_path: &str, dry_run: bool) -> AppResult<()> {
     println!("Checking data path: {}", data_path);
     
     if Path::new(data_path).exists() {
         println!("Data path already exists: {}", data_path);
     } else {
         println!("Data path does not exist, creating: {}", data_path);
-        fs::create_dir_all(data_path)?;
+        create_dir(Path::new(data_path), dry_run)?;
         println!("Data path created successfully: {}", data_path);
     }
     
     Ok(())
 }
 
-fn create_mount_paths(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+/// Determine if a mount host path without an explicit type should be a file or directory
+/// Names with an extension ("nginx.ini") and dotfiles (".env") are files, except
+/// ".d" include directories ("conf.d")
+fn is_file_mount(local_path: &str) -> bool {
+    let name = local_path.rsplit('/').next().unwrap_or_default();
+    match name.rsplit_once('.') {
+        Some((_, "d")) | None => false,
+        Some((_, extension)) => !extension.is_empty(),
+    }
+}
+
+/// Applies the configured mode and owner of a bind mount to its host path.
+///
+/// Rootless containers run in a user namespace, so container UIDs are mapped
+/// to subordinate host UIDs and the path is chowned through `podman unshare`.
+fn set_mount_permissions(spec: &MountSpec, path: &Path, dry_run: bool) -> AppResult<()> {
+    if let Some(bits) = spec.mode_bits() {
+        if dry_run {
+            println!("[dry-run] Would set mode {:04o}: {}", bits, path.display());
+        } else {
+            println!("Setting mode {:04o}: {}", bits, path.display());
+            fs::set_permissions(path, fs::Permissions::from_mode(bits))?;
+        }
+    }
+    
+    if let Some(owner) = &spec.owner {
+        let path_arg = path.to_string_lossy();
+        if !dry_run {
+            println!("Setting owner {}: {}", owner, path.display());
+        }
+        let changed = if is_root() {
+            execute_system_command("chown", &[owner, &path_arg], dry_run)?
+        } else {
+            execute_system_command("podman", &["unshare", "chown", owner, &path_arg], dry_run)?
+        };
+        if !changed {
+            return Err(format!("Failed to change owner of {} to {}", path.display(), owner).into());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Directory containing the config file, which relative template paths are resolved against
+fn config_dir(config_path: &str) -> &Path {
+    Path::new(config_path).parent().unwrap_or(Path::new("."))
+}
+
+/// Renders the template or inline content a mount's host file is seeded from
+fn mount_seed(config: &Config, config_dir: &Path, pod: &Pod, container: &Container, spec: &MountSpec) -> AppResult<Option<String>> {
+    let source = match (&spec.template, &spec.content) {
+        (Some(template), _) => {
+            let template_path = config_dir.join(template);
+            fs::read_to_string(&template_path)
+                .map_err(|e| AppError::context(format!("Failed to read template {}", template_path.display()), e))?
+        }
+        (None, Some(content)) => content.clone(),
+        (None, None) => return Ok(None),
+    };
+    
+    let variables = template::variables(config, pod, container);
+    template::render(&source, &variables)
+        .map(Some)
+        .map_err(|e| AppError::context(format!("Failed to render template for mount {} of container {}", spec.target, container.name), e))
+}
+
+/// Creates the host paths of bind mounts; templates are resolved against `config_dir`
+fn create_mount_paths(config: &Config, config_dir: &Path, dry_run: bool) -> AppResult<()> {
     println!("Creating mount paths within data directory...");
     
     for pod in &config.pods {
         for container in &pod.containers {
-            for mount in &container.mounts {
-                // Parse mount string (format: "local_path:container_path")
-                if let Some((local_path, _)) = mount.split_once(':') {
-                    // Create full path within data directory
-                    let full_path = format!("{}{}", config.data_path, local_path);
-                    let path = Path::new(&full_path);
-                    
-                    // Determine if this should be a file or directory
-                    // If the path ends with a file extension or contains common file patterns, treat as file
-                    let is_file = local_path.contains('.') && 
-                        (local_path.ends_with(".conf") || local_path.ends_with(".log") || 
-                         local_path.ends_with(".txt") || local_path.ends_with(".json") ||
-                         local_path.ends_with(".yaml") || local_path.ends_with(".yml"));
-                    
-                    if is_file {
-                        // Create parent directory first
-                        if let Some(parent) = path.parent() {
-                            if !parent.exists() {
-                                println!("Creating directory for file: {}", parent.display());
-                                fs::create_dir_all(parent)?;
+            // Named volumes and tmpfs mounts are managed by Podman
+            let binds = container.mounts.iter()
+                .filter_map(Mount::spec)
+                .filter(|spec| spec.kind.is_bind() && spec.source.is_some());
+            for spec in binds {
+                // Create full path within data directory
+                let full_path = spec.host_path(&config.data_path);
+                let path = Path::new(&full_path);
+                let seed = mount_seed(config, config_dir, pod, container, &spec)?;
+                
+                if path.exists() {
+                    // An existing path keeps its type; only an explicit type can contradict it
+                    match spec.kind {
+                        MountType::File if path.is_dir() => {
+                            println!("Warning: {} is a directory but the mount has type file", full_path);
+                        }
+                        MountType::Dir if !path.is_dir() => {
+                            println!("Warning: {} is not a directory but the mount has type dir", full_path);
+                        }
+                        _ if path.is_dir() => println!("Directory already exists: {}", full_path),
+                        _ => match &seed {
+                            Some(contents) if spec.overwrite && fs::read_to_string(path).ok().as_ref() != Some(contents) => {
+                                println!("Updating file from template: {}", full_path);
+                                write_file(path, contents, dry_run)?;
                             }
+                            _ => println!("File already exists: {}", full_path),
+                        },
+                    }
+                } else if spec.is_file() {
+                    // Create parent directory first
+                    if let Some(parent) = path.parent()
+                        && !parent.exists()
+                    {
+                        println!("Creating directory for file: {}", parent.display());
+                        create_dir(parent, dry_run)?;
+                    }
+                    match &seed {
+                        Some(contents) => {
+                            println!("Creating file from template: {}", full_path);
+                            write_file(path, contents, dry_run)?;
                         }
-                        // Create empty file if it doesn't exist
-                        if !path.exists() {
+                        None => {
                             println!("Creating empty file: {}", full_path);
-                            fs::write(&full_path, "")?;
-                        } else {
-                            println!("File already exists: {}", full_path);
-                        }
-                    } else {
-                        // Create directory
-                        if !path.exists() {
-                            println!("Creating directory: {}", full_path);
-                            fs::create_dir_all(&full_path)?;
-                        } else {
-                            println!("Directory already exists: {}", full_path);
+                            write_file(path, "", dry_run)?;
                         }
                     }
+                } else {
+                    println!("Creating directory: {}", full_path);
+                    create_dir(path, dry_run)?;
                 }
+                set_mount_permissions(&spec, path, dry_run)?;
             }
         }
     }
@@ -247,92 +1056,237 @@ fn create_mount_paths(config: &Config) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
-fn is_logged_into_registry(registry: &str) -> bool {
-    match Command::new("podman")
-        .args(&["login", "--get-login", registry])
-        .output()
-    {
-        Ok(output) => output.status.success(),
-        Err(_) => false,
+/// Creates the Podman secrets that don't exist yet
+fn create_secrets(rt: &dyn ContainerRuntime, config: &Config, config_dir: &Path) -> AppResult<()> {
+    if config.secrets.is_empty() {
+        println!("No secrets configured.");
+        return Ok(());
+    }
+    
+    for secret in &config.secrets {
+        if rt.secret_exists(&secret.name) {
+            println!("Secret '{}' already exists", secret.name);
+        } else {
+            println!("Creating secret: {}", secret.name);
+            rt.create_secret(&secret.name, &secret_value(secret, config_dir)?)?;
+            println!("Secret '{}' created successfully", secret.name);
+        }
     }
+    
+    Ok(())
 }
 
-fn login_to_registry(registry: &str, username: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Logging into private registry: {}", registry);
-    
-    let status = Command::new("podman")
-        .args(&["login", registry, "-u", username, "-p", password])
-        .status()?;
+/// Creates the named volumes that don't exist yet
+fn create_volumes(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<()> {
+    if config.volumes.is_empty() {
+        println!("No named volumes configured.");
+        return Ok(());
+    }
     
-    if status.success() {
-        println!("Successfully logged into registry: {}", registry);
-        Ok(())
-    } else {
-        Err(format!("Failed to login to registry: {}", registry).into())
+    for volume in &config.volumes {
+        if rt.volume_exists(&volume.name) {
+            println!("Volume '{}' already exists", volume.name);
+        } else {
+            println!("Creating volume: {}", volume.name);
+            rt.create_volume(volume)?;
+            println!("Volume '{}' created successfully", volume.name);
+        }
+    }
+    Ok(())
+}
+
+fn login_to_registry(rt: &dyn ContainerRuntime, registry: &Registry, username: &str, password: &str) -> AppResult<()> {
+    println!("Logging into private registry: {}", registry.host);
+    
+    rt.login(&registry.host, username, password, registry.tls_verify()).map_err(|e| match e {
+        AppError::RegistryLogin { .. } => e,
+        e => AppError::registry_login(&registry.host, e),
+    })?;
+    println!("Successfully logged into registry: {}", registry.host);
+    Ok(())
+}
+
+/// Reads the registry password from `password_from`, falling back to the
+/// deprecated plain text `registry_password`
+fn registry_password(registry: &Registry, config_dir: &Path) -> AppResult<Option<String>> {
+    match (&registry.password_from, &registry.password) {
+        (Some(source), _) => source.read(config_dir).map(Some),
+        (None, password) => Ok(password.clone()),
     }
 }
 
-fn configure_private_registry(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(registry) = &config.private_registry {
-        println!("Private registry configured: {}", registry);
+fn configure_registry(rt: &dyn ContainerRuntime, registry: &Registry, config_dir: &Path) -> AppResult<()> {
+    println!("Private registry configured: {}", registry.host);
+    
+    let has_password = registry.password_from.is_some() || registry.password.is_some();
+    if let Some(username) = &registry.username
+        && has_password
+    {
+        println!("Registry authentication details available for user: {}", username);
         
-        if let (Some(username), Some(password)) = (&config.registry_username, &config.registry_password) {
-            println!("Registry authentication details available for user: {}", username);
-            
-            // Check if already logged in
-            if is_logged_into_registry(registry) {
-                println!("Already logged into registry: {}", registry);
-            } else {
-                println!("Not logged into registry, attempting login...");
-                login_to_registry(registry, username, password)?;
-            }
+        // Check if already logged in, so the password is only read when needed
+        if rt.is_logged_in(&registry.host) {
+            println!("Already logged into registry: {}", registry.host);
         } else {
-            println!("Note: Use 'podman login {}' to authenticate with the registry when needed.", registry);
+            println!("Not logged into registry, attempting login...");
+            let password = registry_password(registry, config_dir)
+                .map_err(|e| AppError::registry_login(&registry.host, e))?
+                .unwrap_or_default();
+            redact::register(&password);
+            login_to_registry(rt, registry, username, &password)?;
         }
     } else {
-        println!("No private registry configured.");
+        println!("Note: Use 'podman login {}' to authenticate with the registry when needed.", registry.host);
     }
     Ok(())
 }
 
-fn pod_exists(pod_name: &str) -> bool {
-    match Command::new("podman")
-        .args(&["pod", "exists", pod_name])
-        .status()
-    {
-        Ok(status) => status.success(),
-        Err(_) => false,
+/// Logs into the configured registries, only warning when a login fails.
+///
+/// Images may already be present locally or be pullable anonymously, so a pull
+/// that really needs the login fails later with its own error.
+fn login_or_warn(rt: &dyn ContainerRuntime, config: &Config, config_dir: &Path) {
+    if let Err(e) = configure_private_registry(rt, config, config_dir) {
+        eprintln!("Warning: Error configuring private registry: {}", redact::redact(&e.to_string()));
     }
 }
 
-fn generate_pod_command(pod: &Pod) -> String {
-    let mut cmd = format!("podman pod create --name {}", pod.name);
+/// Logs into every configured registry that a container image is pulled from.
+///
+/// A failed login is reported and the remaining registries are still tried
+/// before the failures are returned as a registry login error.
+fn configure_private_registry(rt: &dyn ContainerRuntime, config: &Config, config_dir: &Path) -> AppResult<()> {
+    let registries = config.registries();
+    if registries.is_empty() {
+        println!("No private registry configured.");
+        return Ok(());
+    }
     
-    // Collect all unique ports from containers in the pod
-    let mut all_ports = HashSet::new();
-    for container in &pod.containers {
-        for port in &container.ports {
-            all_ports.insert(port);
+    let used: HashSet<&str> = config.pods.iter()
+        .flat_map(|pod| &pod.containers)
+        .map(|container| image_registry(&container.image))
+        .collect();
+    let mut failures = Vec::new();
+    for registry in &registries {
+        if registry.blocked {
+            println!("Skipping blocked registry {}", registry.host);
+            continue;
+        }
+        if !used.contains(registry.host.as_str()) {
+            println!("Skipping registry {}: no container image is pulled from it", registry.host);
+            continue;
+        }
+        if let Err(e) = configure_registry(rt, registry, config_dir) {
+            let reason = std::error::Error::source(&e).map_or_else(|| e.to_string(), ToString::to_string);
+            eprintln!("Error configuring registry {}: {}", registry.host, redact::redact(&reason));
+            failures.push((registry.host.as_str(), e));
         }
     }
     
-    // Add port mappings to pod creation
-    for port in &all_ports {
-        cmd.push_str(&format!(" -p {}", port));
+    let hosts: Vec<&str> = failures.iter().map(|(host, _)| *host).collect();
+    let mut errors = failures.into_iter().map(|(_, e)| e);
+    match (errors.next(), hosts.len()) {
+        (None, _) => Ok(()),
+        (Some(e), 1) => Err(e),
+        (Some(e), count) => Err(AppError::context(format!("Failed to log into {} registries: {}", count, hosts.join(", ")), e)),
     }
-    
-    cmd
 }
 
-fn generate_container_command(pod_name: &str, container: &Container, data_path: &str) -> String {
-    let mut args = vec!["podman".to_string()];
-    args.extend(build_container_args(pod_name, container, data_path));
-    args.join(" ")
-}
-
-fn display_pod_commands(config: &Config) {
-    println!("\n=== Commands to Create Pods and Containers ===");
-    
+/// Writes the registries.conf drop-in, removing a previous one once the config declares nothing
+/// for it, and warns about image names that depend on the host's search registries
+fn write_registries_conf(config: &Config, dry_run: bool) -> AppResult<()> {
+    let dir = registries_conf::drop_in_dir(is_root());
+    let path = dir.join(registries_conf::file_name(config));
+    
+    match registries_conf::generate(config) {
+        Some(contents) if fs::read_to_string(&path).is_ok_and(|existing| existing == contents) => {
+            println!("Registry configuration is up to date: {}", path.display());
+        }
+        Some(contents) => {
+            if !dir.exists() {
+                println!("Creating directory: {}", dir.display());
+                create_dir(&dir, dry_run)?;
+            }
+            println!("Writing registry configuration: {}", path.display());
+            write_file(&path, &contents, dry_run)?;
+        }
+        None if path.exists() => {
+            println!("Removing registry configuration: {}", path.display());
+            if dry_run {
+                println!("[dry-run] Would remove file: {}", path.display());
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        None => println!("No registry configuration to write."),
+    }
+    
+    let search = if config.unqualified_search_registries.is_empty() {
+        "the host's unqualified-search-registries".to_string()
+    } else {
+        config.unqualified_search_registries.join(", ")
+    };
+    for pod in &config.pods {
+        for container in pod.containers.iter().filter(|c| registries_conf::is_short_name(&c.image)) {
+            eprintln!(
+                "Warning: image '{}' of container '{}' has no registry host and is resolved through {}; use the fully qualified name, e.g. {}",
+                container.image, container.name, search, mirror_image("docker.io", &container.image)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Collects the unique port mappings of all containers in a pod, in config order
+fn collect_pod_ports(pod: &Pod) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut ports = Vec::new();
+    for container in &pod.containers {
+        for port in &container.ports {
+            if seen.insert(port) {
+                ports.push(port.clone());
+            }
+        }
+    }
+    ports
+}
+
+fn generate_pod_command(pod: &Pod) -> String {
+    let mut cmd = format!("podman pod create --name {}", pod.name);
+    
+    // Add port mappings to pod creation
+    for port in collect_pod_ports(pod) {
+        cmd.push_str(&format!(" -p {}", port));
+    }
+    
+    cmd
+}
+
+/// Quotes an argument for a POSIX shell if it contains anything but safe characters
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Renders an argument vector as a command line that can be pasted into a shell,
+/// with secret values masked
+fn shell_join(args: &[String]) -> String {
+    redact::redact(&args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "))
+}
+
+fn generate_container_command(pod_name: &str, container: &Container, data_path: &str) -> String {
+    let mut args = vec!["podman".to_string()];
+    args.extend(build_container_args(pod_name, container, data_path));
+    shell_join(&args)
+}
+
+fn display_pod_commands(config: &Config) {
+    println!("\n=== Commands to Create Pods and Containers ===");
+    
     for pod in &config.pods {
         println!("\n--- Pod: {} ---", pod.name);
         println!("Pod creation command:");
@@ -347,47 +1301,20 @@ fn display_pod_commands(config: &Config) {
     }
 }
 
-fn create_pod(pod: &Pod, data_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn create_pod(rt: &dyn ContainerRuntime, pod: &Pod, data_path: &str) -> AppResult<()> {
     println!("Creating pod: {}", pod.name);
     
     // Display the command that will be executed
     println!("Executing command: {}", generate_pod_command(pod));
     
     // Create the pod first with port mappings
-    let mut pod_args = vec!["pod", "create", "--name", &pod.name];
-    
-    // Collect all unique ports from containers in the pod
-    let mut all_ports = std::collections::HashSet::new();
-    for container in &pod.containers {
-        for port in &container.ports {
-            all_ports.insert(port.clone());
-        }
-    }
-    
-    // Add port mappings to pod creation args
-    let mut port_args = Vec::new();
-    for port in all_ports {
-        port_args.push("-p".to_string());
-        port_args.push(port);
-    }
-    
-    // Convert port args to string refs and combine with pod_args
-    let port_refs: Vec<&str> = port_args.iter().map(|s| s.as_str()).collect();
-    pod_args.extend(port_refs);
-    
-    let pod_status = Command::new("podman")
-        .args(&pod_args)
-        .status()?;
-    
-    if !pod_status.success() {
-        return Err(format!("Failed to create pod: {}", pod.name).into());
-    }
+    rt.create_pod(&pod.name, &collect_pod_ports(pod))?;
     
     println!("Pod '{}' created successfully", pod.name);
     
     // Create containers in the pod
     for container in &pod.containers {
-        create_container_in_pod(&pod.name, container, data_path)?;
+        create_container_in_pod(rt, &pod.name, container, data_path)?;
     }
     
     Ok(())
@@ -405,54 +1332,113 @@ fn build_container_args(pod_name: &str, container: &Container, data_path: &str)
         args.push(format!("{}={}", key, value));
     }
     
-    // Add mount points with data_path prefix
-    for mount in &container.mounts {
-        args.push("-v".to_string());
-        // Parse mount string and prefix host path with data_path
-        if let Some((host_path, container_path)) = mount.split_once(':') {
-            args.push(format!("{}{}:{}", data_path, host_path, container_path));
-        } else {
-            args.push(mount.clone());
+    // Add mount points; bind mount host paths are prefixed with data_path
+    for spec in container.mounts.iter().filter_map(Mount::spec) {
+        let source = match spec.kind {
+            MountType::Bind | MountType::File | MountType::Dir => Some(spec.host_path(data_path)),
+            MountType::Volume => spec.source.clone(),
+            MountType::Tmpfs => None,
+        };
+        let mut value = match source {
+            Some(source) => {
+                args.push("-v".to_string());
+                format!("{}:{}", source, spec.target)
+            }
+            None => {
+                args.push("--tmpfs".to_string());
+                spec.target.clone()
+            }
+        };
+        let options = spec.podman_options();
+        if !options.is_empty() {
+            value.push_str(&format!(":{}", options.join(",")));
+        }
+        args.push(value);
+    }
+    
+    for secret in &container.secrets {
+        args.push("--secret".to_string());
+        args.push(secret.spec().podman_arg());
+    }
+    
+    if let Some(policy) = &container.restart_policy {
+        args.push("--restart".to_string());
+        args.push(policy.to_string());
+    }
+    
+    if let Some(health) = &container.healthcheck {
+        args.push("--health-cmd".to_string());
+        args.push(health.command.clone());
+        let options = [
+            ("--health-interval", health.interval.clone()),
+            ("--health-timeout", health.timeout.clone()),
+            ("--health-retries", health.retries.map(|retries| retries.to_string())),
+            ("--health-start-period", health.start_period.clone()),
+        ];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value);
+            }
+        }
+    }
+    
+    if let Some(resources) = &container.resources {
+        let limits = [
+            ("--memory", resources.memory.clone()),
+            ("--cpus", resources.cpus.map(|cpus| cpus.to_string())),
+            ("--pids-limit", resources.pids_limit.map(|limit| limit.to_string())),
+        ];
+        for (flag, value) in limits {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value);
+            }
+        }
+    }
+    
+    let overrides = [
+        ("--entrypoint", container.entrypoint_arg()),
+        ("--workdir", container.workdir.clone()),
+        ("--user", container.user.clone()),
+    ];
+    for (flag, value) in overrides {
+        if let Some(value) = value {
+            args.push(flag.to_string());
+            args.push(value);
         }
     }
     
     // Use the explicit image name from config
     args.push(container.image.clone());
+    
+    // Command and arguments go after the image, one argv element each
+    if let Some(command_line) = container.command_line() {
+        args.extend(command_line);
+    }
     args
 }
 
-fn create_container_in_pod(pod_name: &str, container: &Container, data_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn create_container_in_pod(rt: &dyn ContainerRuntime, pod_name: &str, container: &Container, data_path: &str) -> AppResult<()> {
     println!("Creating container '{}' in pod '{}'", container.name, pod_name);
     
-    let args = build_container_args(pod_name, container, data_path);
-    
     // Display the command that will be executed
-    println!("Executing command: podman {}", args.join(" "));
-    
-    // Convert to string refs for Command
-    let string_args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    println!("Executing command: {}", generate_container_command(pod_name, container, data_path));
     
-    let status = Command::new("podman")
-        .args(&string_args)
-        .status()?;
-    
-    if status.success() {
-        println!("Container '{}' created successfully in pod '{}'", container.name, pod_name);
-        Ok(())
-    } else {
-        Err(format!("Failed to create container '{}' in pod '{}'", container.name, pod_name).into())
-    }
+    rt.create_container(pod_name, container, data_path)?;
+    println!("Container '{}' created successfully in pod '{}'", container.name, pod_name);
+    Ok(())
 }
 
-fn check_and_create_pods(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+fn check_and_create_pods(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<()> {
     println!("Checking and creating pods...");
     
     for pod in &config.pods {
-        if pod_exists(&pod.name) {
+        if rt.pod_exists(&pod.name) {
             println!("Pod '{}' already exists", pod.name);
         } else {
             println!("Pod '{}' does not exist, creating it...", pod.name);
-            create_pod(pod, &config.data_path)?;
+            create_pod(rt, pod, &config.data_path)?;
         }
     }
     
@@ -460,20 +1446,44 @@ fn check_and_create_pods(config: &Config) -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
-fn pull_images(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+/// Creates the pods that don't exist yet from generated Kubernetes manifests
+fn play_kube_pods(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<()> {
+    println!("Checking and creating pods with podman kube play...");
+    
+    let missing: Vec<&Pod> = config.pods.iter()
+        .filter(|pod| {
+            let exists = rt.pod_exists(&pod.name);
+            if exists {
+                println!("Pod '{}' already exists", pod.name);
+            }
+            !exists
+        })
+        .collect();
+    
+    if missing.is_empty() {
+        println!("All pods already exist");
+        return Ok(());
+    }
+    
+    let data_path = quadlet::absolute_data_path(&config.data_path)?;
+    let manifests = missing.iter()
+        .map(|pod| kube::kube_pod(config, pod, &data_path))
+        .collect::<AppResult<Vec<_>>>()?;
+    
+    println!("Playing manifest for pods: {}", missing.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", "));
+    rt.play_kube(&kube::to_yaml(&manifests)?)?;
+    
+    println!("All pods checked and created as needed");
+    Ok(())
+}
+
+fn pull_images(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<()> {
     println!("Pulling all required images...");
     
+    let registries = config.registries();
     for pod in &config.pods {
         for container in &pod.containers {
-            println!("Pulling image: {}", container.image);
-            
-            let status = Command::new("podman")
-                .args(&["pull", &container.image])
-                .status()?;
-            
-            if status.success() {
-                println!("Successfully pulled image: {}", container.image);
-            } else {
+            if pull_image(rt, &registries, &container.image).is_err() {
                 println!("Warning: Failed to pull image: {}", container.image);
             }
         }
@@ -483,7 +1493,7 @@ fn pull_images(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn start_pod(config: &Config, pod_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn start_pod(rt: &dyn ContainerRuntime, config: &Config, pod_name: &str) -> AppResult<()> {
     // Find the pod in config
     let pod = config.pods.iter().find(|p| p.name == pod_name);
     
@@ -491,16 +1501,9 @@ fn start_pod(config: &Config, pod_name: &str) -> Result<(), Box<dyn std::error::
         Some(_pod_config) => {
             println!("Starting pod: {}", pod_name);
             
-            let status = Command::new("podman")
-                .args(&["pod", "start", pod_name])
-                .status()?;
-            
-            if status.success() {
-                println!("Pod '{}' started successfully", pod_name);
-                Ok(())
-            } else {
-                Err(format!("Failed to start pod: {}", pod_name).into())
-            }
+            rt.start_pod(pod_name)?;
+            println!("Pod '{}' started successfully", pod_name);
+            Ok(())
         }
         None => {
             Err(format!("Pod '{}' not found in configuration", pod_name).into())
@@ -508,20 +1511,15 @@ fn start_pod(config: &Config, pod_name: &str) -> Result<(), Box<dyn std::error::
     }
 }
 
-fn start_all_pods(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+fn start_all_pods(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<()> {
     println!("Starting all pods...");
     
     for pod in &config.pods {
         println!("Starting pod: {}", pod.name);
         
-        let status = Command::new("podman")
-            .args(&["pod", "start", &pod.name])
-            .status()?;
-        
-        if status.success() {
-            println!("Pod '{}' started successfully", pod.name);
-        } else {
-            println!("Warning: Failed to start pod '{}' (may not exist or already running)", pod.name);
+        match rt.start_pod(&pod.name) {
+            Ok(()) => println!("Pod '{}' started successfully", pod.name),
+            Err(_) => println!("Warning: Failed to start pod '{}' (may not exist or already running)", pod.name),
         }
     }
     
@@ -529,7 +1527,7 @@ fn start_all_pods(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn stop_pod(config: &Config, pod_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn stop_pod(rt: &dyn ContainerRuntime, config: &Config, pod_name: &str) -> AppResult<()> {
     // Find the pod in config
     let pod = config.pods.iter().find(|p| p.name == pod_name);
     
@@ -537,16 +1535,9 @@ fn stop_pod(config: &Config, pod_name: &str) -> Result<(), Box<dyn std::error::E
         Some(_pod_config) => {
             println!("Stopping pod: {}", pod_name);
             
-            let status = Command::new("podman")
-                .args(&["pod", "stop", pod_name])
-                .status()?;
-            
-            if status.success() {
-                println!("Pod '{}' stopped successfully", pod_name);
-                Ok(())
-            } else {
-                Err(format!("Failed to stop pod: {}", pod_name).into())
-            }
+            rt.stop_pod(pod_name)?;
+            println!("Pod '{}' stopped successfully", pod_name);
+            Ok(())
         }
         None => {
             Err(format!("Pod '{}' not found in configuration", pod_name).into())
@@ -554,55 +1545,103 @@ fn stop_pod(config: &Config, pod_name: &str) -> Result<(), Box<dyn std::error::E
     }
 }
 
-fn get_container_current_image(container_name: &str) -> Option<String> {
-    match Command::new("podman")
-        .args(&["inspect", container_name, "--format", "{{.Config.Image}}"])
-        .output()
-    {
-        Ok(output) => {
-            if output.status.success() {
-                let image = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                Some(image)
-            } else {
-                None
-            }
+/// Normalizes image names by removing common registry prefixes for comparison
+fn normalize_image(img: &str) -> String {
+    if let Some(stripped) = img.strip_prefix("docker.io/library/") {
+        stripped.to_string()
+    } else if let Some(stripped) = img.strip_prefix("docker.io/") {
+        stripped.to_string()
+    } else {
+        img.to_string()
+    }
+}
+
+/// Pulls an image and returns what it resolved to, or None in dry-run mode where nothing is pulled
+fn resolve_image(rt: &dyn ContainerRuntime, registries: &[Registry], image: &str, dry_run: bool) -> AppResult<Option<runtime::ImageInfo>> {
+    pull_image(rt, registries, image)?;
+    if dry_run {
+        println!("[dry-run] Would record the digest of {}", image);
+        return Ok(None);
+    }
+    
+    match rt.inspect_image(image)? {
+        Some(info) => {
+            println!("Resolved {} to {}", image, info.digest);
+            Ok(Some(info))
         }
-        Err(_) => None,
+        None => Err(format!("Image {} was pulled but cannot be inspected", image).into()),
     }
 }
 
-fn container_exists(container_name: &str) -> bool {
-    match Command::new("podman")
-        .args(&["container", "exists", container_name])
-        .status()
+/// Points the local image at its locked digest, pulling it by digest unless it already
+/// is, and returns the image ID (None in dry-run mode)
+fn pull_locked(rt: &dyn ContainerRuntime, registries: &[Registry], image: &str, digest: &str, dry_run: bool) -> AppResult<Option<String>> {
+    let name = qualified_image(image);
+    if let Some(info) = rt.inspect_image(&name)?
+        && info.digest == digest
     {
-        Ok(status) => status.success(),
-        Err(_) => false,
+        println!("Image {} is at its locked digest {}", image, digest);
+        return Ok(Some(info.id));
+    }
+    
+    let pinned = lock::pinned_reference(&name, digest);
+    pull_image(rt, registries, &pinned)?;
+    rt.tag_image(&pinned, &name)?;
+    if dry_run {
+        return Ok(None);
+    }
+    Ok(rt.inspect_image(&name)?.map(|info| info.id))
+}
+
+/// Pins an image to its locked digest, first resolving and recording the digest if the
+/// image is not locked yet, and returns the ID of the local image (None in dry-run mode)
+fn lock_image(rt: &dyn ContainerRuntime, registries: &[Registry], lock: &mut LockFile, image: &str, dry_run: bool) -> AppResult<Option<String>> {
+    if let Some(digest) = lock.images.get(image).cloned() {
+        return pull_locked(rt, registries, image, &digest, dry_run);
+    }
+    
+    println!("Image {} is not locked yet", image);
+    let resolved = resolve_image(rt, registries, image, dry_run)?;
+    if let Some(info) = &resolved {
+        lock.images.insert(image.to_string(), info.digest.clone());
     }
+    Ok(resolved.map(|info| info.id))
 }
 
-fn needs_upgrade(container: &Container) -> bool {
-    if !container_exists(&container.name) {
+fn write_lock_file(path: &Path, lock: &LockFile, dry_run: bool) -> AppResult<()> {
+    println!("Writing lock file: {}", path.display());
+    write_file(path, &lock.to_yaml()?, dry_run)
+}
+
+/// Shortens an image ID the way `podman images` prints it
+fn short_id(id: &str) -> &str {
+    id.get(..12).unwrap_or(id)
+}
+
+/// Returns true if a container does not run the image with `image_id`, or, without an
+/// ID, if its image name differs from the config
+fn needs_upgrade(rt: &dyn ContainerRuntime, container: &Container, image_id: Option<&str>) -> bool {
+    if !rt.container_exists(&container.name) {
         println!("Container '{}' does not exist, no upgrade needed", container.name);
         return false;
     }
     
-    match get_container_current_image(&container.name) {
+    if let Some(image_id) = image_id
+        && let Ok(Some(info)) = rt.inspect_container(&container.name)
+        && normalize_image(&info.image) == normalize_image(&container.image)
+    {
+        if info.image_id == image_id {
+            println!("Container '{}' is already running the locked image: {}", container.name, container.image);
+            return false;
+        }
+        println!("Container '{}' needs upgrade: image ID {} differs from the locked {}", container.name, short_id(&info.image_id), short_id(image_id));
+        return true;
+    }
+    
+    match rt.container_image(&container.name) {
         Some(current_image) => {
             let expected_image = &container.image;
             
-            // Normalize image names by removing registry prefixes for comparison
-            let normalize_image = |img: &str| -> String {
-                // Remove common registry prefixes
-                if let Some(stripped) = img.strip_prefix("docker.io/library/") {
-                    stripped.to_string()
-                } else if let Some(stripped) = img.strip_prefix("docker.io/") {
-                    stripped.to_string()
-                } else {
-                    img.to_string()
-                }
-            };
-            
             let normalized_current = normalize_image(&current_image);
             let normalized_expected = normalize_image(expected_image);
             
@@ -623,71 +1662,290 @@ fn needs_upgrade(container: &Container) -> bool {
     }
 }
 
-fn stop_container(container_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn stop_container(rt: &dyn ContainerRuntime, container_name: &str) -> AppResult<()> {
     println!("Stopping container: {}", container_name);
     
-    let status = Command::new("podman")
-        .args(&["stop", container_name])
-        .status()?;
-    
-    if status.success() {
-        println!("Container '{}' stopped successfully", container_name);
-        Ok(())
-    } else {
-        Err(format!("Failed to stop container: {}", container_name).into())
-    }
+    rt.stop_container(container_name)?;
+    println!("Container '{}' stopped successfully", container_name);
+    Ok(())
 }
 
-fn remove_container(container_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn remove_container(rt: &dyn ContainerRuntime, container_name: &str) -> AppResult<()> {
     println!("Removing container: {}", container_name);
     
-    let status = Command::new("podman")
-        .args(&["rm", container_name])
-        .status()?;
+    rt.remove_container(container_name)?;
+    println!("Container '{}' removed successfully", container_name);
+    Ok(())
+}
+
+/// Pulls an image with the TLS setting of its registry, trying the registry's
+/// mirrors first and tagging a mirrored image with the original name
+fn pull_image(rt: &dyn ContainerRuntime, registries: &[Registry], image: &str) -> AppResult<()> {
+    println!("Pulling image: {}", image);
     
-    if status.success() {
-        println!("Container '{}' removed successfully", container_name);
-        Ok(())
-    } else {
-        Err(format!("Failed to remove container: {}", container_name).into())
+    let registry = registries.iter().find(|registry| registry.host == image_registry(image));
+    let tls_verify = registry.is_none_or(Registry::tls_verify);
+    // A digest cannot be carried over to the original name by tagging
+    let mirrors = registry.filter(|_| !image.contains('@')).map(|registry| registry.mirrors.as_slice()).unwrap_or_default();
+    let target = qualified_image(image);
+    for mirror in mirrors {
+        let mirrored = mirror_image(mirror, image);
+        println!("Trying mirror: {}", mirrored);
+        match rt.pull_image(&mirrored, tls_verify).and_then(|()| rt.tag_image(&mirrored, &target)) {
+            Ok(()) => {
+                println!("Successfully pulled image: {} (from mirror {})", image, mirror);
+                return Ok(());
+            }
+            Err(e) => println!("Warning: Failed to pull from mirror {}: {}", mirror, e),
+        }
     }
+    
+    rt.pull_image(image, tls_verify)?;
+    println!("Successfully pulled image: {}", image);
+    Ok(())
 }
 
-fn pull_image(image: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Pulling image: {}", image);
+/// Default for `--start-gate`
+const DEFAULT_START_GATE: &str = "5s";
+/// Pause between healthcheck runs while waiting for a replaced container to become healthy
+const HEALTH_POLL: Duration = Duration::from_secs(2);
+
+/// Default for `upgrade --timeout`
+const DEFAULT_ROLLING_TIMEOUT: &str = "2m";
+
+/// How a replaced container is judged to have started
+#[derive(Debug, Clone, Copy)]
+struct StartGate {
+    /// How long the container must keep running after it was created
+    delay: Duration,
+    /// Limit for passing the probe or healthcheck in a rolling upgrade
+    rolling_timeout: Option<Duration>,
+}
+
+impl StartGate {
+    /// Parses `--start-gate`, falling back to `DEFAULT_START_GATE`
+    fn new(delay: Option<&str>, rolling_timeout: Option<Duration>) -> AppResult<StartGate> {
+        let delay = delay.unwrap_or(DEFAULT_START_GATE);
+        let delay = units::parse_duration(delay)
+            .ok_or_else(|| format!("Invalid start gate '{}', expected e.g. 5s, 30s or 0", delay))?;
+        Ok(StartGate { delay, rolling_timeout })
+    }
+}
+
+/// Returns an error unless a container is running
+fn ensure_running(rt: &dyn ContainerRuntime, container_name: &str) -> AppResult<()> {
+    match rt.container_state(container_name)? {
+        Some(state) if state.running => Ok(()),
+        Some(state) => Err(format!("Container '{}' exited with code {}", container_name, state.exit_code).into()),
+        None => Err(format!("Container '{}' disappeared", container_name).into()),
+    }
+}
+
+/// Waits until a new container is still running after the gate's delay and then until it
+/// passes its healthcheck, within the healthcheck's start period and retries.
+///
+/// A rolling upgrade passes its timeout, which replaces that limit, and checks the
+/// container's probe instead of the healthcheck if it has one.
+fn wait_until_started(rt: &dyn ContainerRuntime, container: &Container, data_path: &str, gate: StartGate) -> AppResult<()> {
+    println!("Waiting {} for container '{}' to start", units::format_duration(gate.delay), container.name);
+    thread::sleep(gate.delay);
+    ensure_running(rt, &container.name)?;
+    
+    let probe = gate.rolling_timeout.zip(container.probe.as_ref());
+    let health = runtime::ContainerInfo::from_config(container, data_path).healthcheck;
+    let (check, limit) = match (probe, health) {
+        (Some((timeout, probe)), _) => (probe::describe(probe), timeout),
+        (None, Some(health)) => ("healthcheck".to_string(), gate.rolling_timeout.unwrap_or_else(|| health.start_period.saturating_add(health.interval.saturating_mul(health.retries)))),
+        (None, None) => {
+            println!("Container '{}' is running", container.name);
+            return Ok(());
+        }
+    };
+    println!("Waiting up to {} for container '{}' to pass its {}", units::format_duration(limit), container.name, check);
+    // A limit beyond what the clock can represent never runs out
+    let deadline = Instant::now().checked_add(limit);
+    loop {
+        let result = match probe {
+            Some((_, probe)) => probe::check(probe),
+            None if rt.run_healthcheck(&container.name)? => Ok(()),
+            None => Err("healthcheck reported unhealthy".into()),
+        };
+        match result {
+            Ok(()) => {
+                println!("Container '{}' passed its {}", container.name, check);
+                return Ok(());
+            }
+            Err(e) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                return Err(AppError::context(format!("Container '{}' did not pass its {} within {}", container.name, check, units::format_duration(limit)), e));
+            }
+            Err(_) => {}
+        }
+        // No point in waiting for a container that has exited meanwhile
+        ensure_running(rt, &container.name)?;
+        thread::sleep(HEALTH_POLL);
+    }
+}
+
+/// Name the previous container is kept under while its replacement is started
+fn previous_name(container_name: &str) -> String {
+    format!("{}-previous", container_name)
+}
+
+/// Replaces a container with one created from `container`, keeping the old one stopped
+/// under another name until the new one passes the start gate and restoring it otherwise
+fn replace_container(rt: &dyn ContainerRuntime, config: &Config, pod_name: &str, container: &Container, gate: StartGate, dry_run: bool) -> AppResult<()> {
+    let previous = previous_name(&container.name);
+    if rt.container_exists(&previous) {
+        println!("Removing leftover container '{}'", previous);
+        discard_container(rt, &previous)?;
+    }
     
-    let status = Command::new("podman")
-        .args(&["pull", image])
-        .status()?;
+    let was_running = rt.container_state(&container.name)?.is_some_and(|state| state.running);
+    stop_container(rt, &container.name)?;
+    println!("Keeping the previous container as '{}'", previous);
+    rt.rename_container(&container.name, &previous)?;
     
-    if status.success() {
-        println!("Successfully pulled image: {}", image);
-        Ok(())
-    } else {
-        Err(format!("Failed to pull image: {}", image).into())
+    let started = create_container_in_pod(rt, pod_name, container, &config.data_path).and_then(|()| {
+        if dry_run {
+            println!("[dry-run] Would wait for container '{}' to start", container.name);
+            Ok(())
+        } else {
+            wait_until_started(rt, container, &config.data_path, gate)
+        }
+    });
+    if let Err(e) = started {
+        println!("Container '{}' did not pass the start gate: {}", container.name, e);
+        println!("Restoring the previous container '{}'", container.name);
+        if rt.container_exists(&container.name) {
+            discard_container(rt, &container.name)?;
+        }
+        rt.rename_container(&previous, &container.name)?;
+        if was_running {
+            rt.start_container(&container.name)?;
+        }
+        return Err(AppError::context(format!("Upgrade of container '{}' failed and was rolled back", container.name), e));
     }
+    
+    remove_container(rt, &previous)
 }
 
-fn upgrade_container(pod_name: &str, container: &Container, data_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Returns the image a container runs, to be restored by `rollback`
+fn known_good_image(rt: &dyn ContainerRuntime, container_name: &str) -> AppResult<Option<KnownGood>> {
+    let Some(info) = rt.inspect_container(container_name)? else {
+        return Ok(None);
+    };
+    let digest = match info.image_id.as_str() {
+        "" => None,
+        id => rt.inspect_image(id)?.map(|image| image.digest),
+    };
+    Ok(Some(KnownGood { image: info.image, id: info.image_id, digest: digest.unwrap_or_default() }))
+}
+
+fn write_history(path: &Path, history: &History, dry_run: bool) -> AppResult<()> {
+    println!("Recording upgrade history: {}", path.display());
+    write_file(path, &history.to_yaml()?, dry_run)
+}
+
+/// Recreates a container from its image, which `lock_image` has already pulled, and
+/// records the image it ran before as its last known good one
+fn upgrade_container(rt: &dyn ContainerRuntime, config: &Config, history: &mut History, pod_name: &str, container: &Container, gate: StartGate, dry_run: bool) -> AppResult<()> {
     println!("Upgrading container '{}' in pod '{}'", container.name, pod_name);
     
-    // Pull the new image first
-    pull_image(&container.image)?;
+    let known_good = known_good_image(rt, &container.name)?;
+    replace_container(rt, config, pod_name, container, gate, dry_run)?;
     
-    // Stop the existing container
-    stop_container(&container.name)?;
+    if let Some(known_good) = known_good {
+        history.containers.insert(container.name.clone(), known_good);
+        write_history(&History::path(Path::new(&config.data_path)), history, dry_run)?;
+    }
     
-    // Remove the existing container
-    remove_container(&container.name)?;
+    println!("Container '{}' upgraded successfully", container.name);
+    Ok(())
+}
+
+/// Points the name of a known good image at it again, pulling it by digest if it is gone
+fn restore_image(rt: &dyn ContainerRuntime, registries: &[Registry], known_good: &KnownGood) -> AppResult<()> {
+    let name = qualified_image(&known_good.image);
+    if !known_good.id.is_empty() && rt.inspect_image(&known_good.id)?.is_some() {
+        println!("Image {} is still present locally", short_id(&known_good.id));
+        return rt.tag_image(&known_good.id, &name);
+    }
+    if known_good.digest.is_empty() {
+        return Err(format!("Image {} is no longer present and its digest is unknown", known_good.image).into());
+    }
     
-    // Create the container with the new image
-    create_container_in_pod(pod_name, container, data_path)?;
+    let pinned = lock::pinned_reference(&name, &known_good.digest);
+    pull_image(rt, registries, &pinned)?;
+    rt.tag_image(&pinned, &name)
+}
+
+/// Stops (if running) and removes a container that is no longer wanted or must be recreated
+fn discard_container(rt: &dyn ContainerRuntime, container_name: &str) -> AppResult<()> {
+    if let Err(e) = stop_container(rt, container_name) {
+        println!("Warning: {} (may not be running)", e);
+    }
+    remove_container(rt, container_name)
+}
+
+/// Executes the pod and container changes of a plan
+fn apply_plan(rt: &dyn ContainerRuntime, config: &Config, plan: &plan::Plan) -> AppResult<()> {
+    use plan::{Action, ResourceKind};
+    
+    for pod in &config.pods {
+        let pod_action = plan.changes.iter()
+            .find(|c| c.kind == ResourceKind::Pod && c.name == pod.name)
+            .map(|c| c.action)
+            .unwrap_or(Action::NoOp);
+        let container_changes: Vec<&plan::Change> = plan.changes.iter()
+            .filter(|c| c.kind == ResourceKind::Container && c.pod == pod.name)
+            .collect();
+        
+        match pod_action {
+            Action::Create | Action::Recreate => {
+                if pod_action == Action::Recreate {
+                    println!("\nRecreating pod '{}'", pod.name);
+                    rt.remove_pod(&pod.name)?;
+                    println!("Pod '{}' removed", pod.name);
+                } else {
+                    println!("\nCreating pod '{}'", pod.name);
+                }
+                
+                // Containers with the same name outside the pod would block creation
+                for change in &container_changes {
+                    if change.action == Action::Recreate && rt.container_exists(&change.name) {
+                        discard_container(rt, &change.name)?;
+                    }
+                }
+                
+                create_pod(rt, pod, &config.data_path)?;
+            }
+            Action::NoOp | Action::Update | Action::Remove => {
+                for change in &container_changes {
+                    let container = pod.containers.iter().find(|c| c.name == change.name);
+                    match (change.action, container) {
+                        (Action::Create, Some(container)) => {
+                            create_container_in_pod(rt, &pod.name, container, &config.data_path)?;
+                        }
+                        (Action::Recreate, Some(container)) => {
+                            println!("\nRecreating container '{}' in pod '{}'", container.name, pod.name);
+                            discard_container(rt, &container.name)?;
+                            create_container_in_pod(rt, &pod.name, container, &config.data_path)?;
+                        }
+                        (Action::Remove, _) => {
+                            println!("\nRemoving container '{}' from pod '{}'", change.name, pod.name);
+                            discard_container(rt, &change.name)?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
     
-    println!("Container '{}' upgraded successfully", container.name);
     Ok(())
 }
 
-fn stop_containers_and_pods(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+fn stop_containers_and_pods(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<()> {
     println!("Stopping all containers and pods...");
     
     // Stop all containers first
@@ -695,14 +1953,9 @@ fn stop_containers_and_pods(config: &Config) -> Result<(), Box<dyn std::error::E
         for container in &pod.containers {
             println!("Stopping container: {}", container.name);
             
-            let status = Command::new("podman")
-                .args(&["stop", &container.name])
-                .status()?;
-            
-            if status.success() {
-                println!("Container '{}' stopped successfully", container.name);
-            } else {
-                println!("Warning: Failed to stop container '{}' (may not be running)", container.name);
+            match rt.stop_container(&container.name) {
+                Ok(()) => println!("Container '{}' stopped successfully", container.name),
+                Err(_) => println!("Warning: Failed to stop container '{}' (may not be running)", container.name),
             }
         }
     }
@@ -711,14 +1964,9 @@ fn stop_containers_and_pods(config: &Config) -> Result<(), Box<dyn std::error::E
     for pod in &config.pods {
         println!("Stopping pod: {}", pod.name);
         
-        let status = Command::new("podman")
-            .args(&["pod", "stop", &pod.name])
-            .status()?;
-        
-        if status.success() {
-            println!("Pod '{}' stopped successfully", pod.name);
-        } else {
-            println!("Warning: Failed to stop pod '{}' (may not be running)", pod.name);
+        match rt.stop_pod(&pod.name) {
+            Ok(()) => println!("Pod '{}' stopped successfully", pod.name),
+            Err(_) => println!("Warning: Failed to stop pod '{}' (may not be running)", pod.name),
         }
     }
     
@@ -727,99 +1975,136 @@ fn stop_containers_and_pods(config: &Config) -> Result<(), Box<dyn std::error::E
 }
 
 
-fn setup_mode(config_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn setup_mode(rt: &dyn ContainerRuntime, config_path: &str, use_kube: bool, dry_run: bool) -> AppResult<()> {
     println!("=== Running Setup Mode ===");
     
     // Load configuration first
     let mut config = load_config(config_path)?;
     
+    // The manifests refer to Kubernetes Secrets holding key/value data, which the
+    // plain Podman secrets created by setup are not
+    if use_kube
+        && let Some(container) = config.pods.iter().flat_map(|pod| &pod.containers).find(|container| !container.secrets.is_empty())
+    {
+        return Err(format!(
+            "Container '{}' uses secrets, which setup --kube cannot pass to podman kube play; run setup without --kube",
+            container.name
+        ).into());
+    }
+    
     // 1. Check for podman installation and install podman
     println!("\nStep 1: Checking Podman installation...");
-    check_and_install_podman(config_path, &mut config)?;
+    check_and_install_podman(rt, config_path, &mut config, dry_run)?;
     
     // 2. Check for all directories exist, if they don't then create them
     println!("\nStep 2: Checking and creating data path...");
-    check_and_create_data_path(&config.data_path)?;
+    check_and_create_data_path(&config.data_path, dry_run)?;
     
     println!("Creating mount paths...");
-    create_mount_paths(&config)?;
+    create_mount_paths(&config, config_dir(config_path), dry_run)?;
+    
+    println!("Creating named volumes...");
+    create_volumes(rt, &config)?;
+    
+    println!("Creating secrets...");
+    create_secrets(rt, &config, config_dir(config_path))?;
+    
+    println!("Writing registry configuration...");
+    write_registries_conf(&config, dry_run)?;
     
     // Configure private registry if specified
     println!("\nConfiguring private registry...");
-    if let Err(e) = configure_private_registry(&config) {
-        eprintln!("Warning: Error configuring private registry: {}", e);
-    }
+    login_or_warn(rt, &config, config_dir(config_path));
     
-    // 3. Create the pods
-    println!("\nStep 3: Creating pods...");
-    display_pod_commands(&config);
-    check_and_create_pods(&config)?;
+    // 3. Pull all images that are required, with each registry's TLS setting,
+    // before any container is created from them
+    println!("\nStep 3: Pulling all required images...");
+    pull_images(rt, &config)?;
     
-    // 4. Pull all images that are required
-    println!("\nStep 4: Pulling all required images...");
-    pull_images(&config)?;
+    // 4. Create the pods
+    println!("\nStep 4: Creating pods...");
+    if use_kube {
+        play_kube_pods(rt, &config)?;
+    } else {
+        display_pod_commands(&config);
+        check_and_create_pods(rt, &config)?;
+    }
     
     // 5. Stop the containers and pods
     println!("\nStep 5: Stopping containers and pods...");
-    stop_containers_and_pods(&config)?;
+    stop_containers_and_pods(rt, &config)?;
     
     println!("\n=== Setup completed successfully ===");
     Ok(())
 }
 
 
-fn upgrade_mode(config_path: &str, container_name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+fn upgrade_mode(rt: &dyn ContainerRuntime, config_path: &str, container_name: Option<&str>, rolling: bool, timeout: Option<&str>, start_gate: Option<&str>, dry_run: bool) -> AppResult<()> {
     println!("=== Running Upgrade Mode ===");
     
-    let config = load_config(config_path)?;
+    let rolling_timeout = if rolling {
+        let timeout = timeout.unwrap_or(DEFAULT_ROLLING_TIMEOUT);
+        let timeout = units::parse_duration(timeout)
+            .ok_or_else(|| format!("Invalid timeout '{}', expected e.g. 90s, 2m or 1m30s", timeout))?;
+        println!("Rolling upgrade: each container must pass its gate within {}", units::format_duration(timeout));
+        Some(timeout)
+    } else {
+        None
+    };
+    let gate = StartGate::new(start_gate, rolling_timeout)?;
     
-    let mut upgraded_any = false;
+    let config = load_config(config_path)?;
+    login_or_warn(rt, &config, config_dir(config_path));
     
+    let targets: Vec<(&Pod, &Container)> = config.pods.iter()
+        .flat_map(|pod| pod.containers.iter().map(move |container| (pod, container)))
+        .filter(|(_, container)| container_name.is_none_or(|name| container.name == name))
+        .collect();
     match container_name {
-        Some(target_container) => {
-            println!("Upgrading specific container: {}", target_container);
-            
-            // Find the container across all pods
-            let mut found_container = false;
-            for pod in &config.pods {
-                for container in &pod.containers {
-                    if container.name == target_container {
-                        found_container = true;
-                        println!("\nChecking container '{}' in pod '{}'", container.name, pod.name);
-                        
-                        if needs_upgrade(container) {
-                            upgrade_container(&pod.name, container, &config.data_path)?;
-                            upgraded_any = true;
-                        }
-                        break;
-                    }
-                }
-                if found_container {
-                    break;
-                }
-            }
-            
-            if !found_container {
-                return Err(format!("Container '{}' not found in configuration", target_container).into());
-            }
+        Some(target_container) if targets.is_empty() => {
+            return Err(format!("Container '{}' not found in configuration", target_container).into());
         }
-        None => {
-            println!("Upgrading all containers...");
-            
-            for pod in &config.pods {
-                println!("\nChecking pod: {}", pod.name);
-                
-                for container in &pod.containers {
-                    if needs_upgrade(container) {
-                        upgrade_container(&pod.name, container, &config.data_path)?;
-                        upgraded_any = true;
-                    }
-                }
+        Some(target_container) => println!("Upgrading specific container: {}", target_container),
+        None => println!("Upgrading all containers..."),
+    }
+    
+    // Pin every image before touching any container, so a failed pull leaves them all running
+    println!("\nPinning images to their locked digests...");
+    let registries = config.registries();
+    let lock_path = LockFile::path(config_dir(config_path));
+    let mut lock = LockFile::load(&lock_path)?;
+    let previous_lock = lock.clone();
+    let mut history = History::load(&History::path(Path::new(&config.data_path)))?;
+    let mut image_ids: HashMap<&str, Option<String>> = HashMap::new();
+    for (_, container) in &targets {
+        if !image_ids.contains_key(container.image.as_str()) {
+            let image_id = lock_image(rt, &registries, &mut lock, &container.image, dry_run)?;
+            image_ids.insert(&container.image, image_id);
+        }
+    }
+    if lock != previous_lock {
+        write_lock_file(&lock_path, &lock, dry_run)?;
+    }
+    
+    let mut upgraded = Vec::new();
+    for (index, (pod, container)) in targets.iter().enumerate() {
+        println!("\nChecking container '{}' in pod '{}'", container.name, pod.name);
+        let image_id = image_ids.get(container.image.as_str()).cloned().flatten();
+        if !needs_upgrade(rt, container, image_id.as_deref()) {
+            continue;
+        }
+        match upgrade_container(rt, &config, &mut history, &pod.name, container, gate, dry_run) {
+            Ok(()) => upgraded.push(container.name.as_str()),
+            Err(e) if rolling => {
+                let remaining: Vec<&str> = targets[index + 1..].iter().map(|(_, container)| container.name.as_str()).collect();
+                print_rolling_report(&upgraded, &container.name, &e, &remaining);
+                return Err(AppError::context(format!("Rolling upgrade halted at container '{}'", container.name), e));
             }
+            Err(e) => return Err(e),
         }
     }
     
-    if !upgraded_any {
+    if upgraded.is_empty() {
         println!("\nNo containers needed upgrading - all are up to date!");
     } else {
         println!("\nUpgrade process completed successfully!");
@@ -829,7 +2114,110 @@ fn upgrade_mode(config_path: &str, container_name: Option<&str>) -> Result<(), B
     Ok(())
 }
 
-fn start_mode(config_path: &str, pod_name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+/// Summarizes a rolling upgrade that stopped at a container failing its gate
+fn print_rolling_report(upgraded: &[&str], failed: &str, error: &AppError, remaining: &[&str]) {
+    let list = |names: &[&str]| if names.is_empty() { "none".to_string() } else { names.join(", ") };
+    println!("\nRolling upgrade halted:");
+    println!("  Upgraded:    {}", list(upgraded));
+    // The gate failure rather than the rollback notice wrapping it
+    let reason = std::error::Error::source(error).map_or_else(|| error.to_string(), ToString::to_string);
+    println!("  Failed:      {}: {}", failed, reason);
+    println!("  Not checked: {}", list(remaining));
+    println!("Upgraded containers keep their new image; run 'podman_deploy rollback <container>' to restore one.");
+}
+
+/// Resolves every image of the config to its current digest and rewrites the lock file
+fn lock_update_mode(rt: &dyn ContainerRuntime, config_path: &str, dry_run: bool) -> AppResult<()> {
+    println!("=== Updating Lock File ===");
+    
+    let config = load_config(config_path)?;
+    login_or_warn(rt, &config, config_dir(config_path));
+    
+    let registries = config.registries();
+    let lock_path = LockFile::path(config_dir(config_path));
+    let previous_lock = LockFile::load(&lock_path)?;
+    let images: BTreeSet<&str> = config.pods.iter()
+        .flat_map(|pod| &pod.containers)
+        .map(|container| container.image.as_str())
+        .collect();
+    
+    let mut lock = LockFile::default();
+    let mut changes = Vec::new();
+    for image in &images {
+        println!();
+        let previous = previous_lock.images.get(*image);
+        let Some(info) = resolve_image(rt, &registries, image, dry_run)? else {
+            // Nothing is pulled in dry-run mode, so keep what is known
+            if let Some(digest) = previous {
+                lock.images.insert(image.to_string(), digest.clone());
+            }
+            continue;
+        };
+        match previous {
+            Some(digest) if *digest == info.digest => {}
+            Some(digest) => changes.push(format!("  ~ {}: {} -> {}", image, digest, info.digest)),
+            None => changes.push(format!("  + {}: {}", image, info.digest)),
+        }
+        lock.images.insert(image.to_string(), info.digest);
+    }
+    for image in previous_lock.images.keys().filter(|image| !images.contains(image.as_str())) {
+        changes.push(format!("  - {}", image));
+    }
+    
+    println!();
+    if changes.is_empty() && lock == previous_lock && lock_path.exists() {
+        println!("All locked digests are up to date.");
+    } else {
+        for change in &changes {
+            println!("{}", change);
+        }
+        write_lock_file(&lock_path, &lock, dry_run)?;
+        println!("Run 'podman_deploy upgrade' to move the containers to the new digests.");
+    }
+    
+    println!("=== Lock update completed successfully ===");
+    Ok(())
+}
+
+/// Restores the image a container ran before its last upgrade
+fn rollback_mode(rt: &dyn ContainerRuntime, config_path: &str, container_name: &str, start_gate: Option<&str>, dry_run: bool) -> AppResult<()> {
+    println!("=== Running Rollback Mode ===");
+    
+    let gate = StartGate::new(start_gate, None)?;
+    let config = load_config(config_path)?;
+    let Some((pod, container)) = config.pods.iter()
+        .flat_map(|pod| pod.containers.iter().map(move |container| (pod, container)))
+        .find(|(_, container)| container.name == container_name)
+    else {
+        return Err(format!("Container '{}' not found in configuration", container_name).into());
+    };
+    if !rt.container_exists(container_name) {
+        return Err(format!("Container '{}' does not exist", container_name).into());
+    }
+    
+    let history_path = History::path(Path::new(&config.data_path));
+    let mut history = History::load(&history_path)?;
+    let Some(known_good) = history.containers.get(container_name).cloned() else {
+        return Err(format!("No previous image recorded for container '{}', nothing to roll back to", container_name).into());
+    };
+    println!("Rolling back container '{}' to {} ({})", container_name, known_good.image, short_id(&known_good.id));
+    
+    configure_private_registry(rt, &config, config_dir(config_path))?;
+    restore_image(rt, &config.registries(), &known_good)?;
+    
+    let restored = Container { image: known_good.image.clone(), ..container.clone() };
+    replace_container(rt, &config, &pod.name, &restored, gate, dry_run)?;
+    
+    history.containers.remove(container_name);
+    write_history(&history_path, &history, dry_run)?;
+    
+    println!("\nContainer '{}' was rolled back to {}", container_name, known_good.image);
+    println!("The next 'podman_deploy upgrade' moves it to the image of the config and lock file again.");
+    println!("=== Rollback completed successfully ===");
+    Ok(())
+}
+
+fn start_mode(rt: &dyn ContainerRuntime, config_path: &str, pod_name: Option<&str>) -> AppResult<()> {
     println!("=== Running Start Mode ===");
     
     let config = load_config(config_path)?;
@@ -837,11 +2225,11 @@ fn start_mode(config_path: &str, pod_name: Option<&str>) -> Result<(), Box<dyn s
     match pod_name {
         Some(name) => {
             println!("Starting specific pod: {}", name);
-            start_pod(&config, name)?;
+            start_pod(rt, &config, name)?;
         }
         None => {
             println!("Starting all pods...");
-            start_all_pods(&config)?;
+            start_all_pods(rt, &config)?;
         }
     }
     
@@ -849,7 +2237,7 @@ fn start_mode(config_path: &str, pod_name: Option<&str>) -> Result<(), Box<dyn s
     Ok(())
 }
 
-fn stop_mode(config_path: &str, pod_name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+fn stop_mode(rt: &dyn ContainerRuntime, config_path: &str, pod_name: Option<&str>) -> AppResult<()> {
     println!("=== Running Stop Mode ===");
     
     let config = load_config(config_path)?;
@@ -857,11 +2245,11 @@ fn stop_mode(config_path: &str, pod_name: Option<&str>) -> Result<(), Box<dyn st
     match pod_name {
         Some(name) => {
             println!("Stopping specific pod: {}", name);
-            stop_pod(&config, name)?;
+            stop_pod(rt, &config, name)?;
         }
         None => {
             println!("Stopping all pods...");
-            stop_containers_and_pods(&config)?;
+            stop_containers_and_pods(rt, &config)?;
         }
     }
     
@@ -869,64 +2257,37 @@ fn stop_mode(config_path: &str, pod_name: Option<&str>) -> Result<(), Box<dyn st
     Ok(())
 }
 
-fn prune_mode() -> Result<(), Box<dyn std::error::Error>> {
+fn prune_mode(rt: &dyn ContainerRuntime) -> AppResult<()> {
     println!("=== Running Prune Mode ===");
     
     // Prune all images
     println!("Pruning all images...");
-    let prune_status = Command::new("podman")
-        .args(&["image", "prune", "-a"])
-        .status()?;
-    
-    if prune_status.success() {
-        println!("Successfully pruned all images");
-    } else {
-        println!("Warning: Failed to prune all images");
+    match rt.prune_images() {
+        Ok(()) => println!("Successfully pruned all images"),
+        Err(_) => println!("Warning: Failed to prune all images"),
     }
     
     println!("=== Prune completed successfully ===");
     Ok(())
 }
 
-fn list_mode(config_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("=== Listing Pods and Containers ===");
+/// Writes the configured pods, containers, volumes and secrets with their live status to `out`
+fn list_mode(rt: &dyn ContainerRuntime, config_path: &str, out: &mut dyn Write) -> AppResult<()> {
+    writeln!(out, "=== Listing Pods and Containers ===")?;
     
     let config = load_config(config_path)?;
     
     // Get pod status from podman
-    let pod_output = Command::new("podman")
-        .args(&["pod", "ps", "--format", "{{.Name}}\t{{.Status}}"])
-        .output()?;
-    
-    let mut pod_status_map: HashMap<String, String> = HashMap::new();
-    if pod_output.status.success() {
-        let pod_status_str = String::from_utf8_lossy(&pod_output.stdout);
-        for line in pod_status_str.lines() {
-            let parts: Vec<&str> = line.split('\t').collect();
-            if parts.len() >= 2 {
-                pod_status_map.insert(parts[0].to_string(), parts[1].to_string());
-            }
-        }
-    }
+    let pod_status_map: HashMap<String, String> = rt.list_pods()?
+        .into_iter()
+        .map(|pod| (pod.name, pod.status))
+        .collect();
     
     // Get container status from podman
-    let container_output = Command::new("podman")
-        .args(&["ps", "-a", "--format", "{{.Names}}\t{{.Status}}\t{{.Image}}"])
-        .output()?;
-    
-    let mut container_status_map: HashMap<String, (String, String)> = HashMap::new();
-    if container_output.status.success() {
-        let container_status_str = String::from_utf8_lossy(&container_output.stdout);
-        for line in container_status_str.lines() {
-            let parts: Vec<&str> = line.split('\t').collect();
-            if parts.len() >= 3 {
-                container_status_map.insert(
-                    parts[0].to_string(),
-                    (parts[1].to_string(), parts[2].to_string())
-                );
-            }
-        }
-    }
+    let container_status_map: HashMap<String, (String, String)> = rt.list_containers()?
+        .into_iter()
+        .map(|container| (container.name, (container.status, container.image)))
+        .collect();
     
     // Display pods and their containers
     for pod in &config.pods {
@@ -934,26 +2295,272 @@ fn list_mode(config_path: &str) -> Result<(), Box<dyn std::error::Error>> {
             .cloned()
             .unwrap_or_else(|| "Unknown".to_string());
         
-        println!("\nPod: {} (Status: {})", pod.name, pod_status);
-        println!("  Containers:");
+        writeln!(out, "\nPod: {} (Status: {})", pod.name, pod_status)?;
+        writeln!(out, "  Containers:")?;
         
         for container in &pod.containers {
             let (container_status, actual_image) = container_status_map.get(&container.name)
                 .cloned()
                 .unwrap_or_else(|| ("Unknown".to_string(), "Unknown".to_string()));
             
-            println!("    - Name: {}", container.name);
-            println!("      Expected Image: {}", container.image);
-            println!("      Actual Image: {}", actual_image);
-            println!("      Status: {}", container_status);
+            writeln!(out, "    - Name: {}", container.name)?;
+            writeln!(out, "      Expected Image: {}", container.image)?;
+            writeln!(out, "      Actual Image: {}", actual_image)?;
+            writeln!(out, "      Status: {}", container_status)?;
+            if let Some(policy) = &container.restart_policy {
+                writeln!(out, "      Restart Policy: {}", policy)?;
+            }
+            if let Some(health) = &container.healthcheck {
+                let interval = health.interval.as_deref().unwrap_or("30s");
+                writeln!(out, "      Healthcheck: {} (every {})", health.command, interval)?;
+            }
+            if let Some(resources) = &container.resources {
+                let mut limits = Vec::new();
+                if let Some(memory) = &resources.memory {
+                    limits.push(format!("memory {}", memory));
+                }
+                if let Some(cpus) = resources.cpus {
+                    limits.push(format!("cpus {}", cpus));
+                }
+                if let Some(pids_limit) = resources.pids_limit {
+                    limits.push(format!("pids {}", pids_limit));
+                }
+                writeln!(out, "      Limits: {}", limits.join(", "))?;
+            }
+            if let Some(command_line) = container.command_line() {
+                writeln!(out, "      Command: {}", shell_join(&command_line))?;
+            }
+            if !container.mounts.is_empty() {
+                writeln!(out, "      Mounts: {}", container.mounts.iter().map(Mount::to_string).collect::<Vec<_>>().join(", "))?;
+            }
+        }
+    }
+    
+    if !config.volumes.is_empty() {
+        writeln!(out, "\nVolumes:")?;
+        for volume in &config.volumes {
+            let status = if rt.volume_exists(&volume.name) { "Created" } else { "Missing" };
+            let driver = volume.driver.as_deref().unwrap_or("local");
+            writeln!(out, "  - {} (Driver: {}, Status: {})", volume.name, driver, status)?;
+        }
+    }
+    
+    if !config.secrets.is_empty() {
+        writeln!(out, "\nSecrets:")?;
+        for secret in &config.secrets {
+            let status = if rt.secret_exists(&secret.name) { "Created" } else { "Missing" };
+            let source = match (&secret.file, &secret.env) {
+                (Some(file), _) => format!("file {}", file),
+                (None, Some(variable)) => format!("env {}", variable),
+                (None, None) => "none".to_string(),
+            };
+            writeln!(out, "  - {} (Source: {}, Status: {})", secret.name, source, status)?;
+        }
+    }
+    
+    writeln!(out, "\n=== List completed successfully ===")?;
+    Ok(())
+}
+
+fn plan_mode(rt: &dyn ContainerRuntime, config_path: &str, json_output: bool) -> AppResult<()> {
+    if json_output {
+        let config = read_config(config_path)?;
+        let plan = plan::compute(rt, &config)?;
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+    
+    println!("=== Running Plan Mode ===");
+    
+    let config = load_config(config_path)?;
+    let plan = plan::compute(rt, &config)?;
+    
+    println!();
+    print!("{}", plan.render());
+    if !plan.has_changes() {
+        println!("No changes. The running pods and containers match the configuration.");
+    }
+    
+    println!("\n=== Plan completed successfully ===");
+    Ok(())
+}
+
+fn apply_mode(rt: &dyn ContainerRuntime, config_path: &str, dry_run: bool) -> AppResult<()> {
+    println!("=== Running Apply Mode ===");
+    
+    let config = load_config(config_path)?;
+    
+    println!("\nComputing changes...");
+    let plan = plan::compute(rt, &config)?;
+    print!("{}", plan.render());
+    
+    if !plan.has_changes() {
+        println!("\nNothing to apply - all pods and containers match the configuration!");
+        println!("=== Apply completed successfully ===");
+        return Ok(());
+    }
+    
+    println!("\nChecking data path and mount paths...");
+    check_and_create_data_path(&config.data_path, dry_run)?;
+    create_mount_paths(&config, config_dir(config_path), dry_run)?;
+    create_volumes(rt, &config)?;
+    create_secrets(rt, &config, config_dir(config_path))?;
+    
+    println!("\nConfiguring private registry...");
+    configure_private_registry(rt, &config, config_dir(config_path))?;
+    
+    // Pull images before touching anything so a bad image leaves the running containers alone
+    println!("\nPulling images for new and changed containers...");
+    let registries = config.registries();
+    for pod in &config.pods {
+        for container in &pod.containers {
+            let changed = plan.changes.iter().any(|c| {
+                c.kind == plan::ResourceKind::Container
+                    && c.name == container.name
+                    && matches!(c.action, plan::Action::Create | plan::Action::Recreate)
+            });
+            if changed {
+                pull_image(rt, &registries, &container.image)?;
+            }
+        }
+    }
+    
+    apply_plan(rt, &config, &plan)?;
+    
+    println!("\n=== Apply completed successfully ===");
+    Ok(())
+}
+
+fn validate_mode(config_path: &str) -> AppResult<()> {
+    println!("=== Validating Configuration ===");
+    println!("Checking config file: {}", config_path);
+    
+    let config = read_config(config_path)?;
+    let containers: usize = config.pods.iter().map(|pod| pod.containers.len()).sum();
+    
+    println!("Configuration is valid: {} pods, {} containers", config.pods.len(), containers);
+    Ok(())
+}
+
+fn generate_quadlet_mode(config_path: &str, output: Option<&str>, install: bool, dry_run: bool) -> AppResult<()> {
+    // Without a destination the units go to stdout so they can be redirected
+    if output.is_none() && !install {
+        let config = read_config(config_path)?;
+        for unit in quadlet::generate(&config)? {
+            println!("### {}", unit.name);
+            println!("{}", unit.contents);
+        }
+        return Ok(());
+    }
+    
+    println!("=== Generating Quadlet Units ===");
+    
+    let config = load_config(config_path)?;
+    let units = quadlet::generate(&config)?;
+    
+    let rootful = is_root();
+    let dir = match output {
+        Some(dir) => Path::new(dir).to_path_buf(),
+        None => quadlet::install_dir(rootful),
+    };
+    
+    if !dir.exists() {
+        println!("Creating directory: {}", dir.display());
+        create_dir(&dir, dry_run)?;
+    }
+    
+    for unit in &units {
+        let path = dir.join(&unit.name);
+        println!("Writing unit: {}", path.display());
+        write_file(&path, &unit.contents, dry_run)?;
+    }
+    
+    if install {
+        println!("\nReloading systemd units...");
+        let reload_args: &[&str] = if rootful { &["daemon-reload"] } else { &["--user", "daemon-reload"] };
+        if !execute_system_command("systemctl", reload_args, dry_run)? {
+            return Err("Failed to run systemctl daemon-reload".into());
+        }
+        
+        let systemctl = if rootful { "systemctl" } else { "systemctl --user" };
+        println!("\nUnits installed. Start the pods with:");
+        for pod in &config.pods {
+            // Quadlet names the service of a .pod unit "<name>-pod.service"
+            println!("  {} start {}-pod.service", systemctl, pod.name);
         }
     }
     
-    println!("\n=== List completed successfully ===");
+    println!("\n=== Generated {} unit files ===", units.len());
     Ok(())
 }
 
-fn find_config_file() -> Result<String, Box<dyn std::error::Error>> {
+fn generate_kube_mode(config_path: &str, output: Option<&str>, dry_run: bool) -> AppResult<()> {
+    // Without an output file the manifests go to stdout so they can be piped
+    let Some(output) = output else {
+        let config = read_config(config_path)?;
+        print!("{}", kube::to_yaml(&kube::generate(&config)?)?);
+        return Ok(());
+    };
+    
+    println!("=== Generating Kubernetes Manifests ===");
+    
+    let config = load_config(config_path)?;
+    let manifests = kube::generate(&config)?;
+    
+    println!("Writing manifests: {}", output);
+    write_file(Path::new(output), &kube::to_yaml(&manifests)?, dry_run)?;
+    
+    println!("\n=== Generated {} pod manifests ===", manifests.len());
+    println!("Deploy them with: podman kube play {}", output);
+    Ok(())
+}
+
+fn import_compose_mode(compose_path: &str, pod_name: Option<&str>, output: Option<&str>, dry_run: bool) -> AppResult<()> {
+    // Without an output file the config goes to stdout, so the report goes to stderr
+    let Some(output) = output else {
+        let import = compose::import(compose_path, pod_name)?;
+        for line in &import.unmapped {
+            eprintln!("Warning: {}", line);
+        }
+        print!("{}", import.to_yaml()?);
+        return Ok(());
+    };
+    
+    println!("=== Importing Compose File ===");
+    println!("Reading compose file: {}", compose_path);
+    
+    let import = compose::import(compose_path, pod_name)?;
+    for pod in &import.config.pods {
+        println!("Pod '{}' with containers: {}", pod.name,
+            pod.containers.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", "));
+    }
+    
+    if import.unmapped.is_empty() {
+        println!("All compose features were mapped");
+    } else {
+        println!("\nCompose features that could not be mapped:");
+        for line in &import.unmapped {
+            println!("  - {}", line);
+        }
+    }
+    
+    println!("\nWriting config: {}", output);
+    write_file(Path::new(output), &import.to_yaml()?, dry_run)?;
+    
+    println!("\n=== Compose file imported ===");
+    println!("Review the config, then deploy it with: podman_deploy --config {} setup", output);
+    Ok(())
+}
+
+fn find_config_file(custom_path: Option<&str>) -> AppResult<String> {
+    // An explicit path (--config or PODMAN_DEPLOY_CONFIG) must exist
+    if let Some(path) = custom_path {
+        if Path::new(path).exists() {
+            return Ok(path.to_string());
+        }
+        return Err(AppError::ConfigNotFound(format!("Config file not found: {}", path)));
+    }
+    
     // Search in fallback locations
     let search_paths = vec![
         format!("{}/.config/podman_deploy/config.yaml", env::var("HOME").unwrap_or_default()),
@@ -963,98 +2570,338 @@ fn find_config_file() -> Result<String, Box<dyn std::error::Error>> {
     
     for path in &search_paths {
         if Path::new(path).exists() {
-            println!("Found config file at: {}", path);
             return Ok(path.clone());
         }
     }
     
-    Err("Config file not found in any of the search locations".into())
+    Err(AppError::ConfigNotFound(format!(
+        "Config file not found in any of the search locations: {}", search_paths.join(", ")
+    )))
 }
 
-fn print_usage() {
-    println!("Usage: podman_deploy <mode> [container_name/pod_name]");
-    println!();
-    println!("Modes:");
-    println!("  setup                     - Install podman, create directories, create pods, pull images, and stop containers/pods");
-    println!("  list                      - List all pods with their containers, status, and images");
-    println!("  prune                     - Prune unused and untagged images");
-    println!("  upgrade                   - Check container image versions and upgrade if needed for all containers");
-    println!("  upgrade <container_name>  - Check and upgrade specific container if needed");
-    println!("  start                     - Start all pods");
-    println!("  start <pod>               - Start specific pod");
-    println!("  stop                      - Stop all pods");
-    println!("  stop <pod>                - Stop specific pod");
-    println!();
-    println!("Config file search locations (in order):");
-    println!("  1. ~/.config/podman_deploy/config.yaml");
-    println!("  2. /etc/podman_deploy/config.yaml");
-    println!("  3. ./config.yaml (current directory)");
+/// Formats the error with its chain of causes, masking registered secrets
+fn error_chain(error: &AppError) -> String {
+    let mut out = format!("Error: {}", error);
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        out.push_str(&format!("\n  Caused by: {}", cause));
+        source = cause.source();
+    }
+    redact::redact(&out)
+}
+
+/// Prints the error with its chain of causes and exits with its exit code
+fn exit_with_error(error: &AppError) -> ! {
+    eprintln!("{}", error_chain(error));
+    std::process::exit(error.exit_code());
 }
 
 fn main() {
-    println!("=== Starting Podman Deployment Application ===");
-    
-    let args: Vec<String> = env::args().collect();
-    
-    // Validate argument count
-    if args.len() < 2 || args.len() > 3 {
-        eprintln!("Error: Invalid number of arguments");
-        print_usage();
-        std::process::exit(1);
+    let cli = Cli::parse();
+    let dry_run = cli.dry_run;
+    
+    // Machine-readable output must not be mixed with progress messages
+    let quiet = cli.mode.is_machine_readable();
+    if !quiet {
+        println!("=== Starting Podman Deployment Application ===");
+        if dry_run {
+            println!("=== Dry run: no changes will be made ===");
+        }
     }
     
-    let mode = &args[1];
-    let pod_name: Option<&str> = if args.len() == 3 { Some(&args[2]) } else { None };
-    
-    // Find the config file using default search locations
-    let config_path = match find_config_file() {
-        Ok(path) => path,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
+    // Find the config file using --config or the default search locations
+    let config_path = if cli.mode.needs_config() {
+        match find_config_file(cli.config.as_deref()) {
+            Ok(path) => {
+                if !quiet {
+                    println!("Found config file at: {}", path);
+                }
+                path
+            }
+            Err(e) => exit_with_error(&e),
         }
+    } else {
+        String::new()
     };
     
-    let result = match mode.as_str() {
-        "setup" => {
-            if pod_name.is_some() {
-                eprintln!("Error: 'setup' mode does not accept pod name parameter");
-                print_usage();
-                std::process::exit(1);
-            }
-            setup_mode(&config_path)
+    let rt = match runtime::by_name(&cli.runtime) {
+        Ok(rt) if dry_run => Box::new(runtime::DryRunRuntime::new(rt)),
+        Ok(rt) => rt,
+        Err(e) => exit_with_error(&e),
+    };
+    let rt = rt.as_ref();
+    
+    let result = match &cli.mode {
+        Mode::Setup { kube } => setup_mode(rt, &config_path, *kube, dry_run),
+        Mode::List => list_mode(rt, &config_path, &mut io::stdout()),
+        Mode::Prune => prune_mode(rt),
+        Mode::Upgrade { container, rolling, timeout, start_gate } => {
+            upgrade_mode(rt, &config_path, container.as_deref(), *rolling, timeout.as_deref(), start_gate.as_deref(), dry_run)
         }
-        "list" => {
-            if pod_name.is_some() {
-                eprintln!("Error: 'list' mode does not accept pod name parameter");
-                print_usage();
-                std::process::exit(1);
-            }
-            list_mode(&config_path)
+        Mode::Lock { action: LockAction::Update } => lock_update_mode(rt, &config_path, dry_run),
+        Mode::Rollback { container, start_gate } => rollback_mode(rt, &config_path, container, start_gate.as_deref(), dry_run),
+        Mode::Start { pod } => start_mode(rt, &config_path, pod.as_deref()),
+        Mode::Stop { pod } => stop_mode(rt, &config_path, pod.as_deref()),
+        Mode::Plan { json } => plan_mode(rt, &config_path, *json),
+        Mode::Apply => apply_mode(rt, &config_path, dry_run),
+        Mode::Validate => validate_mode(&config_path),
+        Mode::Generate { target: GenerateTarget::Quadlet { output, install } } => {
+            generate_quadlet_mode(&config_path, output.as_deref(), *install, dry_run)
         }
-        "prune" => {
-            if pod_name.is_some() {
-                eprintln!("Error: 'prune' mode does not accept pod name parameter");
-                print_usage();
-                std::process::exit(1);
-            }
-            prune_mode()
+        Mode::Generate { target: GenerateTarget::Kube { output } } => {
+            generate_kube_mode(&config_path, output.as_deref(), dry_run)
         }
-        "upgrade" => upgrade_mode(&config_path, pod_name),
-        "start" => start_mode(&config_path, pod_name),
-        "stop" => stop_mode(&config_path, pod_name),
-        _ => {
-            eprintln!("Error: Invalid mode '{}'", mode);
-            print_usage();
-            std::process::exit(1);
+        Mode::Import { source: ImportSource::Compose { file, pod, output } } => {
+            import_compose_mode(file, pod.as_deref(), output.as_deref(), dry_run)
         }
     };
     
     match result {
-        Ok(()) => println!("\n=== Application completed successfully ==="),
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
+        Ok(()) => {
+            if !quiet {
+                println!("\n=== Application completed successfully ===");
+            }
         }
+        Err(e) => exit_with_error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plan::{Action, ResourceKind};
+    use runtime::{MemoryRuntime, MountInfo};
+    use std::path::PathBuf;
+
+    const CONFIG: &str = r#"
+application_name: memory-test
+is_podman_installed: true
+data_path: {data_path}
+volumes:
+  - name: memory-test-cache
+pods:
+  - name: web-pod
+    containers:
+      - name: web
+        image: docker.io/library/nginx:1.21
+        env_vars:
+          NGINX_HOST: example.com
+        ports:
+          - "8080:80"
+        mounts:
+          - source: /html
+            target: /usr/share/nginx/html
+            read_only: true
+            mode: "0750"
+          - type: volume
+            source: memory-test-cache
+            target: /var/cache/nginx
+      - name: sidecar
+        image: docker.io/library/alpine:3.19
+        command: ["sleep", "infinity"]
+        env_vars: {}
+        ports: []
+        mounts: []
+"#;
+
+    /// Config file and data path in a scratch directory, removed again on drop
+    struct Scratch {
+        dir: PathBuf,
+    }
+
+    impl Scratch {
+        fn new(name: &str) -> Scratch {
+            let dir = env::temp_dir().join(format!("podman_deploy-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let scratch = Scratch { dir };
+            scratch.write_config(CONFIG);
+            scratch
+        }
+
+        fn config_path(&self) -> String {
+            self.dir.join("config.yaml").to_string_lossy().into_owned()
+        }
+
+        fn data_path(&self) -> PathBuf {
+            self.dir.join("data")
+        }
+
+        fn write_config(&self, config: &str) {
+            let config = config.replace("{data_path}", &self.data_path().to_string_lossy());
+            fs::write(self.config_path(), config).unwrap();
+        }
+
+        fn config(&self) -> Config {
+            load_config(&self.config_path()).unwrap()
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn is_running(rt: &MemoryRuntime, container_name: &str) -> bool {
+        rt.container_state(container_name).unwrap().unwrap().running
+    }
+
+    #[test]
+    fn setup_creates_pods_containers_volumes_and_mount_paths() {
+        let scratch = Scratch::new("setup");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        
+        assert!(rt.pod_exists("web-pod"));
+        assert_eq!(rt.inspect_pod("web-pod").unwrap().unwrap().ports, ["8080:80/tcp"]);
+        assert!(rt.volume_exists("memory-test-cache"));
+        let web = rt.inspect_container("web").unwrap().unwrap();
+        assert_eq!(web.image, "docker.io/library/nginx:1.21");
+        assert_eq!(web.env["NGINX_HOST"], "example.com");
+        let mounts: Vec<String> = web.mounts.iter().map(MountInfo::to_string).collect();
+        let html = scratch.data_path().join("html");
+        assert_eq!(mounts, [
+            format!("{}:/usr/share/nginx/html (ro)", html.display()),
+            "volume memory-test-cache:/var/cache/nginx".to_string(),
+        ]);
+        assert_eq!(rt.inspect_container("sidecar").unwrap().unwrap().command, ["sleep", "infinity"]);
+        assert_eq!(fs::metadata(&html).unwrap().permissions().mode() & 0o7777, 0o750);
+        // Setup leaves the deployment stopped for `start`
+        assert!(!is_running(&rt, "web"));
+        assert!(!is_running(&rt, "sidecar"));
+    }
+
+    #[test]
+    fn plan_matches_after_setup_and_reports_drift() {
+        let scratch = Scratch::new("plan");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        assert!(!plan::compute(&rt, &scratch.config()).unwrap().has_changes());
+        
+        scratch.write_config(&CONFIG.replace("NGINX_HOST: example.com", "NGINX_HOST: example.org"));
+        let plan = plan::compute(&rt, &scratch.config()).unwrap();
+        let changed: Vec<&plan::Change> = plan.changes.iter().filter(|change| change.action != Action::NoOp).collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!((changed[0].kind, changed[0].name.as_str(), changed[0].action), (ResourceKind::Container, "web", Action::Recreate));
+        assert_eq!(changed[0].differences[0].field, "env.NGINX_HOST");
+    }
+
+    #[test]
+    fn apply_recreates_drifted_containers_and_removes_dropped_ones() {
+        let scratch = Scratch::new("apply");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        
+        let sidecar = CONFIG.find("      - name: sidecar").unwrap();
+        scratch.write_config(&CONFIG[..sidecar].replace("read_only: true", "read_only: false"));
+        apply_mode(&rt, &scratch.config_path(), false).unwrap();
+        
+        assert!(!rt.container_exists("sidecar"));
+        let web = rt.inspect_container("web").unwrap().unwrap();
+        assert!(web.mounts.iter().all(|mount| !mount.read_only));
+        assert!(!plan::compute(&rt, &scratch.config()).unwrap().has_changes());
+    }
+
+    #[test]
+    fn host_path_drift_updates_the_path_without_recreating_the_container() {
+        let scratch = Scratch::new("host-path");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        let html = scratch.data_path().join("html");
+        fs::set_permissions(&html, fs::Permissions::from_mode(0o700)).unwrap();
+        
+        // The memory backend cannot read owners, which leaves them out rather than reporting drift
+        scratch.write_config(&CONFIG.replace("mode: \"0750\"", "mode: \"0750\"\n            owner: \"1000\""));
+        let plan = plan::compute(&rt, &scratch.config()).unwrap();
+        let changed: Vec<&plan::Change> = plan.changes.iter().filter(|change| change.action != Action::NoOp).collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!((changed[0].kind, changed[0].name.as_str(), changed[0].action), (ResourceKind::Path, html.to_str().unwrap(), Action::Update));
+        assert_eq!(changed[0].differences.len(), 1);
+        assert_eq!((changed[0].differences[0].current.as_deref(), changed[0].differences[0].desired.as_deref()), (Some("0700"), Some("0750")));
+        assert_eq!(changed[0].reason.as_deref(), Some("owner unknown"));
+        
+        scratch.write_config(CONFIG);
+        apply_mode(&rt, &scratch.config_path(), false).unwrap();
+        assert_eq!(fs::metadata(&html).unwrap().permissions().mode() & 0o7777, 0o750);
+        assert!(!plan::compute(&rt, &scratch.config()).unwrap().has_changes());
+    }
+
+    #[test]
+    fn upgrade_replaces_containers_with_a_new_image_and_records_the_old_one() {
+        let scratch = Scratch::new("upgrade");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        let old_image_id = rt.inspect_container("web").unwrap().unwrap().image_id;
+        
+        scratch.write_config(&CONFIG.replace("nginx:1.21", "nginx:1.22"));
+        upgrade_mode(&rt, &scratch.config_path(), Some("web"), false, None, Some("0"), false).unwrap();
+        
+        let web = rt.inspect_container("web").unwrap().unwrap();
+        assert_eq!(web.image, "docker.io/library/nginx:1.22");
+        assert_ne!(web.image_id, old_image_id);
+        assert!(is_running(&rt, "web"));
+        assert!(!rt.container_exists(&previous_name("web")));
+        
+        let lock = LockFile::load(&LockFile::path(&scratch.dir)).unwrap();
+        assert!(lock.images.contains_key("docker.io/library/nginx:1.22"));
+        let history = History::load(&History::path(&scratch.data_path())).unwrap();
+        assert_eq!(history.containers["web"].image, "docker.io/library/nginx:1.21");
+        assert_eq!(history.containers["web"].id, old_image_id);
+    }
+
+    #[test]
+    fn start_gate_defaults_to_five_seconds_and_rejects_invalid_durations() {
+        assert_eq!(StartGate::new(None, None).unwrap().delay, Duration::from_secs(5));
+        assert_eq!(StartGate::new(Some("0"), None).unwrap().delay, Duration::ZERO);
+        let error = StartGate::new(Some("soon"), None).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid start gate 'soon', expected e.g. 5s, 30s or 0");
+    }
+
+    #[test]
+    fn start_and_stop_toggle_pods_and_their_containers() {
+        let scratch = Scratch::new("start-stop");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        
+        start_mode(&rt, &scratch.config_path(), None).unwrap();
+        assert!(is_running(&rt, "web"));
+        assert!(is_running(&rt, "sidecar"));
+        
+        stop_mode(&rt, &scratch.config_path(), Some("web-pod")).unwrap();
+        assert!(!is_running(&rt, "web"));
+        assert!(!is_running(&rt, "sidecar"));
+    }
+
+    #[test]
+    fn list_reports_the_deployed_pods_and_containers() {
+        let scratch = Scratch::new("list");
+        let rt = MemoryRuntime::new();
+        setup_mode(&rt, &scratch.config_path(), false, false).unwrap();
+        start_mode(&rt, &scratch.config_path(), None).unwrap();
+        
+        let mut out = Vec::new();
+        list_mode(&rt, &scratch.config_path(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+=== Listing Pods and Containers ===
+
+Pod: web-pod (Status: Running)
+  Containers:
+    - Name: web
+      Expected Image: docker.io/library/nginx:1.21
+      Actual Image: docker.io/library/nginx:1.21
+      Status: Running
+      Mounts: bind /html:/usr/share/nginx/html, volume memory-test-cache:/var/cache/nginx
+    - Name: sidecar
+      Expected Image: docker.io/library/alpine:3.19
+      Actual Image: docker.io/library/alpine:3.19
+      Status: Running
+      Command: sleep infinity
+
+Volumes:
+  - memory-test-cache (Driver: local, Status: Created)
+
+=== List completed successfully ===
+");
     }
 }
@@ -0,0 +1,679 @@
+//! Comparison of the configuration against live pods and containers.
+//!
+//! A [`Plan`] lists, for every volume, secret, pod and container in the
+//! configuration, whether `setup`/`upgrade` would create, recreate, remove
+//! or leave it alone, together with the individual differences that led
+//! there. Bind mount host paths whose mode or owner drifted are updated in
+//! place, without touching their containers. Existing volumes are never recreated, since that would lose data,
+//! and secret values are not compared since they cannot be read back; only
+//! which secrets a container mounts is.
+
+use crate::runtime::{mounted_secrets, normalize_port, ContainerInfo, ContainerRuntime, MountInfo, PodInfo};
+use crate::redact::redact;
+use crate::units::{format_duration, format_memory};
+use crate::{collect_pod_ports, normalize_image, shell_join, AppResult, Config, Container, Mount, MountSpec, Pod};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// What applying the configuration would do to a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Create,
+    /// Changed in place; only used for host paths
+    Update,
+    Recreate,
+    Remove,
+    NoOp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceKind {
+    Volume,
+    Secret,
+    Pod,
+    Container,
+    /// Host path of a bind mount with a configured mode or owner
+    Path,
+}
+
+/// A single field that differs between the live state and the configuration.
+///
+/// Values pass through [`redact`] when the difference is created, so neither
+/// the rendered nor the JSON plan shows a registered secret.
+#[derive(Debug, Clone, Serialize)]
+pub struct Difference {
+    /// Field name (e.g., "image", "env.NGINX_HOST", "mount", "port")
+    pub field: String,
+    /// Live value, absent if the configuration adds it
+    pub current: Option<String>,
+    /// Configured value, absent if the configuration removes it
+    pub desired: Option<String>,
+}
+
+/// Planned action for one pod or container
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub kind: ResourceKind,
+    pub name: String,
+    /// Pod the resource belongs to (the pod itself for pod changes, empty for volumes and secrets)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub pod: String,
+    pub action: Action,
+    /// Why the action is needed when it is not explained by the differences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub differences: Vec<Difference>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Summary {
+    pub create: usize,
+    pub update: usize,
+    pub recreate: usize,
+    pub remove: usize,
+    pub unchanged: usize,
+}
+
+/// Full set of changes needed to bring the host in line with the configuration
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+    pub summary: Summary,
+}
+
+impl Difference {
+    fn changed(field: impl Into<String>, current: impl Into<String>, desired: impl Into<String>) -> Self {
+        Difference { field: field.into(), current: Some(redact(&current.into())), desired: Some(redact(&desired.into())) }
+    }
+
+    fn added(field: impl Into<String>, desired: impl Into<String>) -> Self {
+        Difference { field: field.into(), current: None, desired: Some(redact(&desired.into())) }
+    }
+
+    fn removed(field: impl Into<String>, current: impl Into<String>) -> Self {
+        Difference { field: field.into(), current: Some(redact(&current.into())), desired: None }
+    }
+}
+
+impl Change {
+    fn new(kind: ResourceKind, name: &str, pod: &str, action: Action) -> Self {
+        Change {
+            kind,
+            name: name.to_string(),
+            pod: pod.to_string(),
+            action,
+            reason: None,
+            differences: Vec::new(),
+        }
+    }
+
+    fn with_reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+}
+
+impl Plan {
+    fn new(changes: Vec<Change>) -> Self {
+        let mut summary = Summary::default();
+        for change in &changes {
+            match change.action {
+                Action::Create => summary.create += 1,
+                Action::Update => summary.update += 1,
+                Action::Recreate => summary.recreate += 1,
+                Action::Remove => summary.remove += 1,
+                Action::NoOp => summary.unchanged += 1,
+            }
+        }
+        Plan { changes, summary }
+    }
+
+    /// Returns true if applying the plan would change anything
+    pub fn has_changes(&self) -> bool {
+        self.changes.iter().any(|change| change.action != Action::NoOp)
+    }
+
+    /// Renders the plan as a Terraform-style diff
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for change in &self.changes {
+            let (symbol, verb) = match change.action {
+                Action::Create => ("+", "will be created"),
+                Action::Update => ("~", "will be updated"),
+                Action::Recreate => ("~", "will be recreated"),
+                Action::Remove => ("-", "will be removed"),
+                Action::NoOp => ("=", "is up to date"),
+            };
+            match change.kind {
+                ResourceKind::Volume => out.push_str(&format!("  {} volume \"{}\" {}", symbol, change.name, verb)),
+                ResourceKind::Secret => out.push_str(&format!("  {} secret \"{}\" {}", symbol, change.name, verb)),
+                ResourceKind::Pod => out.push_str(&format!("  {} pod \"{}\" {}", symbol, change.name, verb)),
+                ResourceKind::Container => out.push_str(&format!(
+                    "  {} container \"{}\" in pod \"{}\" {}", symbol, change.name, change.pod, verb
+                )),
+                ResourceKind::Path => out.push_str(&format!("  {} path \"{}\" {}", symbol, change.name, verb)),
+            }
+            if let Some(reason) = &change.reason {
+                out.push_str(&format!(" ({})", reason));
+            }
+            out.push('\n');
+
+            for diff in &change.differences {
+                let line = match (&diff.current, &diff.desired) {
+                    (Some(current), Some(desired)) => format!("~ {}: {} -> {}", diff.field, current, desired),
+                    (None, Some(desired)) => format!("+ {}: {}", diff.field, desired),
+                    (Some(current), None) => format!("- {}: {}", diff.field, current),
+                    (None, None) => format!("~ {}", diff.field),
+                };
+                out.push_str(&format!("      {}\n", line));
+            }
+        }
+
+        out.push_str(&format!(
+            "\nPlan: {} to create, {} to update, {} to recreate, {} to remove, {} unchanged.\n",
+            self.summary.create, self.summary.update, self.summary.recreate, self.summary.remove, self.summary.unchanged
+        ));
+        out
+    }
+}
+
+/// Compares the published ports of a live pod against the configuration
+pub fn pod_differences(pod: &Pod, live: &PodInfo) -> Vec<Difference> {
+    let desired: BTreeSet<String> = collect_pod_ports(pod).iter().map(|port| normalize_port(port)).collect();
+    let current: BTreeSet<String> = live.ports.iter().cloned().collect();
+
+    let mut differences = Vec::new();
+    for port in current.difference(&desired) {
+        differences.push(Difference::removed("port", port.clone()));
+    }
+    for port in desired.difference(&current) {
+        differences.push(Difference::added("port", port.clone()));
+    }
+    differences
+}
+
+/// Compares a live container against its configuration.
+///
+/// Only configured environment variables are compared, since the live
+/// environment also contains variables set by the image and by Podman.
+/// Likewise the restart policy, healthcheck, resource limits and process
+/// overrides are only compared when configured, since the image provides
+/// defaults for them.
+pub fn container_differences(container: &Container, live: &ContainerInfo, data_path: &str) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    if normalize_image(&live.image) != normalize_image(&container.image) {
+        differences.push(Difference::changed("image", live.image.clone(), container.image.clone()));
+    }
+
+    let mut env_keys: Vec<&String> = container.env_vars.keys().collect();
+    env_keys.sort();
+    for key in env_keys {
+        let desired = &container.env_vars[key];
+        let field = format!("env.{}", key);
+        match live.env.get(key) {
+            Some(current) if current == desired => {}
+            Some(current) => differences.push(Difference::changed(field, current.clone(), desired.clone())),
+            None => differences.push(Difference::added(field, desired.clone())),
+        }
+    }
+
+    let desired: BTreeSet<String> = container.mounts.iter()
+        .filter_map(|mount| MountInfo::from_config(mount, data_path))
+        .map(|mount| mount.to_string())
+        .collect();
+    let current: BTreeSet<String> = live.mounts.iter().map(MountInfo::to_string).collect();
+    for mount in current.difference(&desired) {
+        differences.push(Difference::removed("mount", mount.clone()));
+    }
+    for mount in desired.difference(&current) {
+        differences.push(Difference::added("mount", mount.clone()));
+    }
+
+    // Only mounted secrets can be compared, since Podman does not report environment secrets
+    let desired: BTreeSet<String> = mounted_secrets(container).into_iter().collect();
+    let current: BTreeSet<String> = live.secrets.iter().cloned().collect();
+    for secret in current.difference(&desired) {
+        differences.push(Difference::removed("secret", secret.clone()));
+    }
+    for secret in desired.difference(&current) {
+        differences.push(Difference::added("secret", secret.clone()));
+    }
+
+    differences.extend(runtime_option_differences(container, live, data_path));
+    differences.extend(process_differences(container, live));
+    differences
+}
+
+/// Plans the host path of a bind mount with a configured mode or owner.
+///
+/// Podman does not report these, since they belong to the host path, and
+/// apply sets them again when it creates the mount paths, so drift updates
+/// the path instead of recreating the container. An owner the backend cannot
+/// read is left out of the comparison rather than reported as drift.
+fn plan_host_path(rt: &dyn ContainerRuntime, spec: &MountSpec, pod: &str, data_path: &str) -> Change {
+    let path = spec.host_path(data_path);
+    let mut differences = Vec::new();
+    let mut unknown_owner = false;
+
+    if let Some(bits) = spec.mode_bits() {
+        let desired = format!("{:04o}", bits);
+        match fs::metadata(&path) {
+            Ok(metadata) => {
+                let current = format!("{:04o}", metadata.permissions().mode() & 0o7777);
+                if current != desired {
+                    differences.push(Difference::changed("mode", current, desired));
+                }
+            }
+            Err(_) => differences.push(Difference::added("mode", desired)),
+        }
+    }
+    if let Some(owner) = &spec.owner {
+        // An owner without a gid leaves the group alone
+        let current = rt.path_owner(Path::new(&path))
+            .map(|current| if owner.contains(':') { current } else { current.split(':').next().unwrap_or_default().to_string() });
+        match current {
+            Some(current) if &current == owner => {}
+            Some(current) => differences.push(Difference::changed("owner", current, owner.clone())),
+            None => unknown_owner = true,
+        }
+    }
+
+    let action = if differences.is_empty() { Action::NoOp } else { Action::Update };
+    let mut change = Change::new(ResourceKind::Path, &path, pod, action);
+    if unknown_owner {
+        change = change.with_reason("owner unknown");
+    }
+    change.differences = differences;
+    change
+}
+
+/// Compares the configured entrypoint, command, working directory and user
+fn process_differences(container: &Container, live: &ContainerInfo) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let desired = ContainerInfo::from_config(container, "");
+
+    let argv = [
+        ("entrypoint", container.entrypoint.is_some(), &live.entrypoint, &desired.entrypoint),
+        ("command", container.command_line().is_some(), &live.command, &desired.command),
+    ];
+    for (field, configured, current, desired) in argv {
+        if configured && current != desired {
+            differences.push(Difference::changed(field, shell_join(current), shell_join(desired)));
+        }
+    }
+
+    let strings = [
+        ("workdir", container.workdir.is_some(), &live.workdir, &desired.workdir),
+        ("user", container.user.is_some(), &live.user, &desired.user),
+    ];
+    for (field, configured, current, desired) in strings {
+        if configured && current != desired {
+            differences.push(Difference::changed(field, current.clone(), desired.clone()));
+        }
+    }
+
+    differences
+}
+
+/// Compares the configured restart policy, healthcheck and resource limits
+fn runtime_option_differences(container: &Container, live: &ContainerInfo, data_path: &str) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let desired = ContainerInfo::from_config(container, data_path);
+
+    if container.restart_policy.is_some() && live.restart_policy != desired.restart_policy {
+        differences.push(Difference::changed("restart_policy", live.restart_policy.clone(), desired.restart_policy.clone()));
+    }
+
+    if let (Some(health), Some(desired_health)) = (&container.healthcheck, &desired.healthcheck) {
+        match &live.healthcheck {
+            None => differences.push(Difference::added("healthcheck.command", health.command.clone())),
+            Some(current) => {
+                if current.command != desired_health.command {
+                    differences.push(Difference::changed("healthcheck.command", current.command.clone(), desired_health.command.clone()));
+                }
+                let timings = [
+                    ("healthcheck.interval", health.interval.is_some(), current.interval, desired_health.interval),
+                    ("healthcheck.timeout", health.timeout.is_some(), current.timeout, desired_health.timeout),
+                    ("healthcheck.start_period", health.start_period.is_some(), current.start_period, desired_health.start_period),
+                ];
+                for (field, configured, current, desired) in timings {
+                    if configured && current != desired {
+                        differences.push(Difference::changed(field, format_duration(current), format_duration(desired)));
+                    }
+                }
+                if health.retries.is_some() && current.retries != desired_health.retries {
+                    differences.push(Difference::changed(
+                        "healthcheck.retries", current.retries.to_string(), desired_health.retries.to_string()
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(resources) = &container.resources {
+        let unlimited = |value: String, zero: bool| if zero { "unlimited".to_string() } else { value };
+        if resources.memory.is_some() && live.memory != desired.memory {
+            differences.push(Difference::changed(
+                "resources.memory",
+                unlimited(format_memory(live.memory), live.memory == 0),
+                format_memory(desired.memory),
+            ));
+        }
+        if resources.cpus.is_some() && live.nano_cpus != desired.nano_cpus {
+            let cpus = |nano: u64| (nano as f64 / 1e9).to_string();
+            differences.push(Difference::changed(
+                "resources.cpus",
+                unlimited(cpus(live.nano_cpus), live.nano_cpus == 0),
+                cpus(desired.nano_cpus),
+            ));
+        }
+        if resources.pids_limit.is_some() && live.pids_limit != desired.pids_limit {
+            differences.push(Difference::changed("resources.pids_limit", live.pids_limit.to_string(), desired.pids_limit.to_string()));
+        }
+    }
+
+    differences
+}
+
+/// Plans the containers of a pod that already exists with the right ports
+fn plan_containers(rt: &dyn ContainerRuntime, pod: &Pod, live_pod: &PodInfo, data_path: &str) -> AppResult<Vec<Change>> {
+    let mut changes = Vec::new();
+
+    for container in &pod.containers {
+        let change = match rt.inspect_container(&container.name)? {
+            None => Change::new(ResourceKind::Container, &container.name, &pod.name, Action::Create),
+            Some(live) => {
+                let differences = container_differences(container, &live, data_path);
+                let in_pod = live_pod.containers.contains(&container.name);
+                let action = if differences.is_empty() && in_pod { Action::NoOp } else { Action::Recreate };
+                let mut change = Change::new(ResourceKind::Container, &container.name, &pod.name, action);
+                if !in_pod {
+                    change = change.with_reason("exists outside the pod");
+                }
+                change.differences = differences;
+                change
+            }
+        };
+        changes.push(change);
+    }
+
+    for name in &live_pod.containers {
+        if !pod.containers.iter().any(|container| &container.name == name) {
+            changes.push(
+                Change::new(ResourceKind::Container, name, &pod.name, Action::Remove)
+                    .with_reason("not in configuration"),
+            );
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Computes the plan for every pod in the configuration
+pub fn compute(rt: &dyn ContainerRuntime, config: &Config) -> AppResult<Plan> {
+    let mut changes = Vec::new();
+
+    for volume in &config.volumes {
+        let action = if rt.volume_exists(&volume.name) { Action::NoOp } else { Action::Create };
+        changes.push(Change::new(ResourceKind::Volume, &volume.name, "", action));
+    }
+
+    for secret in &config.secrets {
+        let action = if rt.secret_exists(&secret.name) { Action::NoOp } else { Action::Create };
+        changes.push(Change::new(ResourceKind::Secret, &secret.name, "", action));
+    }
+
+    for pod in &config.pods {
+        match rt.inspect_pod(&pod.name)? {
+            None => {
+                changes.push(Change::new(ResourceKind::Pod, &pod.name, &pod.name, Action::Create));
+                for container in &pod.containers {
+                    let mut change = Change::new(ResourceKind::Container, &container.name, &pod.name, Action::Create);
+                    if rt.container_exists(&container.name) {
+                        change.action = Action::Recreate;
+                        change = change.with_reason("exists outside the pod");
+                    }
+                    changes.push(change);
+                }
+            }
+            Some(live_pod) => {
+                let port_differences = pod_differences(pod, &live_pod);
+                if port_differences.is_empty() {
+                    changes.push(Change::new(ResourceKind::Pod, &pod.name, &pod.name, Action::NoOp));
+                    changes.extend(plan_containers(rt, pod, &live_pod, &config.data_path)?);
+                } else {
+                    let mut pod_change = Change::new(ResourceKind::Pod, &pod.name, &pod.name, Action::Recreate);
+                    pod_change.differences = port_differences;
+                    changes.push(pod_change);
+
+                    // Every container goes away with the pod, so report them
+                    // all as recreated alongside their own differences
+                    for mut change in plan_containers(rt, pod, &live_pod, &config.data_path)? {
+                        if change.action == Action::NoOp {
+                            change.action = Action::Recreate;
+                            change = change.with_reason("pod is recreated");
+                        }
+                        changes.push(change);
+                    }
+                }
+            }
+        }
+
+        // Containers may share a host path, which is only planned once
+        let mut paths = BTreeSet::new();
+        let binds = pod.containers.iter()
+            .flat_map(|container| container.mounts.iter().filter_map(Mount::spec))
+            .filter(|spec| spec.kind.is_bind() && spec.source.is_some())
+            .filter(|spec| spec.mode.is_some() || spec.owner.is_some());
+        for spec in binds {
+            if paths.insert(spec.host_path(&config.data_path)) {
+                changes.push(plan_host_path(rt, &spec, &pod.name, &config.data_path));
+            }
+        }
+    }
+
+    Ok(Plan::new(changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::MemoryRuntime;
+
+    const CONFIG: &str = r#"
+application_name: shop
+is_podman_installed: true
+data_path: /srv/shop
+volumes:
+  - name: cache
+secrets:
+  - name: token
+    env: TOKEN
+pods:
+  - name: web-pod
+    containers:
+      - name: web
+        image: nginx:1.25
+        env_vars:
+          NGINX_HOST: example.com
+        ports:
+          - "8080:80"
+        mounts:
+          - cache:/var/cache/nginx
+      - name: sidecar
+        image: alpine:3.19
+        env_vars: {}
+        ports: []
+        mounts: []
+"#;
+
+    fn config() -> Config {
+        serde_yaml::from_str(CONFIG).unwrap()
+    }
+
+    /// Creates the volumes, secrets, pods and containers of `config` like setup does
+    fn deploy(config: &Config) -> MemoryRuntime {
+        let rt = MemoryRuntime::new();
+        for volume in &config.volumes {
+            rt.create_volume(volume).unwrap();
+        }
+        for secret in &config.secrets {
+            rt.create_secret(&secret.name, "value").unwrap();
+        }
+        for pod in &config.pods {
+            rt.create_pod(&pod.name, &collect_pod_ports(pod)).unwrap();
+            for container in &pod.containers {
+                rt.create_container(&pod.name, container, &config.data_path).unwrap();
+            }
+        }
+        rt
+    }
+
+    fn actions(plan: &Plan) -> Vec<(ResourceKind, &str, Action)> {
+        plan.changes.iter().map(|change| (change.kind, change.name.as_str(), change.action)).collect()
+    }
+
+    fn find<'a>(plan: &'a Plan, name: &str) -> &'a Change {
+        plan.changes.iter().find(|change| change.name == name).unwrap()
+    }
+
+    #[test]
+    fn creates_everything_on_an_empty_host() {
+        let plan = compute(&MemoryRuntime::new(), &config()).unwrap();
+        assert_eq!(actions(&plan), [
+            (ResourceKind::Volume, "cache", Action::Create),
+            (ResourceKind::Secret, "token", Action::Create),
+            (ResourceKind::Pod, "web-pod", Action::Create),
+            (ResourceKind::Container, "web", Action::Create),
+            (ResourceKind::Container, "sidecar", Action::Create),
+        ]);
+        assert_eq!(plan.summary.create, 5);
+        assert!(plan.has_changes());
+    }
+
+    #[test]
+    fn leaves_a_matching_deployment_unchanged() {
+        let config = config();
+        let plan = compute(&deploy(&config), &config).unwrap();
+        assert!(plan.changes.iter().all(|change| change.action == Action::NoOp && change.differences.is_empty()));
+        assert_eq!(plan.summary.unchanged, 5);
+        assert!(!plan.has_changes());
+    }
+
+    #[test]
+    fn compares_images_by_their_normalized_name() {
+        let mut config = config();
+        let rt = deploy(&config);
+        config.pods[0].containers[1].image = "docker.io/library/alpine:3.19".to_string();
+        assert!(!compute(&rt, &config).unwrap().has_changes());
+
+        config.pods[0].containers[1].image = "docker.io/library/alpine:3.20".to_string();
+        let plan = compute(&rt, &config).unwrap();
+        let sidecar = find(&plan, "sidecar");
+        assert_eq!(sidecar.action, Action::Recreate);
+        assert_eq!(sidecar.differences.len(), 1);
+        assert_eq!(
+            (sidecar.differences[0].field.as_str(), sidecar.differences[0].current.as_deref(), sidecar.differences[0].desired.as_deref()),
+            ("image", Some("alpine:3.19"), Some("docker.io/library/alpine:3.20"))
+        );
+    }
+
+    #[test]
+    fn recreates_containers_whose_environment_drifted() {
+        let mut config = config();
+        let rt = deploy(&config);
+        let web = &mut config.pods[0].containers[0];
+        web.env_vars.insert("NGINX_HOST".to_string(), "example.org".to_string());
+        web.env_vars.insert("NGINX_PORT".to_string(), "80".to_string());
+
+        let plan = compute(&rt, &config).unwrap();
+        let web = find(&plan, "web");
+        assert_eq!(web.action, Action::Recreate);
+        let differences: Vec<(&str, Option<&str>, Option<&str>)> = web.differences.iter()
+            .map(|diff| (diff.field.as_str(), diff.current.as_deref(), diff.desired.as_deref()))
+            .collect();
+        assert_eq!(differences, [
+            ("env.NGINX_HOST", Some("example.com"), Some("example.org")),
+            ("env.NGINX_PORT", None, Some("80")),
+        ]);
+        assert_eq!(find(&plan, "sidecar").action, Action::NoOp);
+        assert_eq!((plan.summary.recreate, plan.summary.unchanged), (1, 4));
+    }
+
+    #[test]
+    fn recreates_the_pod_and_all_its_containers_when_ports_drift() {
+        let mut config = config();
+        let rt = deploy(&config);
+        config.pods[0].containers[0].ports = vec!["8081:80".to_string()];
+
+        let plan = compute(&rt, &config).unwrap();
+        let pod = find(&plan, "web-pod");
+        assert_eq!(pod.action, Action::Recreate);
+        let ports: Vec<(Option<&str>, Option<&str>)> = pod.differences.iter()
+            .map(|diff| (diff.current.as_deref(), diff.desired.as_deref()))
+            .collect();
+        assert_eq!(ports, [(Some("8080:80/tcp"), None), (None, Some("8081:80/tcp"))]);
+        for name in ["web", "sidecar"] {
+            let container = find(&plan, name);
+            assert_eq!((container.action, container.reason.as_deref()), (Action::Recreate, Some("pod is recreated")));
+        }
+    }
+
+    #[test]
+    fn removes_containers_dropped_from_the_configuration() {
+        let mut config = config();
+        let rt = deploy(&config);
+        config.pods[0].containers.pop();
+
+        let plan = compute(&rt, &config).unwrap();
+        let sidecar = find(&plan, "sidecar");
+        assert_eq!((sidecar.kind, sidecar.pod.as_str(), sidecar.action), (ResourceKind::Container, "web-pod", Action::Remove));
+        assert_eq!(sidecar.reason.as_deref(), Some("not in configuration"));
+        assert_eq!((plan.summary.remove, plan.summary.unchanged), (1, 4));
+    }
+
+    #[test]
+    fn compares_a_live_container_against_its_configuration() {
+        let config = config();
+        let container = &config.pods[0].containers[0];
+        let mut live = ContainerInfo::from_config(container, &config.data_path);
+        // Variables set by the image are not configured and not compared
+        live.env.insert("PATH".to_string(), "/usr/bin".to_string());
+        assert!(container_differences(container, &live, &config.data_path).is_empty());
+
+        live.env.remove("NGINX_HOST");
+        live.mounts.clear();
+        let fields: Vec<String> = container_differences(container, &live, &config.data_path).iter()
+            .map(|diff| format!("{} {:?} {:?}", diff.field, diff.current, diff.desired))
+            .collect();
+        assert_eq!(fields, [
+            "env.NGINX_HOST None Some(\"example.com\")",
+            "mount None Some(\"volume cache:/var/cache/nginx\")",
+        ]);
+    }
+
+    #[test]
+    fn renders_a_terraform_style_diff() {
+        let mut config = config();
+        let rt = deploy(&config);
+        config.pods[0].containers[0].image = "nginx:1.26".to_string();
+        config.pods[0].containers[0].env_vars.insert("NGINX_HOST".to_string(), "example.org".to_string());
+
+        assert_eq!(compute(&rt, &config).unwrap().render(), "  = volume \"cache\" is up to date
+  = secret \"token\" is up to date
+  = pod \"web-pod\" is up to date
+  ~ container \"web\" in pod \"web-pod\" will be recreated
+      ~ image: nginx:1.25 -> nginx:1.26
+      ~ env.NGINX_HOST: example.com -> example.org
+  = container \"sidecar\" in pod \"web-pod\" is up to date
+
+Plan: 0 to create, 0 to update, 1 to recreate, 0 to remove, 4 unchanged.
+");
+    }
+}
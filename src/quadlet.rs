@@ -0,0 +1,339 @@
+//! systemd Quadlet unit generation.
+//!
+//! Each pod becomes a `.pod` unit and each container a `.container` unit
+//! joined to it, so systemd recreates the whole deployment at boot. Named
+//! volumes become `.volume` units that the containers using them depend on.
+
+use crate::{AppResult, Config, Container, Mount, MountType, Pod, RestartPolicy, Volume};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Rootless Quadlet directory, relative to the home directory
+pub const USER_UNIT_DIR: &str = ".config/containers/systemd";
+/// Rootful Quadlet directory
+pub const SYSTEM_UNIT_DIR: &str = "/etc/containers/systemd";
+
+/// A generated unit file
+#[derive(Debug, Clone)]
+pub struct UnitFile {
+    /// File name including the extension (e.g., "web-pod.pod")
+    pub name: String,
+    pub contents: String,
+}
+
+/// Escapes a value for a systemd key: `%` starts a specifier such as `%h`, so
+/// a literal one is doubled, and values with whitespace or quotes are quoted
+/// with C-style escapes
+fn quote(value: &str) -> String {
+    let value = value.replace('%', "%%");
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+    } else {
+        value
+    }
+}
+
+/// Makes the data path absolute, since units are not run from the current directory
+pub fn absolute_data_path(data_path: &str) -> AppResult<String> {
+    let path = Path::new(data_path);
+    if path.is_absolute() {
+        return Ok(data_path.to_string());
+    }
+    let absolute = env::current_dir()?.join(path.strip_prefix("./").unwrap_or(path));
+    Ok(absolute.to_string_lossy().into_owned())
+}
+
+fn header(config: &Config) -> String {
+    format!("# Generated by podman_deploy for {}\n# Changes will be overwritten on the next generate run\n\n", config.application_name)
+}
+
+fn pod_unit(config: &Config, pod: &Pod) -> UnitFile {
+    let mut contents = header(config);
+    contents.push_str(&format!("[Unit]\nDescription={} pod ({})\n\n", pod.name, config.application_name));
+    contents.push_str(&format!("[Pod]\nPodName={}\n", pod.name));
+    for port in crate::collect_pod_ports(pod) {
+        contents.push_str(&format!("PublishPort={}\n", port));
+    }
+    contents.push_str("\n[Install]\nWantedBy=default.target\n");
+
+    UnitFile { name: format!("{}.pod", pod.name), contents }
+}
+
+fn container_unit(config: &Config, pod: &Pod, container: &Container, data_path: &str) -> UnitFile {
+    let mut contents = header(config);
+    contents.push_str(&format!("[Unit]\nDescription={} container in pod {}\n", container.name, pod.name));
+    if let Some(retries) = container.restart_policy.and_then(|policy| policy.max_retries()) {
+        contents.push_str(&format!("StartLimitBurst={}\n", retries));
+    }
+    contents.push('\n');
+    contents.push_str("[Container]\n");
+    contents.push_str(&format!("ContainerName={}\n", container.name));
+    contents.push_str(&format!("Image={}\n", container.image));
+    contents.push_str(&format!("Pod={}.pod\n", pod.name));
+
+    let mut env_vars: Vec<(&String, &String)> = container.env_vars.iter().collect();
+    env_vars.sort();
+    for (key, value) in env_vars {
+        contents.push_str(&format!("Environment={}\n", quote(&format!("{}={}", key, value))));
+    }
+
+    for spec in container.mounts.iter().filter_map(Mount::spec) {
+        // "<name>.volume" refers to the generated volume unit
+        let (key, mut value) = match spec.kind {
+            MountType::Bind | MountType::File | MountType::Dir => ("Volume", format!("{}:{}", spec.host_path(data_path), spec.target)),
+            MountType::Volume => ("Volume", format!("{}.volume:{}", spec.source.as_deref().unwrap_or_default(), spec.target)),
+            MountType::Tmpfs => ("Tmpfs", spec.target.clone()),
+        };
+        let options = spec.podman_options();
+        if !options.is_empty() {
+            value.push_str(&format!(":{}", options.join(",")));
+        }
+        contents.push_str(&format!("{}={}\n", key, quote(&value)));
+    }
+
+    for secret in &container.secrets {
+        contents.push_str(&format!("Secret={}\n", quote(&secret.spec().podman_arg())));
+    }
+
+    if let Some(health) = &container.healthcheck {
+        contents.push_str(&format!("HealthCmd={}\n", quote(&health.command)));
+        let timings = [
+            ("HealthInterval", &health.interval),
+            ("HealthTimeout", &health.timeout),
+            ("HealthStartPeriod", &health.start_period),
+        ];
+        for (key, value) in timings {
+            if let Some(value) = value {
+                contents.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        if let Some(retries) = health.retries {
+            contents.push_str(&format!("HealthRetries={}\n", retries));
+        }
+    }
+
+    if let Some(entrypoint) = container.entrypoint_arg() {
+        contents.push_str(&format!("Entrypoint={}\n", quote(&entrypoint)));
+    }
+    if let Some(command_line) = container.command_line() {
+        let exec: Vec<String> = command_line.iter().map(|arg| quote(arg)).collect();
+        contents.push_str(&format!("Exec={}\n", exec.join(" ")));
+    }
+    if let Some(workdir) = &container.workdir {
+        contents.push_str(&format!("WorkingDir={}\n", quote(workdir)));
+    }
+    if let Some(user) = &container.user {
+        match user.split_once(':') {
+            Some((user, group)) => contents.push_str(&format!("User={}\nGroup={}\n", user, group)),
+            None => contents.push_str(&format!("User={}\n", user)),
+        }
+    }
+
+    if let Some(resources) = &container.resources {
+        if let Some(memory) = &resources.memory {
+            contents.push_str(&format!("PodmanArgs=--memory={}\n", memory));
+        }
+        if let Some(cpus) = resources.cpus {
+            contents.push_str(&format!("PodmanArgs=--cpus={}\n", cpus));
+        }
+        if let Some(pids_limit) = resources.pids_limit {
+            contents.push_str(&format!("PodmanArgs=--pids-limit={}\n", pids_limit));
+        }
+    }
+
+    // systemd restarts the unit instead of Podman restarting the container
+    if let Some(policy) = container.restart_policy {
+        let restart = match policy {
+            RestartPolicy::No => "no",
+            RestartPolicy::Always | RestartPolicy::UnlessStopped => "always",
+            RestartPolicy::OnFailure(_) => "on-failure",
+        };
+        contents.push_str(&format!("\n[Service]\nRestart={}\n", restart));
+    }
+
+    UnitFile { name: format!("{}.container", container.name), contents }
+}
+
+fn volume_unit(config: &Config, volume: &Volume) -> UnitFile {
+    let mut contents = header(config);
+    contents.push_str(&format!("[Unit]\nDescription={} volume ({})\n\n", volume.name, config.application_name));
+    contents.push_str(&format!("[Volume]\nVolumeName={}\n", volume.name));
+    if let Some(driver) = &volume.driver {
+        contents.push_str(&format!("Driver={}\n", driver));
+    }
+    // Quadlet has dedicated keys for the local driver's options
+    for (key, value) in &volume.options {
+        match key.as_str() {
+            "device" => contents.push_str(&format!("Device={}\n", quote(value))),
+            "type" => contents.push_str(&format!("Type={}\n", value)),
+            "o" => contents.push_str(&format!("Options={}\n", value)),
+            _ => contents.push_str(&format!("PodmanArgs=--opt={}\n", quote(&format!("{}={}", key, value)))),
+        }
+    }
+    for (key, value) in &volume.labels {
+        contents.push_str(&format!("Label={}\n", quote(&format!("{}={}", key, value))));
+    }
+
+    UnitFile { name: format!("{}.volume", volume.name), contents }
+}
+
+/// Generates the unit files for every volume, pod and container in the configuration
+pub fn generate(config: &Config) -> AppResult<Vec<UnitFile>> {
+    let data_path = absolute_data_path(&config.data_path)?;
+    let mut units: Vec<UnitFile> = config.volumes.iter().map(|volume| volume_unit(config, volume)).collect();
+
+    for pod in &config.pods {
+        units.push(pod_unit(config, pod));
+        for container in &pod.containers {
+            units.push(container_unit(config, pod, container, &data_path));
+        }
+    }
+
+    Ok(units)
+}
+
+/// Returns the Quadlet directory for the current user (rootful for root)
+pub fn install_dir(rootful: bool) -> PathBuf {
+    if rootful {
+        PathBuf::from(SYSTEM_UNIT_DIR)
+    } else {
+        Path::new(&env::var("HOME").unwrap_or_default()).join(USER_UNIT_DIR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+application_name: shop
+is_podman_installed: true
+data_path: /srv/shop
+volumes:
+  - name: cache
+    options:
+      device: /mnt/my disk
+      o: bind
+      custom: a b
+    labels:
+      team: web ops
+pods:
+  - name: web-pod
+    containers:
+      - name: web
+        image: nginx:1.25
+        env_vars:
+          GREETING: hello world
+          RATE: 100%
+          QUOTE: say "hi"
+        ports:
+          - "8080:80"
+        mounts:
+          - /html:/usr/share/nginx/html:ro
+          - cache:/var/cache/nginx
+          - type: tmpfs
+            target: /tmp
+            size: 64m
+        secrets:
+          - api_key
+        command: ["sh", "-c", "echo 'up 100%' && exec nginx"]
+        args: ["--root", 'C:\www']
+        workdir: /srv/my app
+        user: "1000:1000"
+        restart_policy: on-failure:3
+        healthcheck:
+          command: curl -f http://localhost/
+          interval: 30s
+          retries: 3
+        resources:
+          memory: 512m
+"#;
+
+    const HEADER: &str = "# Generated by podman_deploy for shop\n# Changes will be overwritten on the next generate run\n\n";
+
+    fn units() -> Vec<UnitFile> {
+        generate(&serde_yaml::from_str(CONFIG).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn quotes_only_values_that_need_it() {
+        assert_eq!(quote("nginx:1.25"), "nginx:1.25");
+        assert_eq!(quote(""), r#""""#);
+        assert_eq!(quote("hello world"), r#""hello world""#);
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote(r"C:\www"), r#""C:\\www""#);
+        assert_eq!(quote("one\ntwo"), r#""one\ntwo""#);
+    }
+
+    #[test]
+    fn doubles_percent_signs_so_systemd_does_not_expand_them() {
+        assert_eq!(quote("100%"), "100%%");
+        assert_eq!(quote("%h is home"), r#""%%h is home""#);
+    }
+
+    #[test]
+    fn generates_volume_pod_and_container_units_in_order() {
+        let names: Vec<String> = units().into_iter().map(|unit| unit.name).collect();
+        assert_eq!(names, ["cache.volume", "web-pod.pod", "web.container"]);
+    }
+
+    #[test]
+    fn pod_unit_publishes_the_ports_of_its_containers() {
+        assert_eq!(units()[1].contents, format!("{}{}", HEADER, "\
+[Unit]
+Description=web-pod pod (shop)
+
+[Pod]
+PodName=web-pod
+PublishPort=8080:80
+
+[Install]
+WantedBy=default.target
+"));
+    }
+
+    #[test]
+    fn volume_unit_quotes_options_and_labels() {
+        assert_eq!(units()[0].contents, format!("{}{}", HEADER, r#"[Unit]
+Description=cache volume (shop)
+
+[Volume]
+VolumeName=cache
+PodmanArgs=--opt="custom=a b"
+Device="/mnt/my disk"
+Options=bind
+Label="team=web ops"
+"#));
+    }
+
+    #[test]
+    fn container_unit_escapes_exec_and_environment_values() {
+        assert_eq!(units()[2].contents, format!("{}{}", HEADER, r#"[Unit]
+Description=web container in pod web-pod
+StartLimitBurst=3
+
+[Container]
+ContainerName=web
+Image=nginx:1.25
+Pod=web-pod.pod
+Environment="GREETING=hello world"
+Environment="QUOTE=say \"hi\""
+Environment=RATE=100%%
+Volume=/srv/shop/html:/usr/share/nginx/html:ro
+Volume=cache.volume:/var/cache/nginx
+Tmpfs=/tmp:size=64m
+Secret=api_key
+HealthCmd="curl -f http://localhost/"
+HealthInterval=30s
+HealthRetries=3
+Exec=sh -c "echo 'up 100%%' && exec nginx" --root "C:\\www"
+WorkingDir="/srv/my app"
+User=1000
+Group=1000
+PodmanArgs=--memory=512m
+
+[Service]
+Restart=on-failure
+"#));
+    }
+}
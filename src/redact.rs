@@ -0,0 +1,86 @@
+//! Redaction of secret values from printed output.
+//!
+//! Secret values and the registry password are registered when the config
+//! is loaded. Every command line printed through
+//! [`shell_join`](crate::shell_join) passes through [`redact`], so a secret
+//! that also ended up in an environment variable or argument is masked in
+//! "Executing command:" lines, `--dry-run` output, plans and printed errors.
+
+use std::sync::Mutex;
+
+/// Replacement for redacted values, as used for passwords in dry-run output
+pub const MASK: &str = "********";
+
+static SECRET_VALUES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Registers a value that must never be printed
+pub fn register(value: &str) {
+    // Surrounding whitespace is usually a trailing newline in a secret file
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+    let mut values = SECRET_VALUES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !values.iter().any(|known| known == value) {
+        values.push(value.to_string());
+        // Longest first, so a secret containing another is masked as a whole
+        values.sort_by_key(|known| std::cmp::Reverse(known.len()));
+    }
+}
+
+/// Masks every registered value in `text`
+pub fn redact(text: &str) -> String {
+    let values = SECRET_VALUES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    values.iter().fold(text.to_string(), |text, value| text.replace(value.as_str(), MASK))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+
+    // Registered values are global to the process, so every test uses its own
+
+    #[test]
+    fn masks_every_occurrence_of_a_registered_value() {
+        register("hunter2-masks");
+        assert_eq!(redact("hunter2-masks and hunter2-masks again"), "******** and ******** again");
+        assert_eq!(redact("nothing to hide"), "nothing to hide");
+    }
+
+    #[test]
+    fn ignores_surrounding_whitespace_and_empty_values() {
+        register("  file-secret-trim\n");
+        register(" \n");
+        assert_eq!(redact("value=file-secret-trim"), "value=********");
+        assert_eq!(redact("  spaced  "), "  spaced  ");
+    }
+
+    #[test]
+    fn masks_a_value_containing_another_as_a_whole() {
+        register("nested-pw");
+        register("outer-nested-pw-value");
+        assert_eq!(redact("outer-nested-pw-value / nested-pw"), "******** / ********");
+    }
+
+    #[test]
+    fn masks_passwords_in_displayed_commands() {
+        register("cmd-pa ss");
+        let args: Vec<String> = ["podman", "login", "--password", "cmd-pa ss", "--password=cmd-pa ss"]
+            .iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(crate::shell_join(&args), "podman login --password '********' '--password=********'");
+    }
+
+    #[test]
+    fn masks_passwords_in_printed_errors() {
+        register("err-pw-123");
+        let error = AppError::registry_login(
+            "ghcr.io",
+            AppError::context("Failed to run podman login --password err-pw-123", AppError::Message("denied for err-pw-123".to_string())),
+        );
+        assert_eq!(
+            crate::error_chain(&error),
+            "Error: Failed to login to registry: ghcr.io\n  Caused by: Failed to run podman login --password ********\n  Caused by: denied for ********"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! containers-registries.conf drop-in generation.
+//!
+//! Short image names such as `nginx:1.21` are resolved through the
+//! `unqualified-search-registries` of each host's registries.conf, so the
+//! same config can pull different images on different machines. `setup`
+//! writes a drop-in that pins the search registries and declares the
+//! mirrors, TLS settings and blocked state of the configured registries.
+
+use crate::{image_registry, Config};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Rootless drop-in directory, relative to the user's config directory
+pub const USER_DROP_IN_DIR: &str = "containers/registries.conf.d";
+/// Rootful drop-in directory
+pub const SYSTEM_DROP_IN_DIR: &str = "/etc/containers/registries.conf.d";
+
+/// Returns the drop-in directory for the current user (rootful for root).
+///
+/// Like Podman, a rootless user's config directory is `$XDG_CONFIG_HOME`,
+/// or `~/.config` when that is unset or not an absolute path.
+pub fn drop_in_dir(rootful: bool) -> PathBuf {
+    if rootful {
+        return PathBuf::from(SYSTEM_DROP_IN_DIR);
+    }
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| Path::new(&env::var("HOME").unwrap_or_default()).join(".config"));
+    config_home.join(USER_DROP_IN_DIR)
+}
+
+/// Drop-in file name, unique per application so several deployments can share a host.
+///
+/// Characters outside `[A-Za-z0-9_.-]` in the application name become `_`, so
+/// the name can never leave the drop-in directory.
+pub fn file_name(config: &Config) -> String {
+    let name: String = config.application_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') { c } else { '_' })
+        .collect();
+    format!("50-podman_deploy-{}.conf", name)
+}
+
+/// Returns true for image names without a registry host, which Podman resolves
+/// through `unqualified-search-registries`
+pub fn is_short_name(image: &str) -> bool {
+    image_registry(image) == "docker.io" && !image.starts_with("docker.io/")
+}
+
+/// Quotes a value as a TOML basic string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Generates the drop-in, or None if the config declares nothing for it
+pub fn generate(config: &Config) -> Option<String> {
+    let mut contents = String::new();
+    if !config.unqualified_search_registries.is_empty() {
+        let registries: Vec<String> = config.unqualified_search_registries.iter().map(|registry| quote(registry)).collect();
+        contents.push_str(&format!("unqualified-search-registries = [{}]\n", registries.join(", ")));
+    }
+
+    for registry in config.registries() {
+        let insecure = !registry.tls_verify();
+        if !insecure && !registry.blocked && registry.mirrors.is_empty() {
+            continue;
+        }
+        contents.push_str(&format!("\n[[registry]]\nlocation = {}\n", quote(&registry.host)));
+        if insecure {
+            contents.push_str("insecure = true\n");
+        }
+        if registry.blocked {
+            contents.push_str("blocked = true\n");
+        }
+        for mirror in &registry.mirrors {
+            contents.push_str(&format!("\n[[registry.mirror]]\nlocation = {}\n", quote(mirror)));
+            // Mirrors are pulled with the TLS setting of their registry
+            if insecure {
+                contents.push_str("insecure = true\n");
+            }
+        }
+    }
+
+    if contents.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "# Generated by podman_deploy for {}\n# Changes will be overwritten on the next setup run\n\n{}",
+        config.application_name,
+        contents.trim_start_matches('\n')
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: &str) -> Config {
+        let yaml = format!("application_name: shop\nis_podman_installed: true\ndata_path: /srv/shop\npods: []\n{}", extra);
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    const HEADER: &str = "# Generated by podman_deploy for shop\n# Changes will be overwritten on the next setup run\n\n";
+
+    #[test]
+    fn nothing_to_declare_generates_nothing() {
+        assert_eq!(generate(&config("")), None);
+        // Credentials alone are handled by `podman login`
+        assert_eq!(generate(&config("registries:\n  - host: ghcr.io\n    username: me\n")), None);
+    }
+
+    #[test]
+    fn pins_the_search_registries() {
+        let contents = generate(&config("unqualified_search_registries: [docker.io, quay.io]\n")).unwrap();
+        assert_eq!(contents, format!("{}unqualified-search-registries = [\"docker.io\", \"quay.io\"]\n", HEADER));
+    }
+
+    #[test]
+    fn declares_insecure_blocked_and_mirrored_registries() {
+        let contents = generate(&config(r#"
+unqualified_search_registries: [harbor.local:5000]
+registries:
+  - host: harbor.local:5000
+    tls_verify: false
+    mirrors: [mirror.local/harbor]
+  - host: docker.io
+    blocked: true
+"#)).unwrap();
+        let expected = "unqualified-search-registries = [\"harbor.local:5000\"]\n\
+            \n[[registry]]\nlocation = \"harbor.local:5000\"\ninsecure = true\n\
+            \n[[registry.mirror]]\nlocation = \"mirror.local/harbor\"\ninsecure = true\n\
+            \n[[registry]]\nlocation = \"docker.io\"\nblocked = true\n";
+        assert_eq!(contents, format!("{}{}", HEADER, expected));
+    }
+
+    #[test]
+    fn drops_the_leading_blank_line_without_search_registries() {
+        let contents = generate(&config("registries:\n  - host: quay.io\n    mirrors: [mirror.local/quay]\n")).unwrap();
+        assert_eq!(contents, format!("{}[[registry]]\nlocation = \"quay.io\"\n\n[[registry.mirror]]\nlocation = \"mirror.local/quay\"\n", HEADER));
+    }
+
+    #[test]
+    fn file_name_keeps_only_safe_characters() {
+        assert_eq!(file_name(&config("")), "50-podman_deploy-shop.conf");
+        let mut spaced = config("");
+        spaced.application_name = "My App/../v1.2".to_string();
+        assert_eq!(file_name(&spaced), "50-podman_deploy-My_App_.._v1.2.conf");
+    }
+
+    #[test]
+    fn quotes_values_as_toml_strings() {
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+
+    #[test]
+    fn short_names_have_no_registry_host() {
+        assert!(is_short_name("nginx:1.21"));
+        assert!(is_short_name("library/nginx"));
+        assert!(!is_short_name("docker.io/library/nginx"));
+        assert!(!is_short_name("ghcr.io/org/app"));
+        assert!(!is_short_name("localhost/app"));
+    }
+}
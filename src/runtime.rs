@@ -0,0 +1,400 @@
+//! Container runtime backends.
+//!
+//! Every pod, container and image operation goes through the
+//! [`ContainerRuntime`] trait so the deployment modes do not depend on how
+//! Podman is reached. The default backend shells out to the `podman` CLI,
+//! and the API backend talks to the libpod REST service over its unix
+//! socket. [`DryRunRuntime`] wraps any backend for `--dry-run`. Tests run
+//! the modes against an in-memory backend that keeps all state in process.
+
+mod api;
+mod cli;
+mod commands;
+mod dry_run;
+mod inspect;
+#[cfg(test)]
+mod memory;
+
+pub use api::ApiRuntime;
+pub use cli::CliRuntime;
+pub use dry_run::DryRunRuntime;
+#[cfg(test)]
+pub use memory::MemoryRuntime;
+
+use crate::units::{format_memory, parse_duration, parse_memory};
+use crate::{AppResult, Container, ContainerSecret, Mount, MountType, SecretType, Volume};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable used to select the runtime backend (same as `--runtime`)
+pub const RUNTIME_ENV_VAR: &str = "PODMAN_DEPLOY_RUNTIME";
+
+/// Pod status as reported by the runtime
+#[derive(Debug, Clone)]
+pub struct PodStatus {
+    /// Pod name
+    pub name: String,
+    /// Human readable status (e.g., "Running")
+    pub status: String,
+}
+
+/// Container status as reported by the runtime
+#[derive(Debug, Clone)]
+pub struct ContainerStatus {
+    /// Container name
+    pub name: String,
+    /// Human readable status (e.g., "Up 5 minutes")
+    pub status: String,
+    /// Image the container was created from
+    pub image: String,
+}
+
+/// Run state of a container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerState {
+    pub running: bool,
+    /// Exit code of the last run, 0 while running
+    pub exit_code: i32,
+}
+
+/// Identity of a local image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// Image ID, the digest of the image config; the same on every host for the same image
+    pub id: String,
+    /// Digest of the manifest the image was pulled by (e.g., "sha256:...")
+    pub digest: String,
+}
+
+/// Mount as reported by container inspection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub kind: MountType,
+    /// Absolute host path for bind mounts, volume name for volumes, empty for tmpfs
+    pub source: String,
+    /// Path inside the container
+    pub destination: String,
+    pub read_only: bool,
+    /// SELinux relabel option ("z" or "Z"), if any
+    pub selinux: Option<String>,
+    /// Size limit of a tmpfs mount in bytes
+    pub size: Option<u64>,
+}
+
+impl MountInfo {
+    /// Resolves a configured mount, prefixing bind mount host paths with the data path
+    pub fn from_config(mount: &Mount, data_path: &str) -> Option<MountInfo> {
+        let spec = mount.spec()?;
+        let source = match spec.kind {
+            MountType::Bind | MountType::File | MountType::Dir => spec.host_path(data_path),
+            MountType::Volume => spec.source.clone().unwrap_or_default(),
+            MountType::Tmpfs => String::new(),
+        };
+        // Podman only reports bind mounts, not whether the host path is a file
+        let kind = if spec.kind.is_bind() { MountType::Bind } else { spec.kind };
+        Some(MountInfo {
+            kind,
+            source,
+            destination: spec.target,
+            read_only: spec.read_only,
+            selinux: spec.selinux.map(|label| label.option().to_string()),
+            size: spec.size.as_deref().and_then(parse_memory),
+        })
+    }
+
+    /// Mount options in Podman's comma-separated form (e.g., "ro", "Z", "size=64m")
+    fn options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if self.read_only {
+            options.push("ro".to_string());
+        }
+        options.extend(self.selinux.clone());
+        if let Some(size) = self.size {
+            options.push(format!("size={}", format_memory(size)));
+        }
+        options
+    }
+}
+
+impl fmt::Display for MountInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            MountType::Bind | MountType::File | MountType::Dir => write!(f, "{}:{}", self.source, self.destination)?,
+            MountType::Volume => write!(f, "volume {}:{}", self.source, self.destination)?,
+            MountType::Tmpfs => write!(f, "tmpfs {}", self.destination)?,
+        }
+        let options = self.options();
+        if !options.is_empty() {
+            write!(f, " ({})", options.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Live container state used for drift detection
+#[derive(Debug, Clone)]
+pub struct ContainerInfo {
+    /// Image the container was created from
+    pub image: String,
+    /// ID of that image, empty if unknown
+    pub image_id: String,
+    /// Environment variables, including those set by the image
+    pub env: HashMap<String, String>,
+    /// Bind mounts, named volumes and tmpfs mounts (anonymous volumes are left out)
+    pub mounts: Vec<MountInfo>,
+    /// Restart policy in config form (e.g., "no", "on-failure:3")
+    pub restart_policy: String,
+    /// Healthcheck, if the container has one
+    pub healthcheck: Option<HealthInfo>,
+    /// Memory limit in bytes, 0 if unlimited
+    pub memory: u64,
+    /// CPU limit in billionths of a CPU, 0 if unlimited
+    pub nano_cpus: u64,
+    /// Process limit, 0 if the runtime default applies
+    pub pids_limit: i64,
+    /// Entrypoint, empty if the image has none
+    pub entrypoint: Vec<String>,
+    /// Arguments passed to the entrypoint (the image's CMD unless overridden)
+    pub command: Vec<String>,
+    /// Working directory, empty for the image default
+    pub workdir: String,
+    /// User the process runs as, empty for the image default
+    pub user: String,
+    /// Names of the secrets mounted as files, sorted (Podman does not report environment secrets)
+    pub secrets: Vec<String>,
+}
+
+/// Healthcheck with every timing resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthInfo {
+    pub command: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub retries: u32,
+    pub start_period: Duration,
+}
+
+/// Podman's healthcheck defaults for unset timings
+const DEFAULT_HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_HEALTH_RETRIES: u32 = 3;
+
+impl ContainerInfo {
+    /// Returns the state a container created from this configuration will have
+    pub fn from_config(container: &Container, data_path: &str) -> ContainerInfo {
+        let resources = container.resources.clone().unwrap_or_default();
+        ContainerInfo {
+            image: container.image.clone(),
+            image_id: String::new(),
+            env: container.env_vars.clone(),
+            mounts: container.mounts.iter()
+                .filter_map(|mount| MountInfo::from_config(mount, data_path))
+                .collect(),
+            restart_policy: container.restart_policy.map(|policy| policy.to_string()).unwrap_or_else(|| "no".to_string()),
+            healthcheck: container.healthcheck.as_ref().map(|health| {
+                let duration = |value: &Option<String>| value.as_deref().and_then(parse_duration);
+                HealthInfo {
+                    command: health.command.clone(),
+                    interval: duration(&health.interval).unwrap_or(DEFAULT_HEALTH_INTERVAL),
+                    timeout: duration(&health.timeout).unwrap_or(DEFAULT_HEALTH_TIMEOUT),
+                    retries: health.retries.unwrap_or(DEFAULT_HEALTH_RETRIES),
+                    start_period: duration(&health.start_period).unwrap_or_default(),
+                }
+            }),
+            memory: resources.memory.as_deref().and_then(parse_memory).unwrap_or_default(),
+            nano_cpus: resources.cpus.map(|cpus| (cpus * 1e9).round() as u64).unwrap_or_default(),
+            pids_limit: resources.pids_limit.unwrap_or_default(),
+            entrypoint: container.entrypoint.clone().unwrap_or_default(),
+            command: container.command_line().unwrap_or_default(),
+            workdir: container.workdir.clone().unwrap_or_default(),
+            user: container.user.clone().unwrap_or_default(),
+            secrets: mounted_secrets(container),
+        }
+    }
+}
+
+/// Names of a container's secrets that are mounted as files, sorted
+pub fn mounted_secrets(container: &Container) -> Vec<String> {
+    let mut secrets: Vec<String> = container.secrets.iter()
+        .map(ContainerSecret::spec)
+        .filter(|spec| spec.kind == SecretType::Mount)
+        .map(|spec| spec.name)
+        .collect();
+    secrets.sort();
+    secrets
+}
+
+/// Live pod state used for drift detection
+#[derive(Debug, Clone)]
+pub struct PodInfo {
+    /// Published ports in canonical form (see [`PortMapping`]), sorted
+    pub ports: Vec<String>,
+    /// Names of the containers in the pod, excluding the infra container
+    pub containers: Vec<String>,
+}
+
+/// Parsed "[host_ip:]host_port:container_port[/protocol]" port mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMapping {
+    pub host_ip: String,
+    pub host_port: u16,
+    pub container_port: u16,
+    pub protocol: String,
+}
+
+impl PortMapping {
+    /// Parses a port mapping; a bare port publishes the same port on the host
+    pub fn parse(port: &str) -> Option<PortMapping> {
+        let (ports, protocol) = port.split_once('/').unwrap_or((port, "tcp"));
+        let mut parts: Vec<&str> = ports.rsplitn(3, ':').collect();
+        parts.reverse();
+
+        let (host_ip, host_port, container_port) = match parts.as_slice() {
+            [container] => ("", *container, *container),
+            [host, container] => ("", *host, *container),
+            [ip, host, container] => (*ip, *host, *container),
+            _ => return None,
+        };
+
+        Some(PortMapping {
+            host_ip: host_ip.to_string(),
+            host_port: host_port.parse().ok()?,
+            container_port: container_port.parse().ok()?,
+            protocol: protocol.to_string(),
+        })
+    }
+}
+
+/// Canonical form used to compare configured and live ports
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.host_ip.is_empty() {
+            write!(f, "{}:", self.host_ip)?;
+        }
+        write!(f, "{}:{}/{}", self.host_port, self.container_port, self.protocol)
+    }
+}
+
+/// Returns the canonical form of a configured port mapping, or the input unchanged if it does not parse
+pub fn normalize_port(port: &str) -> String {
+    PortMapping::parse(port)
+        .map(|mapping| mapping.to_string())
+        .unwrap_or_else(|| port.to_string())
+}
+
+/// Operations the deployment modes need from a container engine
+pub trait ContainerRuntime {
+    /// Returns true if the runtime can be reached
+    fn is_available(&self) -> bool;
+
+    /// Returns true if a pod with the given name exists
+    fn pod_exists(&self, pod_name: &str) -> bool;
+
+    /// Creates a pod publishing the given "host_port:container_port" mappings
+    fn create_pod(&self, pod_name: &str, ports: &[String]) -> AppResult<()>;
+
+    /// Starts a pod and all of its containers
+    fn start_pod(&self, pod_name: &str) -> AppResult<()>;
+
+    /// Stops a pod and all of its containers
+    fn stop_pod(&self, pod_name: &str) -> AppResult<()>;
+
+    /// Removes a pod together with all of its containers, stopping them if needed
+    fn remove_pod(&self, pod_name: &str) -> AppResult<()>;
+
+    /// Lists all pods known to the runtime
+    fn list_pods(&self) -> AppResult<Vec<PodStatus>>;
+
+    /// Returns true if a container with the given name exists
+    fn container_exists(&self, container_name: &str) -> bool;
+
+    /// Creates and starts a container inside an existing pod
+    fn create_container(&self, pod_name: &str, container: &Container, data_path: &str) -> AppResult<()>;
+
+    /// Stops a running container
+    fn stop_container(&self, container_name: &str) -> AppResult<()>;
+
+    /// Removes a stopped container
+    fn remove_container(&self, container_name: &str) -> AppResult<()>;
+
+    /// Returns the image a container was created from, if it can be determined
+    fn container_image(&self, container_name: &str) -> Option<String>;
+
+    /// Returns the live state of a container, or None if it does not exist
+    fn inspect_container(&self, container_name: &str) -> AppResult<Option<ContainerInfo>>;
+
+    /// Returns whether a container is running, or None if it does not exist
+    fn container_state(&self, container_name: &str) -> AppResult<Option<ContainerState>>;
+
+    /// Runs a container's healthcheck once and returns true if it passed
+    fn run_healthcheck(&self, container_name: &str) -> AppResult<bool>;
+
+    /// Starts a stopped container
+    fn start_container(&self, container_name: &str) -> AppResult<()>;
+
+    /// Renames a container, keeping its pod and state
+    fn rename_container(&self, container_name: &str, new_name: &str) -> AppResult<()>;
+
+    /// Returns the live state of a pod, or None if it does not exist
+    fn inspect_pod(&self, pod_name: &str) -> AppResult<Option<PodInfo>>;
+
+    /// Lists all containers, running or not
+    fn list_containers(&self) -> AppResult<Vec<ContainerStatus>>;
+
+    /// Pulls an image from its registry, skipping TLS certificate verification if `tls_verify` is false
+    fn pull_image(&self, image: &str, tls_verify: bool) -> AppResult<()>;
+
+    /// Adds another name to a local image
+    fn tag_image(&self, image: &str, target: &str) -> AppResult<()>;
+
+    /// Returns the ID and digest of a local image, or None if it has not been pulled
+    fn inspect_image(&self, image: &str) -> AppResult<Option<ImageInfo>>;
+
+    /// Creates (or replaces) the pods described by a Kubernetes YAML manifest
+    fn play_kube(&self, manifest: &str) -> AppResult<()>;
+
+    /// Removes all unused images
+    fn prune_images(&self) -> AppResult<()>;
+
+    /// Returns true if a named volume exists
+    fn volume_exists(&self, volume_name: &str) -> bool;
+
+    /// Creates a named volume with its driver, options and labels
+    fn create_volume(&self, volume: &Volume) -> AppResult<()>;
+
+    /// Returns true if a Podman secret exists
+    fn secret_exists(&self, secret_name: &str) -> bool;
+
+    /// Creates a secret; implementations must never print or log `value`
+    fn create_secret(&self, secret_name: &str, value: &str) -> AppResult<()>;
+
+    /// Returns true if there are stored credentials for the registry
+    fn is_logged_in(&self, registry: &str) -> bool;
+
+    /// Logs into a registry with the given credentials
+    fn login(&self, registry: &str, username: &str, password: &str, tls_verify: bool) -> AppResult<()>;
+
+    /// Returns the "uid:gid" owner of a host path as a bind mount `owner` is
+    /// applied, or None if the backend cannot tell
+    fn path_owner(&self, path: &Path) -> Option<String>;
+}
+
+/// Returns the "uid:gid" owner of a path from its metadata, which matches the
+/// container's view only when running as root
+fn metadata_owner(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(format!("{}:{}", metadata.uid(), metadata.gid()))
+}
+
+/// Creates a runtime backend by name ("cli" or "api")
+pub fn by_name(name: &str) -> AppResult<Box<dyn ContainerRuntime>> {
+    match name {
+        "cli" => Ok(Box::new(CliRuntime)),
+        "api" => Ok(Box::new(ApiRuntime::new(ApiRuntime::default_socket()))),
+        other => Err(format!("Unknown runtime backend '{}' (expected 'cli' or 'api')", other).into()),
+    }
+}
@@ -0,0 +1,828 @@
+use super::inspect::{parse_one, ContainerInspect, ImageInspect, PodInspect};
+use super::{metadata_owner, CliRuntime, ContainerInfo, ContainerRuntime, ContainerState, ContainerStatus, ImageInfo, PodInfo, PodStatus, PortMapping};
+use crate::error::AppError;
+use crate::{AppResult, Container, ContainerSecret, Mount, MountType, SecretType, Volume};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+/// Version prefix for libpod endpoints; Podman accepts any version >= 4 here
+const API_PREFIX: &str = "/v4.0.0/libpod";
+
+/// Pod entry returned by `GET /libpod/pods/json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiPod {
+    pub name: String,
+    #[serde(default)]
+    pub status: String,
+}
+
+/// Container entry returned by `GET /libpod/containers/json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiContainer {
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub state: String,
+}
+
+/// One line of the progress stream returned by `POST /libpod/images/pull`
+#[derive(Debug, Deserialize)]
+pub struct ApiPullReport {
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+/// Result returned by `GET /libpod/containers/{name}/healthcheck`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiHealthCheck {
+    #[serde(default)]
+    pub status: String,
+}
+
+/// Error body returned by libpod for non-2xx responses
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    cause: String,
+}
+
+/// Raw HTTP response from the API socket
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    fn json<T: for<'de> Deserialize<'de>>(&self) -> AppResult<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    /// Extracts the libpod error message, falling back to the raw body
+    fn error_message(&self) -> String {
+        match serde_json::from_slice::<ApiError>(&self.body) {
+            Ok(err) if !err.message.is_empty() => err.message,
+            Ok(err) if !err.cause.is_empty() => err.cause,
+            _ => format!("HTTP {}: {}", self.status, self.text().trim()),
+        }
+    }
+}
+
+/// Runtime backend that talks to the libpod REST API over a unix socket.
+///
+/// Registry credentials live in the client-side auth file, so login checks
+/// are delegated to the `podman` CLI.
+pub struct ApiRuntime {
+    socket: PathBuf,
+}
+
+impl ApiRuntime {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        ApiRuntime { socket: socket.into() }
+    }
+
+    /// Locates the Podman API socket.
+    ///
+    /// `CONTAINER_HOST=unix://<path>` takes precedence, followed by the
+    /// rootless socket under `$XDG_RUNTIME_DIR` and the rootful socket.
+    pub fn default_socket() -> PathBuf {
+        if let Ok(host) = env::var("CONTAINER_HOST")
+            && let Some(path) = host.strip_prefix("unix://")
+        {
+            return PathBuf::from(path);
+        }
+
+        if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
+            let rootless = Path::new(&runtime_dir).join("podman/podman.sock");
+            if rootless.exists() {
+                return rootless;
+            }
+        }
+
+        PathBuf::from("/run/podman/podman.sock")
+    }
+
+    /// Sends a single HTTP/1.1 request and reads the whole response
+    fn request(&self, method: &str, path: &str, body: Option<&Value>) -> AppResult<Response> {
+        let payload = body.map(|b| ("application/json", b.to_string()));
+        self.request_raw(method, path, payload.as_ref().map(|(kind, text)| (*kind, text.as_str())))
+    }
+
+    /// Sends a request with an arbitrary (content type, body) payload
+    fn request_raw(&self, method: &str, path: &str, payload: Option<(&str, &str)>) -> AppResult<Response> {
+        let mut stream = UnixStream::connect(&self.socket).map_err(|e| match e.kind() {
+            ErrorKind::NotFound | ErrorKind::ConnectionRefused => AppError::PodmanMissing(format!(
+                "Podman API socket {} is not reachable ({})", self.socket.display(), e
+            )),
+            _ => AppError::context(format!("Failed to connect to Podman API socket {}", self.socket.display()), e),
+        })?;
+
+        let body = payload.map(|(_, body)| body).unwrap_or_default();
+        let mut request = format!(
+            "{} {}{} HTTP/1.1\r\nHost: d\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method, API_PREFIX, path, body.len()
+        );
+        if let Some((content_type, _)) = payload {
+            request.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes())?;
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw)?;
+        parse_response(&raw)
+    }
+
+    /// Sends a request, wrapping the libpod error of a non-2xx status with `error`
+    fn expect_success(&self, method: &str, path: &str, body: Option<&Value>, error: impl FnOnce(AppError) -> AppError) -> AppResult<Response> {
+        let response = self.request(method, path, body)?;
+        if response.is_success() {
+            Ok(response)
+        } else {
+            Err(error(AppError::Message(response.error_message())))
+        }
+    }
+
+    /// Sends a start or stop request. libpod answers 304 Not Modified when the
+    /// pod or container is already in that state, which counts as success.
+    fn change_state(&self, path: &str, error: impl FnOnce(AppError) -> AppError) -> AppResult<()> {
+        let response = self.request("POST", path, None)?;
+        if response.is_success() || response.status == 304 {
+            Ok(())
+        } else {
+            Err(error(AppError::Message(response.error_message())))
+        }
+    }
+
+    /// Returns true if an `exists` endpoint answers 204 No Content
+    fn exists(&self, path: &str) -> bool {
+        matches!(self.request("GET", path, None), Ok(response) if response.status == 204)
+    }
+}
+
+fn parse_response(raw: &[u8]) -> AppResult<Response> {
+    let split = raw.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or("Malformed HTTP response from Podman API")?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+    let mut body = raw[split + 4..].to_vec();
+
+    let mut lines = head.lines();
+    let status = lines.next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or("Malformed HTTP status line from Podman API")?;
+
+    let chunked = lines.any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    if chunked {
+        body = decode_chunked(&body)?;
+    }
+
+    Ok(Response { status, body })
+}
+
+fn decode_chunked(mut data: &[u8]) -> AppResult<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or("Malformed chunked response from Podman API")?;
+        let size_line = String::from_utf8_lossy(&data[..line_end]);
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| "Malformed chunk size in Podman API response")?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            return Err("Truncated chunked response from Podman API".into());
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// Percent-encodes a value for use in a URL path segment or query string
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Converts "[ip:]host_port:container_port[/protocol]" into a libpod port mapping
+fn port_mapping(port: &str) -> AppResult<Value> {
+    let mapping = PortMapping::parse(port).ok_or_else(|| format!("Invalid port mapping '{}'", port))?;
+    Ok(json!({
+        "host_ip": mapping.host_ip,
+        "host_port": mapping.host_port,
+        "container_port": mapping.container_port,
+        "protocol": mapping.protocol,
+    }))
+}
+
+/// Splits the configured mounts into libpod `mounts` (bind and tmpfs) and named `volumes`
+fn container_mounts(mounts: &[Mount], data_path: &str) -> (Vec<Value>, Vec<Value>) {
+    let mut spec_mounts = Vec::new();
+    let mut volumes = Vec::new();
+    for spec in mounts.iter().filter_map(Mount::spec) {
+        let options = spec.podman_options();
+        match spec.kind {
+            MountType::Bind | MountType::File | MountType::Dir => {
+                let mut bind_options = vec!["rbind".to_string()];
+                bind_options.extend(options);
+                spec_mounts.push(json!({
+                    "type": "bind",
+                    "source": spec.host_path(data_path),
+                    "destination": spec.target,
+                    "options": bind_options,
+                }));
+            }
+            MountType::Tmpfs => spec_mounts.push(json!({
+                "type": "tmpfs",
+                "source": "tmpfs",
+                "destination": spec.target,
+                "options": options,
+            })),
+            MountType::Volume => volumes.push(json!({
+                "Name": spec.source,
+                "Dest": spec.target,
+                "Options": options,
+            })),
+        }
+    }
+    (spec_mounts, volumes)
+}
+
+/// Adds the configured restart policy, healthcheck and resource limits to a
+/// container create request
+fn add_runtime_options(body: &mut Value, container: &Container, data_path: &str) {
+    let resolved = ContainerInfo::from_config(container, data_path);
+
+    if let Some(policy) = &container.restart_policy {
+        body["restart_policy"] = json!(policy.name());
+        if let Some(retries) = policy.max_retries() {
+            body["restart_tries"] = json!(retries);
+        }
+    }
+
+    if let Some(health) = &resolved.healthcheck {
+        body["healthconfig"] = json!({
+            "Test": ["CMD-SHELL", health.command],
+            "Interval": health.interval.as_nanos() as u64,
+            "Timeout": health.timeout.as_nanos() as u64,
+            "Retries": health.retries,
+            "StartPeriod": health.start_period.as_nanos() as u64,
+        });
+    }
+
+    if let Some(resources) = &container.resources {
+        let mut limits = json!({});
+        if resources.memory.is_some() {
+            limits["memory"] = json!({ "limit": resolved.memory });
+        }
+        if resources.cpus.is_some() {
+            // Same quota/period split as `podman run --cpus`
+            limits["cpu"] = json!({ "quota": resolved.nano_cpus / 10_000, "period": 100_000 });
+        }
+        if let Some(pids_limit) = resources.pids_limit {
+            limits["pids"] = json!({ "limit": pids_limit });
+        }
+        body["resource_limits"] = limits;
+    }
+}
+
+/// Adds the configured entrypoint, command, working directory and user to a
+/// container create request
+fn add_process_options(body: &mut Value, container: &Container) {
+    if let Some(entrypoint) = &container.entrypoint {
+        body["entrypoint"] = json!(entrypoint);
+    }
+    if let Some(command_line) = container.command_line() {
+        body["command"] = json!(command_line);
+    }
+    if let Some(workdir) = &container.workdir {
+        body["work_dir"] = json!(workdir);
+    }
+    if let Some(user) = &container.user {
+        body["user"] = json!(user);
+    }
+}
+
+/// Adds the container's secrets to a container create request: mounted secrets
+/// go to `secrets`, environment secrets to `secret_env` (variable -> secret)
+fn add_secrets(body: &mut Value, container: &Container) {
+    let mut mounted = Vec::new();
+    let mut env = serde_json::Map::new();
+    for spec in container.secrets.iter().map(ContainerSecret::spec) {
+        match spec.kind {
+            SecretType::Mount => mounted.push(json!({
+                "Source": spec.name,
+                "Target": spec.target.clone().unwrap_or_default(),
+            })),
+            SecretType::Env => {
+                env.insert(spec.target.clone().unwrap_or_else(|| spec.name.clone()), json!(spec.name));
+            }
+        }
+    }
+    if !mounted.is_empty() {
+        body["secrets"] = json!(mounted);
+    }
+    if !env.is_empty() {
+        body["secret_env"] = Value::Object(env);
+    }
+}
+
+impl ContainerRuntime for ApiRuntime {
+    fn is_available(&self) -> bool {
+        matches!(self.request("GET", "/_ping", None), Ok(response) if response.is_success())
+    }
+
+    fn pod_exists(&self, pod_name: &str) -> bool {
+        self.exists(&format!("/pods/{}/exists", encode(pod_name)))
+    }
+
+    fn create_pod(&self, pod_name: &str, ports: &[String]) -> AppResult<()> {
+        let portmappings = ports.iter()
+            .map(|port| port_mapping(port))
+            .collect::<AppResult<Vec<Value>>>()?;
+        let body = json!({ "name": pod_name, "portmappings": portmappings });
+
+        self.expect_success("POST", "/pods/create", Some(&body), |e| AppError::context(format!("Failed to create pod: {}", pod_name), e))?;
+        Ok(())
+    }
+
+    fn start_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.change_state(&format!("/pods/{}/start", encode(pod_name)), |e| AppError::context(format!("Failed to start pod: {}", pod_name), e))
+    }
+
+    fn stop_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.change_state(&format!("/pods/{}/stop", encode(pod_name)), |e| AppError::context(format!("Failed to stop pod: {}", pod_name), e))
+    }
+
+    fn remove_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.expect_success("DELETE", &format!("/pods/{}?force=true", encode(pod_name)), None, |e| AppError::context(format!("Failed to remove pod: {}", pod_name), e))?;
+        Ok(())
+    }
+
+    fn list_pods(&self) -> AppResult<Vec<PodStatus>> {
+        let pods: Vec<ApiPod> = self.expect_success("GET", "/pods/json", None, |e| AppError::context("Failed to list pods", e))?
+            .json()?;
+        Ok(pods.into_iter()
+            .map(|pod| PodStatus { name: pod.name, status: pod.status })
+            .collect())
+    }
+
+    fn container_exists(&self, container_name: &str) -> bool {
+        self.exists(&format!("/containers/{}/exists", encode(container_name)))
+    }
+
+    fn create_container(&self, pod_name: &str, container: &Container, data_path: &str) -> AppResult<()> {
+        let failed = |e: AppError| AppError::container_create(&container.name, pod_name, e);
+        let env: HashMap<&str, &str> = container.env_vars.iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let (mounts, volumes) = container_mounts(&container.mounts, data_path);
+        let mut body = json!({
+            "name": container.name,
+            "image": container.image,
+            "pod": pod_name,
+            "env": env,
+            "mounts": mounts,
+            "volumes": volumes,
+        });
+        add_runtime_options(&mut body, container, data_path);
+        add_process_options(&mut body, container);
+        add_secrets(&mut body, container);
+
+        // Like `podman run`, pull an image that is not in local storage yet
+        if !self.exists(&format!("/images/{}/exists", encode(&container.image))) {
+            self.pull_image(&container.image, true).map_err(failed)?;
+        }
+        self.expect_success("POST", "/containers/create", Some(&body), failed)?;
+        self.change_state(&format!("/containers/{}/start", encode(&container.name)), failed)
+    }
+
+    fn stop_container(&self, container_name: &str) -> AppResult<()> {
+        self.change_state(&format!("/containers/{}/stop", encode(container_name)), |e| AppError::context(format!("Failed to stop container: {}", container_name), e))
+    }
+
+    fn remove_container(&self, container_name: &str) -> AppResult<()> {
+        self.expect_success("DELETE", &format!("/containers/{}", encode(container_name)), None, |e| AppError::context(format!("Failed to remove container: {}", container_name), e))?;
+        Ok(())
+    }
+
+    fn container_image(&self, container_name: &str) -> Option<String> {
+        self.inspect_container(container_name).ok().flatten().map(|info| info.image)
+    }
+
+    fn inspect_container(&self, container_name: &str) -> AppResult<Option<ContainerInfo>> {
+        let response = self.request("GET", &format!("/containers/{}/json", encode(container_name)), None)?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        if !response.is_success() {
+            return Err(AppError::context(format!("Failed to inspect container: {}", container_name), response.error_message()));
+        }
+        Ok(parse_one::<ContainerInspect>(&response.body)?.map(ContainerInfo::from))
+    }
+
+    fn container_state(&self, container_name: &str) -> AppResult<Option<ContainerState>> {
+        let response = self.request("GET", &format!("/containers/{}/json", encode(container_name)), None)?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        if !response.is_success() {
+            return Err(AppError::context(format!("Failed to inspect container: {}", container_name), response.error_message()));
+        }
+        Ok(parse_one::<ContainerInspect>(&response.body)?.map(|inspect| inspect.state.into()))
+    }
+
+    fn run_healthcheck(&self, container_name: &str) -> AppResult<bool> {
+        let response = self.request("GET", &format!("/containers/{}/healthcheck", encode(container_name)), None)?;
+        if !response.is_success() {
+            return Ok(false);
+        }
+        let health: ApiHealthCheck = response.json()?;
+        Ok(health.status == "healthy")
+    }
+
+    fn start_container(&self, container_name: &str) -> AppResult<()> {
+        self.change_state(&format!("/containers/{}/start", encode(container_name)), |e| AppError::context(format!("Failed to start container: {}", container_name), e))
+    }
+
+    fn rename_container(&self, container_name: &str, new_name: &str) -> AppResult<()> {
+        let path = format!("/containers/{}/rename?name={}", encode(container_name), encode(new_name));
+        self.expect_success("POST", &path, None, |e| AppError::context(format!("Failed to rename container {} to {}", container_name, new_name), e))?;
+        Ok(())
+    }
+
+    fn inspect_pod(&self, pod_name: &str) -> AppResult<Option<PodInfo>> {
+        let response = self.request("GET", &format!("/pods/{}/json", encode(pod_name)), None)?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        if !response.is_success() {
+            return Err(AppError::context(format!("Failed to inspect pod: {}", pod_name), response.error_message()));
+        }
+        Ok(parse_one::<PodInspect>(&response.body)?.map(PodInfo::from))
+    }
+
+    fn list_containers(&self) -> AppResult<Vec<ContainerStatus>> {
+        let containers: Vec<ApiContainer> = self.expect_success("GET", "/containers/json?all=true", None, |e| AppError::context("Failed to list containers", e))?.json()?;
+
+        Ok(containers.into_iter()
+            .filter_map(|container| {
+                let name = container.names.into_iter().next()?;
+                let status = if container.status.is_empty() { container.state } else { container.status };
+                Some(ContainerStatus { name, status, image: container.image })
+            })
+            .collect())
+    }
+
+    fn pull_image(&self, image: &str, tls_verify: bool) -> AppResult<()> {
+        let path = format!("/images/pull?reference={}&tlsVerify={}", encode(image), tls_verify);
+        let response = self.expect_success("POST", &path, None, |e| {
+            AppError::pull(image, e)
+        })?;
+
+        // The pull endpoint streams one JSON report per line and signals
+        // failures in-band with an "error" field
+        for line in response.text().lines().filter(|line| !line.trim().is_empty()) {
+            let report: ApiPullReport = serde_json::from_str(line)?;
+            if let Some(error) = report.error {
+                return Err(AppError::pull(image, error));
+            }
+            if report.id.is_some() {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn tag_image(&self, image: &str, target: &str) -> AppResult<()> {
+        // The tag is whatever follows the last colon, unless that colon is part of a registry port
+        let (repo, tag) = match target.rsplit_once(':') {
+            Some((repo, tag)) if !tag.contains('/') => (repo, tag),
+            _ => (target, "latest"),
+        };
+        let path = format!("/images/{}/tag?repo={}&tag={}", encode(image), encode(repo), encode(tag));
+        self.expect_success("POST", &path, None, |e| AppError::context(format!("Failed to tag image {} as {}", image, target), e))?;
+        Ok(())
+    }
+
+    fn inspect_image(&self, image: &str) -> AppResult<Option<ImageInfo>> {
+        let response = self.request("GET", &format!("/images/{}/json", encode(image)), None)?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        if !response.is_success() {
+            return Err(AppError::context(format!("Failed to inspect image: {}", image), response.error_message()));
+        }
+        Ok(parse_one::<ImageInspect>(&response.body)?.map(ImageInfo::from))
+    }
+
+    fn play_kube(&self, manifest: &str) -> AppResult<()> {
+        let response = self.request_raw("POST", "/play/kube?replace=true", Some(("application/x-yaml", manifest)))?;
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(AppError::context("Failed to play Kubernetes manifest", response.error_message()))
+        }
+    }
+
+    fn prune_images(&self) -> AppResult<()> {
+        self.expect_success("POST", "/images/prune?all=true", None, |e| AppError::context("Failed to prune all images", e))?;
+        Ok(())
+    }
+
+    fn volume_exists(&self, volume_name: &str) -> bool {
+        self.exists(&format!("/volumes/{}/exists", encode(volume_name)))
+    }
+
+    fn create_volume(&self, volume: &Volume) -> AppResult<()> {
+        let mut body = json!({
+            "Name": volume.name,
+            "Options": volume.options,
+            "Labels": volume.labels,
+        });
+        if let Some(driver) = &volume.driver {
+            body["Driver"] = json!(driver);
+        }
+        self.expect_success("POST", "/volumes/create", Some(&body), |e| AppError::context(format!("Failed to create volume: {}", volume.name), e))?;
+        Ok(())
+    }
+
+    fn secret_exists(&self, secret_name: &str) -> bool {
+        self.exists(&format!("/secrets/{}/exists", encode(secret_name)))
+    }
+
+    fn create_secret(&self, secret_name: &str, value: &str) -> AppResult<()> {
+        let path = format!("/secrets/create?name={}", encode(secret_name));
+        let response = self.request_raw("POST", &path, Some(("application/octet-stream", value)))?;
+        if !response.is_success() {
+            return Err(AppError::context(format!("Failed to create secret: {}", secret_name), AppError::Message(response.error_message())));
+        }
+        Ok(())
+    }
+
+    fn is_logged_in(&self, registry: &str) -> bool {
+        CliRuntime.is_logged_in(registry)
+    }
+
+    fn login(&self, registry: &str, username: &str, password: &str, tls_verify: bool) -> AppResult<()> {
+        CliRuntime.login(registry, username, password, tls_verify)
+    }
+
+    fn path_owner(&self, path: &Path) -> Option<String> {
+        // The REST API cannot look into the rootless user namespace
+        if crate::is_root() { metadata_owner(path) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread::{self, JoinHandle};
+
+    /// Stand-in for the Podman service: answers each connection on a scratch
+    /// socket with the next canned response and records the request lines
+    struct StandIn {
+        socket: PathBuf,
+        server: Option<JoinHandle<Vec<String>>>,
+    }
+
+    impl StandIn {
+        fn serve(responses: &[&str]) -> StandIn {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let socket = env::temp_dir().join(format!(
+                "podman_deploy-api-{}-{}.sock", std::process::id(), NEXT.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = std::fs::remove_file(&socket);
+            let listener = UnixListener::bind(&socket).unwrap();
+            let responses: Vec<String> = responses.iter().map(|response| response.to_string()).collect();
+            let server = thread::spawn(move || {
+                let mut requests = Vec::new();
+                for response in responses {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    requests.push(read_request_line(&mut stream));
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+                requests
+            });
+            StandIn { socket, server: Some(server) }
+        }
+
+        fn runtime(&self) -> ApiRuntime {
+            ApiRuntime::new(&self.socket)
+        }
+
+        /// Waits for every canned response to be sent and returns the request lines
+        fn requests(mut self) -> Vec<String> {
+            self.server.take().unwrap().join().unwrap()
+        }
+    }
+
+    impl Drop for StandIn {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.socket);
+        }
+    }
+
+    /// Reads a request up to the end of its body and returns its request line
+    fn read_request_line(stream: &mut UnixStream) -> String {
+        let mut raw = Vec::new();
+        let mut buffer = [0u8; 1024];
+        loop {
+            let read = stream.read(&mut buffer).unwrap();
+            raw.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&raw);
+            if let Some(head_end) = text.find("\r\n\r\n") {
+                let length = text[..head_end].lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .unwrap_or_default();
+                if read == 0 || raw.len() >= head_end + 4 + length {
+                    return text.lines().next().unwrap_or_default().to_string();
+                }
+            }
+            if read == 0 {
+                return String::new();
+            }
+        }
+    }
+
+    fn with_length(status: &str, body: &str) -> String {
+        format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body)
+    }
+
+    fn chunked(status: &str, chunks: &[&str]) -> String {
+        let mut response = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n", status);
+        for chunk in chunks {
+            response.push_str(&format!("{:x}\r\n{}\r\n", chunk.len(), chunk));
+        }
+        response.push_str("0\r\n\r\n");
+        response
+    }
+
+    #[test]
+    fn reads_content_length_bodies() {
+        let response = with_length("200 OK", r#"[{"Name":"web-pod","Status":"Running"},{"Name":"db-pod","Status":"Exited"}]"#);
+        let stand_in = StandIn::serve(&[&response]);
+        let pods = stand_in.runtime().list_pods().unwrap();
+        let pods: Vec<(String, String)> = pods.into_iter().map(|pod| (pod.name, pod.status)).collect();
+        assert_eq!(pods, [("web-pod".to_string(), "Running".to_string()), ("db-pod".to_string(), "Exited".to_string())]);
+        assert_eq!(stand_in.requests(), ["GET /v4.0.0/libpod/pods/json HTTP/1.1"]);
+    }
+
+    #[test]
+    fn reads_chunked_bodies() {
+        let response = chunked("200 OK", &[r#"[{"Names":["web"],"Image":"docker.io/library/ng"#, r#"inx:1.21","Status":"Up 5 minutes"}]"#]);
+        let stand_in = StandIn::serve(&[&response]);
+        let containers = stand_in.runtime().list_containers().unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].name, "web");
+        assert_eq!(containers[0].image, "docker.io/library/nginx:1.21");
+        assert_eq!(containers[0].status, "Up 5 minutes");
+    }
+
+    #[test]
+    fn rejects_truncated_chunked_bodies() {
+        assert!(decode_chunked(b"10\r\nshort\r\n").is_err());
+        assert!(decode_chunked(b"zz\r\n").is_err());
+        assert_eq!(decode_chunked(b"3;ext=1\r\nabc\r\n0\r\n\r\n").unwrap(), b"abc");
+    }
+
+    #[test]
+    fn maps_not_found_to_none() {
+        let not_found = with_length("404 Not Found", r#"{"cause":"no such container","message":"no container with name or ID \"web\" found: no such container","response":404}"#);
+        let stand_in = StandIn::serve(&[&not_found, &not_found, &not_found]);
+        let runtime = stand_in.runtime();
+        assert!(runtime.inspect_container("web").unwrap().is_none());
+        assert!(runtime.container_state("web").unwrap().is_none());
+        assert!(runtime.inspect_pod("web-pod").unwrap().is_none());
+    }
+
+    #[test]
+    fn maps_libpod_error_bodies_to_errors() {
+        let stand_in = StandIn::serve(&[
+            &with_length("500 Internal Server Error", r#"{"cause":"container state improper","message":"container web is already running","response":500}"#),
+            &with_length("409 Conflict", r#"{"cause":"name is in use","response":409}"#),
+            &with_length("500 Internal Server Error", "not json"),
+        ]);
+        let runtime = stand_in.runtime();
+
+        let error = runtime.start_container("web").unwrap_err();
+        assert_eq!(error.to_string(), "Failed to start container: web");
+        assert_eq!(error.source().unwrap().to_string(), "container web is already running");
+
+        let error = runtime.rename_container("web", "web-previous").unwrap_err();
+        assert_eq!(error.source().unwrap().to_string(), "name is in use");
+
+        let error = runtime.stop_container("web").unwrap_err();
+        assert_eq!(error.source().unwrap().to_string(), "HTTP 500: not json");
+
+        let requests = stand_in.requests();
+        assert_eq!(requests[1], "POST /v4.0.0/libpod/containers/web/rename?name=web-previous HTTP/1.1");
+    }
+
+    #[test]
+    fn treats_not_modified_as_already_started_or_stopped() {
+        let not_modified = "HTTP/1.1 304 Not Modified\r\n\r\n";
+        let stand_in = StandIn::serve(&[not_modified, not_modified, not_modified, not_modified]);
+        let runtime = stand_in.runtime();
+        runtime.start_container("web").unwrap();
+        runtime.stop_container("web").unwrap();
+        runtime.start_pod("web-pod").unwrap();
+        runtime.stop_pod("web-pod").unwrap();
+        assert_eq!(stand_in.requests(), [
+            "POST /v4.0.0/libpod/containers/web/start HTTP/1.1",
+            "POST /v4.0.0/libpod/containers/web/stop HTTP/1.1",
+            "POST /v4.0.0/libpod/pods/web-pod/start HTTP/1.1",
+            "POST /v4.0.0/libpod/pods/web-pod/stop HTTP/1.1",
+        ]);
+    }
+
+    #[test]
+    fn pulls_a_missing_image_before_creating_a_container() {
+        let container: Container = serde_yaml::from_str("name: web\nimage: docker.io/library/nginx:1.21\nenv_vars: {}\nports: []\nmounts: []\n").unwrap();
+        let created = with_length("201 Created", r#"{"Id":"abc","Warnings":[]}"#);
+        let started = "HTTP/1.1 204 No Content\r\n\r\n";
+        let stand_in = StandIn::serve(&[
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+            &chunked("200 OK", &["{\"id\":\"605c77e624dd\"}\n"]),
+            &created,
+            started,
+            "HTTP/1.1 204 No Content\r\n\r\n",
+            &created,
+            started,
+        ]);
+        let runtime = stand_in.runtime();
+        runtime.create_container("web-pod", &container, "/srv").unwrap();
+        // An image already in local storage is not pulled again
+        runtime.create_container("web-pod", &container, "/srv").unwrap();
+        assert_eq!(stand_in.requests(), [
+            "GET /v4.0.0/libpod/images/docker.io%2Flibrary%2Fnginx%3A1.21/exists HTTP/1.1",
+            "POST /v4.0.0/libpod/images/pull?reference=docker.io%2Flibrary%2Fnginx%3A1.21&tlsVerify=true HTTP/1.1",
+            "POST /v4.0.0/libpod/containers/create HTTP/1.1",
+            "POST /v4.0.0/libpod/containers/web/start HTTP/1.1",
+            "GET /v4.0.0/libpod/images/docker.io%2Flibrary%2Fnginx%3A1.21/exists HTTP/1.1",
+            "POST /v4.0.0/libpod/containers/create HTTP/1.1",
+            "POST /v4.0.0/libpod/containers/web/start HTTP/1.1",
+        ]);
+    }
+
+    #[test]
+    fn reports_errors_inside_the_pull_stream() {
+        let stand_in = StandIn::serve(&[
+            &chunked("200 OK", &["{\"stream\":\"Trying to pull docker.io/library/nginx:9.9...\\n\"}\n", "{\"error\":\"manifest unknown\"}\n"]),
+            &chunked("200 OK", &["{\"stream\":\"Copying blob\\n\"}\n", "{\"id\":\"605c77e624dd\"}\n"]),
+        ]);
+        let runtime = stand_in.runtime();
+
+        let error = runtime.pull_image("docker.io/library/nginx:9.9", true).unwrap_err();
+        assert!(matches!(&error, AppError::Pull { image, .. } if image == "docker.io/library/nginx:9.9"));
+        assert_eq!(error.source().unwrap().to_string(), "manifest unknown");
+
+        runtime.pull_image("docker.io/library/nginx:1.21", false).unwrap();
+
+        let requests = stand_in.requests();
+        assert_eq!(requests[0], "POST /v4.0.0/libpod/images/pull?reference=docker.io%2Flibrary%2Fnginx%3A9.9&tlsVerify=true HTTP/1.1");
+        assert_eq!(requests[1], "POST /v4.0.0/libpod/images/pull?reference=docker.io%2Flibrary%2Fnginx%3A1.21&tlsVerify=false HTTP/1.1");
+    }
+}
@@ -0,0 +1,277 @@
+use super::inspect::{parse_one, ContainerInspect, ImageInspect, PodInspect};
+use super::{commands, metadata_owner, ContainerInfo, ContainerRuntime, ContainerState, ContainerStatus, ImageInfo, PodInfo, PodStatus};
+use crate::error::AppError;
+use crate::{AppResult, Container, Volume};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Runtime backend that shells out to the `podman` command line tool
+pub struct CliRuntime;
+
+impl CliRuntime {
+    /// Helper function to execute podman commands with consistent error handling
+    fn status(&self, args: &[&str]) -> AppResult<bool> {
+        let status = Command::new("podman")
+            .args(args)
+            .status()
+            .map_err(spawn_failed)?;
+
+        Ok(status.success())
+    }
+
+    /// Runs a podman command and returns its stdout if it succeeded
+    fn output(&self, args: &[&str]) -> AppResult<Option<String>> {
+        let output = Command::new("podman")
+            .args(args)
+            .output()
+            .map_err(spawn_failed)?;
+
+        if output.status.success() {
+            Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Runs a podman command with the given input on stdin
+    fn run_with_stdin(&self, args: &[String], input: &str, error: impl FnOnce(AppError) -> AppError) -> AppResult<()> {
+        let mut child = Command::new("podman")
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(spawn_failed)?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.as_bytes())?;
+        }
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(error(command_failed(args, status.code())))
+        }
+    }
+
+    /// Runs a podman command, wrapping a non-zero exit status with `error`
+    fn run(&self, args: &[String], error: impl FnOnce(AppError) -> AppError) -> AppResult<()> {
+        let status = Command::new("podman")
+            .args(args)
+            .status()
+            .map_err(spawn_failed)?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(error(command_failed(args, status.code())))
+        }
+    }
+}
+
+/// Reports a missing `podman` binary as such rather than as a plain I/O error
+fn spawn_failed(e: io::Error) -> AppError {
+    if e.kind() == io::ErrorKind::NotFound {
+        AppError::PodmanMissing("podman executable not found in PATH".to_string())
+    } else {
+        AppError::context("Failed to run podman", e)
+    }
+}
+
+/// Describes a failed command by its subcommand only (e.g. "podman pod start
+/// web-pod"), since later arguments may carry passwords or environment values
+fn command_failed(args: &[String], code: Option<i32>) -> AppError {
+    let words: Vec<&str> = args.iter()
+        .map(String::as_str)
+        .take_while(|arg| !arg.starts_with('-'))
+        .collect();
+    AppError::CommandFailed { command: format!("podman {}", words.join(" ")), code }
+}
+
+impl ContainerRuntime for CliRuntime {
+    fn is_available(&self) -> bool {
+        self.status(&["--version"]).unwrap_or(false)
+    }
+
+    fn pod_exists(&self, pod_name: &str) -> bool {
+        self.status(&["pod", "exists", pod_name]).unwrap_or(false)
+    }
+
+    fn create_pod(&self, pod_name: &str, ports: &[String]) -> AppResult<()> {
+        self.run(&commands::create_pod(pod_name, ports), |e| AppError::context(format!("Failed to create pod: {}", pod_name), e))
+    }
+
+    fn start_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.run(&commands::start_pod(pod_name), |e| AppError::context(format!("Failed to start pod: {}", pod_name), e))
+    }
+
+    fn stop_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.run(&commands::stop_pod(pod_name), |e| AppError::context(format!("Failed to stop pod: {}", pod_name), e))
+    }
+
+    fn remove_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.run(&commands::remove_pod(pod_name), |e| AppError::context(format!("Failed to remove pod: {}", pod_name), e))
+    }
+
+    fn list_pods(&self) -> AppResult<Vec<PodStatus>> {
+        let mut pods = Vec::new();
+        if let Some(stdout) = self.output(&["pod", "ps", "--format", "{{.Name}}\t{{.Status}}"])? {
+            for line in stdout.lines() {
+                let parts: Vec<&str> = line.split('\t').collect();
+                if parts.len() >= 2 {
+                    pods.push(PodStatus {
+                        name: parts[0].to_string(),
+                        status: parts[1].to_string(),
+                    });
+                }
+            }
+        }
+        Ok(pods)
+    }
+
+    fn container_exists(&self, container_name: &str) -> bool {
+        self.status(&["container", "exists", container_name]).unwrap_or(false)
+    }
+
+    fn create_container(&self, pod_name: &str, container: &Container, data_path: &str) -> AppResult<()> {
+        self.run(&commands::create_container(pod_name, container, data_path), |e| {
+            AppError::container_create(&container.name, pod_name, e)
+        })
+    }
+
+    fn stop_container(&self, container_name: &str) -> AppResult<()> {
+        self.run(&commands::stop_container(container_name), |e| AppError::context(format!("Failed to stop container: {}", container_name), e))
+    }
+
+    fn remove_container(&self, container_name: &str) -> AppResult<()> {
+        self.run(&commands::remove_container(container_name), |e| AppError::context(format!("Failed to remove container: {}", container_name), e))
+    }
+
+    fn container_image(&self, container_name: &str) -> Option<String> {
+        self.output(&["inspect", container_name, "--format", "{{.Config.Image}}"])
+            .ok()
+            .flatten()
+            .map(|stdout| stdout.trim().to_string())
+    }
+
+    fn inspect_container(&self, container_name: &str) -> AppResult<Option<ContainerInfo>> {
+        if !self.container_exists(container_name) {
+            return Ok(None);
+        }
+        match self.output(&["container", "inspect", "--format", "json", container_name])? {
+            Some(stdout) => Ok(parse_one::<ContainerInspect>(stdout.as_bytes())?.map(ContainerInfo::from)),
+            None => Err(format!("Failed to inspect container: {}", container_name).into()),
+        }
+    }
+
+    fn container_state(&self, container_name: &str) -> AppResult<Option<ContainerState>> {
+        if !self.container_exists(container_name) {
+            return Ok(None);
+        }
+        match self.output(&["container", "inspect", "--format", "json", container_name])? {
+            Some(stdout) => Ok(parse_one::<ContainerInspect>(stdout.as_bytes())?.map(|inspect| inspect.state.into())),
+            None => Err(format!("Failed to inspect container: {}", container_name).into()),
+        }
+    }
+
+    fn run_healthcheck(&self, container_name: &str) -> AppResult<bool> {
+        self.status(&["healthcheck", "run", container_name])
+    }
+
+    fn start_container(&self, container_name: &str) -> AppResult<()> {
+        self.run(&commands::start_container(container_name), |e| AppError::context(format!("Failed to start container: {}", container_name), e))
+    }
+
+    fn rename_container(&self, container_name: &str, new_name: &str) -> AppResult<()> {
+        self.run(&commands::rename_container(container_name, new_name), |e| {
+            AppError::context(format!("Failed to rename container {} to {}", container_name, new_name), e)
+        })
+    }
+
+    fn inspect_pod(&self, pod_name: &str) -> AppResult<Option<PodInfo>> {
+        if !self.pod_exists(pod_name) {
+            return Ok(None);
+        }
+        match self.output(&["pod", "inspect", "--format", "json", pod_name])? {
+            Some(stdout) => Ok(parse_one::<PodInspect>(stdout.as_bytes())?.map(PodInfo::from)),
+            None => Err(format!("Failed to inspect pod: {}", pod_name).into()),
+        }
+    }
+
+    fn list_containers(&self) -> AppResult<Vec<ContainerStatus>> {
+        let mut containers = Vec::new();
+        if let Some(stdout) = self.output(&["ps", "-a", "--format", "{{.Names}}\t{{.Status}}\t{{.Image}}"])? {
+            for line in stdout.lines() {
+                let parts: Vec<&str> = line.split('\t').collect();
+                if parts.len() >= 3 {
+                    containers.push(ContainerStatus {
+                        name: parts[0].to_string(),
+                        status: parts[1].to_string(),
+                        image: parts[2].to_string(),
+                    });
+                }
+            }
+        }
+        Ok(containers)
+    }
+
+    fn pull_image(&self, image: &str, tls_verify: bool) -> AppResult<()> {
+        self.run(&commands::pull_image(image, tls_verify), |e| AppError::pull(image, e))
+    }
+
+    fn tag_image(&self, image: &str, target: &str) -> AppResult<()> {
+        self.run(&commands::tag_image(image, target), |e| AppError::context(format!("Failed to tag image {} as {}", image, target), e))
+    }
+
+    fn inspect_image(&self, image: &str) -> AppResult<Option<ImageInfo>> {
+        if !self.status(&["image", "exists", image]).unwrap_or(false) {
+            return Ok(None);
+        }
+        match self.output(&["image", "inspect", "--format", "json", image])? {
+            Some(stdout) => Ok(parse_one::<ImageInspect>(stdout.as_bytes())?.map(ImageInfo::from)),
+            None => Err(format!("Failed to inspect image: {}", image).into()),
+        }
+    }
+
+    fn play_kube(&self, manifest: &str) -> AppResult<()> {
+        self.run_with_stdin(&commands::play_kube(), manifest, |e| AppError::context("Failed to play Kubernetes manifest", e))
+    }
+
+    fn prune_images(&self) -> AppResult<()> {
+        self.run(&commands::prune_images(), |e| AppError::context("Failed to prune all images", e))
+    }
+
+    fn volume_exists(&self, volume_name: &str) -> bool {
+        self.status(&["volume", "exists", volume_name]).unwrap_or(false)
+    }
+
+    fn create_volume(&self, volume: &Volume) -> AppResult<()> {
+        self.run(&commands::create_volume(volume), |e| AppError::context(format!("Failed to create volume: {}", volume.name), e))
+    }
+
+    fn secret_exists(&self, secret_name: &str) -> bool {
+        self.status(&["secret", "exists", secret_name]).unwrap_or(false)
+    }
+
+    fn create_secret(&self, secret_name: &str, value: &str) -> AppResult<()> {
+        self.run_with_stdin(&commands::create_secret(secret_name), value, |e| AppError::context(format!("Failed to create secret: {}", secret_name), e))
+    }
+
+    fn is_logged_in(&self, registry: &str) -> bool {
+        matches!(self.output(&["login", "--get-login", registry]), Ok(Some(_)))
+    }
+
+    fn login(&self, registry: &str, username: &str, password: &str, tls_verify: bool) -> AppResult<()> {
+        self.run_with_stdin(&commands::login(registry, username, tls_verify), password, |e| AppError::registry_login(registry, e))
+    }
+
+    fn path_owner(&self, path: &Path) -> Option<String> {
+        if crate::is_root() {
+            return metadata_owner(path);
+        }
+        // Rootless owners are set through `podman unshare`, so read them back the same way
+        let path = path.to_string_lossy();
+        let owner = self.output(&["unshare", "stat", "-c", "%u:%g", &path]).ok()??;
+        Some(owner.trim().to_string())
+    }
+}
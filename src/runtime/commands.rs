@@ -0,0 +1,105 @@
+//! Argument vectors for the mutating `podman` commands.
+//!
+//! The CLI backend executes these and the dry-run wrapper prints them, so
+//! what `--dry-run` shows is exactly what a real run would execute.
+
+use crate::{build_container_args, Container, Volume};
+
+fn owned(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+pub fn create_pod(pod_name: &str, ports: &[String]) -> Vec<String> {
+    let mut args = owned(&["pod", "create", "--name", pod_name]);
+    for port in ports {
+        args.push("-p".to_string());
+        args.push(port.clone());
+    }
+    args
+}
+
+pub fn start_pod(pod_name: &str) -> Vec<String> {
+    owned(&["pod", "start", pod_name])
+}
+
+pub fn stop_pod(pod_name: &str) -> Vec<String> {
+    owned(&["pod", "stop", pod_name])
+}
+
+pub fn remove_pod(pod_name: &str) -> Vec<String> {
+    owned(&["pod", "rm", "-f", pod_name])
+}
+
+pub fn create_container(pod_name: &str, container: &Container, data_path: &str) -> Vec<String> {
+    build_container_args(pod_name, container, data_path)
+}
+
+pub fn stop_container(container_name: &str) -> Vec<String> {
+    owned(&["stop", container_name])
+}
+
+pub fn remove_container(container_name: &str) -> Vec<String> {
+    owned(&["rm", container_name])
+}
+
+pub fn start_container(container_name: &str) -> Vec<String> {
+    owned(&["start", container_name])
+}
+
+pub fn rename_container(container_name: &str, new_name: &str) -> Vec<String> {
+    owned(&["rename", container_name, new_name])
+}
+
+pub fn pull_image(image: &str, tls_verify: bool) -> Vec<String> {
+    let mut args = owned(&["pull"]);
+    if !tls_verify {
+        args.push("--tls-verify=false".to_string());
+    }
+    args.push(image.to_string());
+    args
+}
+
+pub fn tag_image(image: &str, target: &str) -> Vec<String> {
+    owned(&["tag", image, target])
+}
+
+/// Reads the manifest from stdin and replaces existing pods of the same name
+pub fn play_kube() -> Vec<String> {
+    owned(&["kube", "play", "--replace", "-"])
+}
+
+pub fn create_volume(volume: &Volume) -> Vec<String> {
+    let mut args = owned(&["volume", "create"]);
+    if let Some(driver) = &volume.driver {
+        args.push("--driver".to_string());
+        args.push(driver.clone());
+    }
+    for (key, value) in &volume.options {
+        args.push("--opt".to_string());
+        args.push(format!("{}={}", key, value));
+    }
+    for (key, value) in &volume.labels {
+        args.push("--label".to_string());
+        args.push(format!("{}={}", key, value));
+    }
+    args.push(volume.name.clone());
+    args
+}
+
+/// Reads the secret value from stdin, so it never appears in the process list
+pub fn create_secret(secret_name: &str) -> Vec<String> {
+    owned(&["secret", "create", secret_name, "-"])
+}
+
+pub fn prune_images() -> Vec<String> {
+    owned(&["image", "prune", "-a"])
+}
+
+/// Reads the password from stdin, so it never appears in the process list
+pub fn login(registry: &str, username: &str, tls_verify: bool) -> Vec<String> {
+    let mut args = owned(&["login", registry, "-u", username, "--password-stdin"]);
+    if !tls_verify {
+        args.push("--tls-verify=false".to_string());
+    }
+    args
+}
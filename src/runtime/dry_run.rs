@@ -0,0 +1,156 @@
+use super::{commands, ContainerInfo, ContainerRuntime, ContainerState, ContainerStatus, ImageInfo, PodInfo, PodStatus};
+use crate::{shell_join, AppResult, Container, Volume};
+use std::path::Path;
+
+/// Runtime wrapper that answers queries from the wrapped backend but only
+/// prints the `podman` commands for operations that would change anything
+pub struct DryRunRuntime {
+    inner: Box<dyn ContainerRuntime>,
+}
+
+impl DryRunRuntime {
+    pub fn new(inner: Box<dyn ContainerRuntime>) -> Self {
+        DryRunRuntime { inner }
+    }
+
+    fn would_run(&self, args: &[String]) -> AppResult<()> {
+        println!("[dry-run] Would run: podman {}", shell_join(args));
+        Ok(())
+    }
+}
+
+impl ContainerRuntime for DryRunRuntime {
+    fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+
+    fn pod_exists(&self, pod_name: &str) -> bool {
+        self.inner.pod_exists(pod_name)
+    }
+
+    fn create_pod(&self, pod_name: &str, ports: &[String]) -> AppResult<()> {
+        self.would_run(&commands::create_pod(pod_name, ports))
+    }
+
+    fn start_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.would_run(&commands::start_pod(pod_name))
+    }
+
+    fn stop_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.would_run(&commands::stop_pod(pod_name))
+    }
+
+    fn remove_pod(&self, pod_name: &str) -> AppResult<()> {
+        self.would_run(&commands::remove_pod(pod_name))
+    }
+
+    fn list_pods(&self) -> AppResult<Vec<PodStatus>> {
+        self.inner.list_pods()
+    }
+
+    fn container_exists(&self, container_name: &str) -> bool {
+        self.inner.container_exists(container_name)
+    }
+
+    fn create_container(&self, pod_name: &str, container: &Container, data_path: &str) -> AppResult<()> {
+        self.would_run(&commands::create_container(pod_name, container, data_path))
+    }
+
+    fn stop_container(&self, container_name: &str) -> AppResult<()> {
+        self.would_run(&commands::stop_container(container_name))
+    }
+
+    fn remove_container(&self, container_name: &str) -> AppResult<()> {
+        self.would_run(&commands::remove_container(container_name))
+    }
+
+    fn container_image(&self, container_name: &str) -> Option<String> {
+        self.inner.container_image(container_name)
+    }
+
+    fn inspect_container(&self, container_name: &str) -> AppResult<Option<ContainerInfo>> {
+        self.inner.inspect_container(container_name)
+    }
+
+    fn container_state(&self, container_name: &str) -> AppResult<Option<ContainerState>> {
+        self.inner.container_state(container_name)
+    }
+
+    fn run_healthcheck(&self, container_name: &str) -> AppResult<bool> {
+        self.would_run(&["healthcheck".to_string(), "run".to_string(), container_name.to_string()])?;
+        Ok(true)
+    }
+
+    fn start_container(&self, container_name: &str) -> AppResult<()> {
+        self.would_run(&commands::start_container(container_name))
+    }
+
+    fn rename_container(&self, container_name: &str, new_name: &str) -> AppResult<()> {
+        self.would_run(&commands::rename_container(container_name, new_name))
+    }
+
+    fn inspect_pod(&self, pod_name: &str) -> AppResult<Option<PodInfo>> {
+        self.inner.inspect_pod(pod_name)
+    }
+
+    fn list_containers(&self) -> AppResult<Vec<ContainerStatus>> {
+        self.inner.list_containers()
+    }
+
+    fn pull_image(&self, image: &str, tls_verify: bool) -> AppResult<()> {
+        self.would_run(&commands::pull_image(image, tls_verify))
+    }
+
+    fn tag_image(&self, image: &str, target: &str) -> AppResult<()> {
+        self.would_run(&commands::tag_image(image, target))
+    }
+
+    fn inspect_image(&self, image: &str) -> AppResult<Option<ImageInfo>> {
+        self.inner.inspect_image(image)
+    }
+
+    fn play_kube(&self, manifest: &str) -> AppResult<()> {
+        self.would_run(&commands::play_kube())?;
+        println!("[dry-run] With manifest on stdin:");
+        for line in manifest.lines() {
+            println!("[dry-run]   {}", line);
+        }
+        Ok(())
+    }
+
+    fn prune_images(&self) -> AppResult<()> {
+        self.would_run(&commands::prune_images())
+    }
+
+    fn volume_exists(&self, volume_name: &str) -> bool {
+        self.inner.volume_exists(volume_name)
+    }
+
+    fn create_volume(&self, volume: &Volume) -> AppResult<()> {
+        self.would_run(&commands::create_volume(volume))
+    }
+
+    fn secret_exists(&self, secret_name: &str) -> bool {
+        self.inner.secret_exists(secret_name)
+    }
+
+    fn create_secret(&self, secret_name: &str, _value: &str) -> AppResult<()> {
+        self.would_run(&commands::create_secret(secret_name))?;
+        println!("[dry-run] With the secret value on stdin");
+        Ok(())
+    }
+
+    fn is_logged_in(&self, registry: &str) -> bool {
+        self.inner.is_logged_in(registry)
+    }
+
+    fn login(&self, registry: &str, username: &str, _password: &str, tls_verify: bool) -> AppResult<()> {
+        self.would_run(&commands::login(registry, username, tls_verify))?;
+        println!("[dry-run] With the password on stdin");
+        Ok(())
+    }
+
+    fn path_owner(&self, path: &Path) -> Option<String> {
+        self.inner.path_owner(path)
+    }
+}
@@ -0,0 +1,327 @@
+//! Inspection documents shared by the CLI and API backends.
+//!
+//! `podman container inspect --format json` and
+//! `GET /libpod/containers/{name}/json` return the same document (and
+//! likewise for pods and images), so both backends decode them here.
+
+use super::{ContainerInfo, ContainerState, HealthInfo, ImageInfo, MountInfo, PodInfo, PortMapping};
+use crate::units::parse_memory;
+use crate::{AppResult, MountType};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerInspect {
+    /// Image ID
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub image_name: String,
+    #[serde(default)]
+    pub config: InspectConfig,
+    #[serde(default)]
+    pub mounts: Vec<InspectMount>,
+    #[serde(default)]
+    pub host_config: InspectHostConfig,
+    #[serde(default)]
+    pub state: InspectState,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectState {
+    #[serde(default)]
+    pub running: bool,
+    #[serde(default)]
+    pub exit_code: i32,
+}
+
+impl From<InspectState> for ContainerState {
+    fn from(state: InspectState) -> Self {
+        ContainerState { running: state.running, exit_code: state.exit_code }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectConfig {
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub env: Option<Vec<String>>,
+    #[serde(default)]
+    pub healthcheck: Option<InspectHealthcheck>,
+    #[serde(default)]
+    pub cmd: Option<Vec<String>>,
+    /// A string in Podman 4 and an array in Podman 5
+    #[serde(default)]
+    pub entrypoint: Value,
+    #[serde(default)]
+    pub working_dir: String,
+    #[serde(default)]
+    pub user: String,
+    /// Secrets mounted as files
+    #[serde(default)]
+    pub secrets: Option<Vec<InspectSecret>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectSecret {
+    pub name: String,
+}
+
+/// Healthcheck as stored by Podman; timings are in nanoseconds
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectHealthcheck {
+    #[serde(default)]
+    pub test: Vec<String>,
+    #[serde(default)]
+    pub interval: u64,
+    #[serde(default)]
+    pub timeout: u64,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub start_period: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectHostConfig {
+    #[serde(default)]
+    pub restart_policy: Option<InspectRestartPolicy>,
+    #[serde(default)]
+    pub memory: u64,
+    #[serde(default)]
+    pub nano_cpus: u64,
+    #[serde(default)]
+    pub cpu_quota: u64,
+    #[serde(default)]
+    pub cpu_period: u64,
+    #[serde(default)]
+    pub pids_limit: i64,
+    /// tmpfs mounts: target -> options
+    #[serde(default)]
+    pub tmpfs: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectRestartPolicy {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub maximum_retry_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectMount {
+    #[serde(rename = "Type", default)]
+    pub kind: String,
+    /// Volume name, for volume mounts
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub destination: String,
+    /// False for read-only mounts
+    #[serde(rename = "RW", default = "read_write")]
+    pub rw: bool,
+    /// SELinux relabel option ("z" or "Z"), empty if none
+    #[serde(default)]
+    pub mode: String,
+}
+
+fn read_write() -> bool {
+    true
+}
+
+/// Image document of `podman image inspect` and `GET /libpod/images/{name}/json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImageInspect {
+    pub id: String,
+    #[serde(default)]
+    pub digest: String,
+}
+
+impl From<ImageInspect> for ImageInfo {
+    fn from(inspect: ImageInspect) -> Self {
+        ImageInfo { id: inspect.id, digest: inspect.digest }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PodInspect {
+    #[serde(rename = "InfraContainerID", default)]
+    pub infra_container_id: String,
+    #[serde(default)]
+    pub infra_config: Option<InfraConfig>,
+    #[serde(default)]
+    pub containers: Vec<PodInspectContainer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InfraConfig {
+    #[serde(default)]
+    pub port_bindings: Option<HashMap<String, Option<Vec<PortBinding>>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PortBinding {
+    #[serde(default)]
+    pub host_ip: String,
+    #[serde(default)]
+    pub host_port: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PodInspectContainer {
+    pub id: String,
+    pub name: String,
+}
+
+/// Decodes an inspect document that may be wrapped in a one-element array
+pub fn parse_one<T: for<'de> Deserialize<'de>>(json: &[u8]) -> AppResult<Option<T>> {
+    let value: Value = serde_json::from_slice(json)?;
+    let value = match value {
+        Value::Array(items) if items.is_empty() => return Ok(None),
+        Value::Array(mut items) => items.swap_remove(0),
+        other => other,
+    };
+    Ok(Some(serde_json::from_value(value)?))
+}
+
+impl From<ContainerInspect> for ContainerInfo {
+    fn from(inspect: ContainerInspect) -> Self {
+        let env = inspect.config.env.unwrap_or_default()
+            .into_iter()
+            .filter_map(|var| var.split_once('=').map(|(k, v)| (k.to_string(), v.to_string())))
+            .collect();
+        // Anonymous volumes (e.g. from an image's VOLUME) are named by a random 64 digit hex id
+        let anonymous = |name: &str| name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit());
+        let mut mounts: Vec<MountInfo> = inspect.mounts.into_iter()
+            .filter_map(|mount| {
+                let (kind, source) = match mount.kind.as_str() {
+                    "bind" => (MountType::Bind, mount.source),
+                    "volume" if !anonymous(&mount.name) => (MountType::Volume, mount.name),
+                    _ => return None,
+                };
+                let selinux = Some(mount.mode).filter(|mode| mode == "z" || mode == "Z");
+                Some(MountInfo { kind, source, destination: mount.destination, read_only: !mount.rw, selinux, size: None })
+            })
+            .collect();
+        let image = if inspect.config.image.is_empty() { inspect.image_name } else { inspect.config.image };
+
+        let host = inspect.host_config;
+        for (target, options) in host.tmpfs.unwrap_or_default() {
+            // Podman adds its defaults (e.g., "rw,rprivate,nosuid,nodev,tmpcopyup") to the configured options
+            let options: Vec<&str> = options.split(',').collect();
+            let size = options.iter().find_map(|option| option.strip_prefix("size=")).and_then(parse_memory);
+            mounts.push(MountInfo {
+                kind: MountType::Tmpfs,
+                source: String::new(),
+                destination: target,
+                read_only: options.contains(&"ro"),
+                selinux: None,
+                size,
+            });
+        }
+        let restart_policy = match host.restart_policy {
+            Some(policy) if policy.name == "on-failure" && policy.maximum_retry_count > 0 => {
+                format!("on-failure:{}", policy.maximum_retry_count)
+            }
+            Some(policy) if !policy.name.is_empty() => policy.name,
+            _ => "no".to_string(),
+        };
+
+        // "NONE" disables a healthcheck inherited from the image
+        let healthcheck = inspect.config.healthcheck
+            .filter(|health| !health.test.is_empty() && health.test[0] != "NONE")
+            .map(|health| HealthInfo {
+                command: match health.test.first().map(String::as_str) {
+                    Some("CMD-SHELL") | Some("CMD") => health.test[1..].join(" "),
+                    _ => health.test.join(" "),
+                },
+                interval: Duration::from_nanos(health.interval),
+                timeout: Duration::from_nanos(health.timeout),
+                retries: health.retries,
+                start_period: Duration::from_nanos(health.start_period),
+            });
+
+        let nano_cpus = if host.nano_cpus > 0 || host.cpu_period == 0 {
+            host.nano_cpus
+        } else {
+            host.cpu_quota * 1_000_000_000 / host.cpu_period
+        };
+
+        let entrypoint = match inspect.config.entrypoint {
+            Value::String(entrypoint) => entrypoint.split_whitespace().map(str::to_string).collect(),
+            Value::Array(entrypoint) => entrypoint.iter().filter_map(|arg| arg.as_str().map(str::to_string)).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut secrets: Vec<String> = inspect.config.secrets.unwrap_or_default()
+            .into_iter()
+            .map(|secret| secret.name)
+            .collect();
+        secrets.sort();
+
+        ContainerInfo {
+            image,
+            image_id: inspect.image,
+            env,
+            mounts,
+            restart_policy,
+            healthcheck,
+            memory: host.memory,
+            nano_cpus,
+            pids_limit: host.pids_limit,
+            entrypoint,
+            command: inspect.config.cmd.unwrap_or_default(),
+            workdir: inspect.config.working_dir,
+            user: inspect.config.user,
+            secrets,
+        }
+    }
+}
+
+impl From<PodInspect> for PodInfo {
+    fn from(inspect: PodInspect) -> Self {
+        let mut ports = Vec::new();
+        let bindings = inspect.infra_config
+            .and_then(|infra| infra.port_bindings)
+            .unwrap_or_default();
+        for (container_port, hosts) in bindings {
+            let (port, protocol) = container_port.split_once('/').unwrap_or((&container_port, "tcp"));
+            for host in hosts.unwrap_or_default() {
+                let host_ip = if host.host_ip == "0.0.0.0" { "" } else { host.host_ip.as_str() };
+                ports.push(PortMapping {
+                    host_ip: host_ip.to_string(),
+                    host_port: host.host_port.parse().unwrap_or_default(),
+                    container_port: port.parse().unwrap_or_default(),
+                    protocol: protocol.to_string(),
+                }.to_string());
+            }
+        }
+        ports.sort();
+
+        let containers = inspect.containers.into_iter()
+            .filter(|container| container.id != inspect.infra_container_id)
+            .map(|container| container.name)
+            .collect();
+
+        PodInfo { ports, containers }
+    }
+}
@@ -0,0 +1,434 @@
+use super::{normalize_port, ContainerInfo, HealthInfo, ContainerRuntime, ContainerState, ContainerStatus, ImageInfo, MountInfo, PodInfo, PodStatus};
+use crate::error::AppError;
+use crate::units::parse_memory;
+use crate::{kube, normalize_image, AppResult, Container, MountType, Volume};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::Duration;
+
+/// Pod tracked by the in-memory runtime
+#[derive(Debug, Clone)]
+struct MemoryPod {
+    ports: Vec<String>,
+    running: bool,
+}
+
+/// Container tracked by the in-memory runtime
+#[derive(Debug, Clone)]
+struct MemoryContainer {
+    pod: String,
+    info: ContainerInfo,
+    running: bool,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    pods: BTreeMap<String, MemoryPod>,
+    containers: BTreeMap<String, MemoryContainer>,
+    /// Normalized image name -> manifest digest
+    images: BTreeMap<String, String>,
+    volumes: BTreeSet<String>,
+    secrets: BTreeSet<String>,
+    logins: BTreeMap<String, String>,
+}
+
+/// Runtime backend that keeps pods, containers and images in memory.
+///
+/// It mirrors the observable behaviour of Podman closely enough for the
+/// deployment modes (e.g. creating a container requires its pod, removing
+/// a running container fails) without touching the host.
+#[derive(Debug, Default)]
+pub struct MemoryRuntime {
+    state: RefCell<MemoryState>,
+}
+
+impl MemoryRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Digest a registry would report for an image; pinned references carry their own
+fn image_digest(image: &str) -> String {
+    match image.split_once('@') {
+        Some((_, digest)) => digest.to_string(),
+        None => {
+            let mut hasher = DefaultHasher::new();
+            normalize_image(image).hash(&mut hasher);
+            format!("sha256:{:064x}", hasher.finish())
+        }
+    }
+}
+
+/// Image ID of the image with a digest
+fn image_id(digest: &str) -> String {
+    digest.trim_start_matches("sha256:").to_string()
+}
+
+impl MemoryState {
+    /// Returns the digest of an image, pulling it first if needed as `podman run` does
+    fn ensure_image(&mut self, image: &str) -> String {
+        self.images.entry(normalize_image(image)).or_insert_with(|| image_digest(image)).clone()
+    }
+
+    /// Returns the digest of a local image, looked up by name or image ID
+    fn find_image(&self, image: &str) -> Option<String> {
+        self.images.get(&normalize_image(image)).cloned()
+            .or_else(|| self.images.values().find(|digest| image_id(digest) == image).cloned())
+    }
+}
+
+fn status_text(running: bool) -> String {
+    if running { "Running".to_string() } else { "Exited".to_string() }
+}
+
+impl ContainerRuntime for MemoryRuntime {
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn pod_exists(&self, pod_name: &str) -> bool {
+        self.state.borrow().pods.contains_key(pod_name)
+    }
+
+    fn create_pod(&self, pod_name: &str, ports: &[String]) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        if state.pods.contains_key(pod_name) {
+            return Err(format!("Failed to create pod: {} (already exists)", pod_name).into());
+        }
+        state.pods.insert(pod_name.to_string(), MemoryPod {
+            ports: ports.iter().map(|port| normalize_port(port)).collect(),
+            running: false,
+        });
+        Ok(())
+    }
+
+    fn start_pod(&self, pod_name: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        match state.pods.get_mut(pod_name) {
+            Some(pod) => pod.running = true,
+            None => return Err(format!("Failed to start pod: {}", pod_name).into()),
+        }
+        for container in state.containers.values_mut().filter(|c| c.pod == pod_name) {
+            container.running = true;
+        }
+        Ok(())
+    }
+
+    fn stop_pod(&self, pod_name: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        match state.pods.get_mut(pod_name) {
+            Some(pod) => pod.running = false,
+            None => return Err(format!("Failed to stop pod: {}", pod_name).into()),
+        }
+        for container in state.containers.values_mut().filter(|c| c.pod == pod_name) {
+            container.running = false;
+        }
+        Ok(())
+    }
+
+    fn remove_pod(&self, pod_name: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        if state.pods.remove(pod_name).is_none() {
+            return Err(format!("Failed to remove pod: {}", pod_name).into());
+        }
+        state.containers.retain(|_, container| container.pod != pod_name);
+        Ok(())
+    }
+
+    fn list_pods(&self) -> AppResult<Vec<PodStatus>> {
+        Ok(self.state.borrow().pods.iter()
+            .map(|(name, pod)| PodStatus {
+                name: name.clone(),
+                status: status_text(pod.running),
+            })
+            .collect())
+    }
+
+    fn container_exists(&self, container_name: &str) -> bool {
+        self.state.borrow().containers.contains_key(container_name)
+    }
+
+    fn create_container(&self, pod_name: &str, container: &Container, data_path: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        let failed = |reason: &str| AppError::container_create(&container.name, pod_name, reason);
+
+        if state.containers.contains_key(&container.name) {
+            return Err(failed("name already in use"));
+        }
+        match state.pods.get_mut(pod_name) {
+            Some(pod) => pod.running = true,
+            None => return Err(failed("no such pod")),
+        }
+
+        // `podman run` pulls missing images and creates missing volumes implicitly
+        let mut info = ContainerInfo::from_config(container, data_path);
+        for mount in info.mounts.iter().filter(|mount| mount.kind == MountType::Volume) {
+            state.volumes.insert(mount.source.clone());
+        }
+        info.image_id = image_id(&state.ensure_image(&container.image));
+        state.containers.insert(container.name.clone(), MemoryContainer {
+            pod: pod_name.to_string(),
+            info,
+            running: true,
+        });
+        Ok(())
+    }
+
+    fn stop_container(&self, container_name: &str) -> AppResult<()> {
+        match self.state.borrow_mut().containers.get_mut(container_name) {
+            Some(container) => {
+                container.running = false;
+                Ok(())
+            }
+            None => Err(format!("Failed to stop container: {}", container_name).into()),
+        }
+    }
+
+    fn remove_container(&self, container_name: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        match state.containers.get(container_name) {
+            Some(container) if !container.running => {
+                state.containers.remove(container_name);
+                Ok(())
+            }
+            _ => Err(format!("Failed to remove container: {}", container_name).into()),
+        }
+    }
+
+    fn container_image(&self, container_name: &str) -> Option<String> {
+        self.state.borrow().containers.get(container_name).map(|c| c.info.image.clone())
+    }
+
+    fn inspect_container(&self, container_name: &str) -> AppResult<Option<ContainerInfo>> {
+        Ok(self.state.borrow().containers.get(container_name).map(|container| container.info.clone()))
+    }
+
+    fn container_state(&self, container_name: &str) -> AppResult<Option<ContainerState>> {
+        Ok(self.state.borrow().containers.get(container_name).map(|container| ContainerState {
+            running: container.running,
+            exit_code: 0,
+        }))
+    }
+
+    fn run_healthcheck(&self, container_name: &str) -> AppResult<bool> {
+        Ok(self.state.borrow().containers.get(container_name).is_some_and(|container| container.running))
+    }
+
+    fn start_container(&self, container_name: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        let Some(pod_name) = state.containers.get(container_name).map(|container| container.pod.clone()) else {
+            return Err(format!("Failed to start container: {}", container_name).into());
+        };
+        if let Some(pod) = state.pods.get_mut(&pod_name) {
+            pod.running = true;
+        }
+        if let Some(container) = state.containers.get_mut(container_name) {
+            container.running = true;
+        }
+        Ok(())
+    }
+
+    fn rename_container(&self, container_name: &str, new_name: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        if state.containers.contains_key(new_name) {
+            return Err(format!("Failed to rename container {} to {}: name already in use", container_name, new_name).into());
+        }
+        match state.containers.remove(container_name) {
+            Some(container) => {
+                state.containers.insert(new_name.to_string(), container);
+                Ok(())
+            }
+            None => Err(format!("Failed to rename container {} to {}: no such container", container_name, new_name).into()),
+        }
+    }
+
+    fn inspect_pod(&self, pod_name: &str) -> AppResult<Option<PodInfo>> {
+        let state = self.state.borrow();
+        Ok(state.pods.get(pod_name).map(|pod| {
+            let mut ports = pod.ports.clone();
+            ports.sort();
+            PodInfo {
+                ports,
+                containers: state.containers.iter()
+                    .filter(|(_, container)| container.pod == pod_name)
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            }
+        }))
+    }
+
+    fn list_containers(&self) -> AppResult<Vec<ContainerStatus>> {
+        Ok(self.state.borrow().containers.iter()
+            .map(|(name, container)| ContainerStatus {
+                name: name.clone(),
+                status: status_text(container.running),
+                image: container.info.image.clone(),
+            })
+            .collect())
+    }
+
+    fn pull_image(&self, image: &str, _tls_verify: bool) -> AppResult<()> {
+        if image.is_empty() {
+            return Err(AppError::pull(image, "empty image name"));
+        }
+        self.state.borrow_mut().images.insert(normalize_image(image), image_digest(image));
+        Ok(())
+    }
+
+    fn tag_image(&self, image: &str, target: &str) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        let Some(digest) = state.find_image(image) else {
+            return Err(format!("Failed to tag image {}: no such image", image).into());
+        };
+        state.images.insert(normalize_image(target), digest);
+        Ok(())
+    }
+
+    fn inspect_image(&self, image: &str) -> AppResult<Option<ImageInfo>> {
+        Ok(self.state.borrow().find_image(image).map(|digest| ImageInfo {
+            id: image_id(&digest),
+            digest,
+        }))
+    }
+
+    fn play_kube(&self, manifest: &str) -> AppResult<()> {
+        let pods = kube::from_yaml(manifest)?;
+        let mut state = self.state.borrow_mut();
+
+        for pod in pods {
+            let pod_name = pod.metadata.name;
+            state.containers.retain(|_, container| container.pod != pod_name);
+
+            let mut ports = Vec::new();
+            for container in pod.spec.containers {
+                for port in &container.ports {
+                    let host_port = port.host_port.unwrap_or(port.container_port);
+                    let host_ip = port.host_ip.as_deref().map(|ip| format!("{}:", ip)).unwrap_or_default();
+                    ports.push(normalize_port(&format!(
+                        "{}{}:{}/{}", host_ip, host_port, port.container_port, port.protocol.to_lowercase()
+                    )));
+                }
+                // Podman lists secrets apart from the mounts
+                let mounts: Vec<MountInfo> = container.volume_mounts.iter()
+                    .filter_map(|mount| {
+                        let volume = pod.spec.volumes.iter().find(|v| v.name == mount.name && v.secret.is_none())?;
+                        let (kind, source) = match (&volume.host_path, &volume.persistent_volume_claim) {
+                            (Some(host_path), _) => (MountType::Bind, host_path.path.clone()),
+                            (None, Some(claim)) => (MountType::Volume, claim.claim_name.clone()),
+                            (None, None) => (MountType::Tmpfs, String::new()),
+                        };
+                        // Kubernetes has no SELinux relabel option for a single mount
+                        let size = volume.empty_dir.as_ref()
+                            .and_then(|empty_dir| empty_dir.size_limit.as_deref())
+                            .and_then(parse_memory);
+                        Some(MountInfo {
+                            kind,
+                            source,
+                            destination: mount.mount_path.clone(),
+                            read_only: mount.read_only,
+                            selinux: None,
+                            size,
+                        })
+                    })
+                    .collect();
+                let mut secrets: Vec<String> = container.volume_mounts.iter()
+                    .filter_map(|mount| pod.spec.volumes.iter().find(|v| v.name == mount.name)?.secret.as_ref())
+                    .map(|secret| secret.secret_name.clone())
+                    .collect();
+                secrets.sort();
+                for mount in mounts.iter().filter(|mount| mount.kind == MountType::Volume) {
+                    state.volumes.insert(mount.source.clone());
+                }
+
+                let limits = container.resources.map(|resources| resources.limits).unwrap_or_default();
+                let healthcheck = container.liveness_probe.map(|probe| HealthInfo {
+                    command: probe.exec.command.last().cloned().unwrap_or_default(),
+                    interval: Duration::from_secs(probe.period_seconds.unwrap_or(30)),
+                    timeout: Duration::from_secs(probe.timeout_seconds.unwrap_or(30)),
+                    retries: probe.failure_threshold.unwrap_or(3),
+                    start_period: Duration::from_secs(probe.initial_delay_seconds.unwrap_or_default()),
+                });
+
+                let digest = state.ensure_image(&container.image);
+                state.containers.insert(container.name.clone(), MemoryContainer {
+                    pod: pod_name.clone(),
+                    info: ContainerInfo {
+                        image: container.image,
+                        image_id: image_id(&digest),
+                        env: container.env.into_iter()
+                            .filter_map(|var| Some((var.name, var.value?)))
+                            .collect(),
+                        mounts,
+                        restart_policy: "no".to_string(),
+                        healthcheck,
+                        memory: limits.get("memory").and_then(|memory| memory.parse().ok()).unwrap_or_default(),
+                        nano_cpus: limits.get("cpu")
+                            .and_then(|cpu| cpu.parse::<f64>().ok())
+                            .map(|cpus| (cpus * 1e9).round() as u64)
+                            .unwrap_or_default(),
+                        pids_limit: 0,
+                        entrypoint: container.command,
+                        command: container.args,
+                        workdir: container.working_dir.unwrap_or_default(),
+                        user: container.security_context
+                            .and_then(|context| context.run_as_user.map(|uid| match context.run_as_group {
+                                Some(gid) => format!("{}:{}", uid, gid),
+                                None => uid.to_string(),
+                            }))
+                            .unwrap_or_default(),
+                        secrets,
+                    },
+                    running: true,
+                });
+            }
+            state.pods.insert(pod_name, MemoryPod { ports, running: true });
+        }
+        Ok(())
+    }
+
+    fn prune_images(&self) -> AppResult<()> {
+        let mut state = self.state.borrow_mut();
+        let in_use: BTreeSet<String> = state.containers.values().map(|c| normalize_image(&c.info.image)).collect();
+        state.images.retain(|image, _| in_use.contains(image));
+        Ok(())
+    }
+
+    fn volume_exists(&self, volume_name: &str) -> bool {
+        self.state.borrow().volumes.contains(volume_name)
+    }
+
+    fn create_volume(&self, volume: &Volume) -> AppResult<()> {
+        if !self.state.borrow_mut().volumes.insert(volume.name.clone()) {
+            return Err(format!("Failed to create volume: {} (already exists)", volume.name).into());
+        }
+        Ok(())
+    }
+
+    fn secret_exists(&self, secret_name: &str) -> bool {
+        self.state.borrow().secrets.contains(secret_name)
+    }
+
+    fn create_secret(&self, secret_name: &str, _value: &str) -> AppResult<()> {
+        if !self.state.borrow_mut().secrets.insert(secret_name.to_string()) {
+            return Err(format!("Failed to create secret: {} (already exists)", secret_name).into());
+        }
+        Ok(())
+    }
+
+    fn is_logged_in(&self, registry: &str) -> bool {
+        self.state.borrow().logins.contains_key(registry)
+    }
+
+    fn login(&self, registry: &str, username: &str, _password: &str, _tls_verify: bool) -> AppResult<()> {
+        self.state.borrow_mut().logins.insert(registry.to_string(), username.to_string());
+        Ok(())
+    }
+
+    fn path_owner(&self, _path: &Path) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,106 @@
+//! Rendering of mount file templates.
+//!
+//! Templates are plain text with `{{ name }}` placeholders. The variables
+//! are `application_name`, `data_path`, `pod_name`, `container_name` and
+//! `env.<NAME>` for each of the container's environment variables. Any
+//! other text, including `$var` and `${var}`, is written unchanged.
+//!
+//! Inline `content` is part of the config file, so it is interpolated like
+//! every other value before it gets here: a literal `${var}` has to be
+//! written as `$${var}`, and `$$` as `$$$$`.
+
+use crate::{Config, Container, Pod};
+use std::collections::BTreeMap;
+
+/// Collects the variables available to the templates of a container's mounts
+pub fn variables(config: &Config, pod: &Pod, container: &Container) -> BTreeMap<String, String> {
+    let mut variables = BTreeMap::new();
+    variables.insert("application_name".to_string(), config.application_name.clone());
+    variables.insert("data_path".to_string(), config.data_path.clone());
+    variables.insert("pod_name".to_string(), pod.name.clone());
+    variables.insert("container_name".to_string(), container.name.clone());
+    for (key, value) in &container.env_vars {
+        variables.insert(format!("env.{}", key), value.clone());
+    }
+    variables
+}
+
+/// Replaces every `{{ name }}` placeholder, failing on unknown variables
+pub fn render(template: &str, variables: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            return Err("unclosed '{{' in template".to_string());
+        };
+        let name = rest[start + 2..start + end].trim();
+        match variables.get(name) {
+            Some(value) => rendered.push_str(value),
+            None => return Err(format!("unknown template variable '{}'", name)),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("container_name".to_string(), "web".to_string()),
+            ("env.PORT".to_string(), "8080".to_string()),
+        ])
+    }
+
+    #[test]
+    fn replaces_placeholders_with_or_without_spaces() {
+        let rendered = render("listen {{ env.PORT }}; # {{container_name}}", &sample());
+        assert_eq!(rendered.unwrap(), "listen 8080; # web");
+    }
+
+    #[test]
+    fn leaves_other_text_unchanged() {
+        let template = "proxy_set_header Host $host; ${var} { }";
+        assert_eq!(render(template, &sample()).unwrap(), template);
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        assert_eq!(render("{{ env.MISSING }}", &sample()), Err("unknown template variable 'env.MISSING'".to_string()));
+    }
+
+    #[test]
+    fn rejects_unclosed_placeholders() {
+        assert_eq!(render("{{ container_name }} {{ env.PORT", &sample()), Err("unclosed '{{' in template".to_string()));
+    }
+
+    #[test]
+    fn collects_config_pod_container_and_env_variables() {
+        let config: Config = serde_yaml::from_str(r#"
+application_name: shop
+is_podman_installed: true
+data_path: /srv/shop
+pods:
+  - name: shop-pod
+    containers:
+      - name: web
+        image: nginx
+        env_vars:
+          PORT: "8080"
+        ports: []
+        mounts: []
+"#).unwrap();
+        let pod = &config.pods[0];
+        let variables = variables(&config, pod, &pod.containers[0]);
+        assert_eq!(variables["application_name"], "shop");
+        assert_eq!(variables["data_path"], "/srv/shop");
+        assert_eq!(variables["pod_name"], "shop-pod");
+        assert_eq!(variables["container_name"], "web");
+        assert_eq!(variables["env.PORT"], "8080");
+        assert_eq!(variables.len(), 5);
+    }
+}
@@ -0,0 +1,152 @@
+//! Duration and size strings as accepted by Podman (e.g. "1m30s", "512m").
+
+use std::time::Duration;
+
+const KIB: u64 = 1024;
+
+/// Parses a Go-style duration such as "30s", "1m30s", "500ms" or "2h"
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value == "0" {
+        return Some(Duration::ZERO);
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let number: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(rest.len());
+        let seconds = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+        // Durations too long to represent are invalid rather than a panic
+        total = total.checked_add(Duration::try_from_secs_f64(number * seconds).ok()?)?;
+    }
+
+    if value.is_empty() { None } else { Some(total) }
+}
+
+/// Formats a duration the way Podman prints it (e.g. "1m30s", "500ms")
+pub fn format_duration(duration: Duration) -> String {
+    if duration.is_zero() {
+        return "0s".to_string();
+    }
+    if duration.subsec_nanos() != 0 && duration.as_secs() == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+
+    let secs = duration.as_secs();
+    let mut out = String::new();
+    if secs >= 3600 {
+        out.push_str(&format!("{}h", secs / 3600));
+    }
+    if secs % 3600 >= 60 {
+        out.push_str(&format!("{}m", secs % 3600 / 60));
+    }
+    if !secs.is_multiple_of(60) || out.is_empty() {
+        out.push_str(&format!("{}s", secs % 60));
+    }
+    out
+}
+
+/// Parses a memory size such as "512m", "1g" or "1048576" into bytes.
+///
+/// Like Podman, the units are binary and case-insensitive ("512MB" and
+/// "512MiB" are both 512 * 1024 * 1024 bytes).
+pub fn parse_memory(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let digits = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let number: f64 = value[..digits].parse().ok()?;
+    let multiplier = match &value[digits..] {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => KIB,
+        "m" | "mb" | "mib" => KIB * KIB,
+        "g" | "gb" | "gib" => KIB * KIB * KIB,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Formats a byte count with the largest unit that divides it (e.g. "512m")
+pub fn format_memory(bytes: u64) -> String {
+    for (unit, size) in [("g", KIB * KIB * KIB), ("m", KIB * KIB), ("k", KIB)] {
+        if bytes >= size && bytes.is_multiple_of(size) {
+            return format!("{}{}", bytes / size, unit);
+        }
+    }
+    bytes.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_and_combined_duration_units() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1m30s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("10us"), Some(Duration::from_micros(10)));
+        assert_eq!(parse_duration(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for value in ["", "30", "5d", "s", "1m30", "-5s", "1..5s"] {
+            assert_eq!(parse_duration(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_durations_too_long_to_represent() {
+        assert_eq!(parse_duration("99999999999999999999h"), None);
+        assert_eq!(parse_duration("9223372036854775807h9223372036854775807h"), None);
+    }
+
+    #[test]
+    fn formats_durations_like_podman() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_millis(500)), "500ms");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h");
+        assert_eq!(format_duration(Duration::from_secs(3661)), "1h1m1s");
+    }
+
+    #[test]
+    fn parses_binary_memory_units_case_insensitively() {
+        assert_eq!(parse_memory("1048576"), Some(1048576));
+        assert_eq!(parse_memory("512b"), Some(512));
+        assert_eq!(parse_memory("64k"), Some(64 * KIB));
+        assert_eq!(parse_memory("512m"), Some(512 * KIB * KIB));
+        assert_eq!(parse_memory("512MB"), Some(512 * KIB * KIB));
+        assert_eq!(parse_memory("512MiB"), Some(512 * KIB * KIB));
+        assert_eq!(parse_memory("1.5g"), Some(3 * KIB * KIB * KIB / 2));
+    }
+
+    #[test]
+    fn rejects_invalid_memory_sizes() {
+        for value in ["", "m", "12t", "1 g", "-1m"] {
+            assert_eq!(parse_memory(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn formats_memory_with_the_largest_dividing_unit() {
+        assert_eq!(format_memory(512 * KIB * KIB), "512m");
+        assert_eq!(format_memory(2 * KIB * KIB * KIB), "2g");
+        assert_eq!(format_memory(1536 * KIB), "1536k");
+        assert_eq!(format_memory(1000), "1000");
+    }
+}
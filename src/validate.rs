@@ -0,0 +1,819 @@
+//! Semantic validation of the configuration.
+//!
+//! Deserializing only proves the YAML has the right shape. The checks here
+//! catch problems that would otherwise surface later as Podman failures,
+//! and point at the offending line and column of the config file.
+
+use crate::runtime::PortMapping;
+use crate::units::{parse_duration, parse_memory};
+use crate::interpolate::is_variable_name;
+use crate::{collect_pod_ports, image_registry, probe, template, Config, Mount, MountType, PasswordSource, Probe, SecretType};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// One problem found in the configuration
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// 1-based line and column in the config file, if the value could be located
+    pub location: Option<(usize, usize)>,
+    /// Pod/container path of the offending value (e.g., "pod web-pod > container nginx > ports[0]")
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.location {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Step of a path into the YAML document
+#[derive(Debug, Clone, Copy)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// A non-empty, non-comment line of the config file
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+/// Finds the line and column of a value in block-style YAML.
+///
+/// This only understands the block mappings and sequences the config file
+/// is written in. It gives up (returning the closest parent found so far)
+/// when it meets flow-style collections.
+fn locate(source: &str, path: &[Segment]) -> Option<(usize, usize)> {
+    let mut region: Vec<Line> = source.lines()
+        .enumerate()
+        .filter_map(|(index, raw)| {
+            let text = raw.trim_start();
+            if text.is_empty() || text.starts_with('#') || text == "---" {
+                return None;
+            }
+            Some(Line { number: index + 1, indent: raw.len() - text.len(), text: text.trim_end() })
+        })
+        .collect();
+    let mut found = None;
+
+    for segment in path {
+        let Some(indent) = region.first().map(|line| line.indent) else { return found };
+        let (position, line, rest) = match *segment {
+            Segment::Key(key) => {
+                let Some(position) = region.iter().position(|line| {
+                    line.indent == indent && key_of(line.text) == Some(key)
+                }) else {
+                    return found;
+                };
+                let line = region[position];
+                let after_colon = line.text.find(':').map(|i| i + 1).unwrap_or(line.text.len());
+                (position, line, after_colon)
+            }
+            Segment::Index(index) => {
+                let Some((position, line)) = region.iter()
+                    .enumerate()
+                    .filter(|(_, line)| line.indent == indent && is_item(line.text))
+                    .nth(index)
+                else {
+                    return found;
+                };
+                (position, *line, 1)
+            }
+        };
+
+        let value = &line.text[rest..];
+        let value_offset = rest + (value.len() - value.trim_start().len());
+        let value = value.trim_start();
+        found = Some(if value.is_empty() {
+            (line.number, line.indent + 1)
+        } else {
+            (line.number, line.indent + value_offset + 1)
+        });
+
+        // The children of this node: an inline mapping after "- ", followed
+        // by the deeper-indented lines (sequences may share the key's indent)
+        let mut children = Vec::new();
+        if matches!(segment, Segment::Index(_)) && !value.is_empty() {
+            children.push(Line { number: line.number, indent: line.indent + value_offset, text: value });
+        } else if !value.is_empty() {
+            return found;
+        }
+        for next in &region[position + 1..] {
+            let nested = next.indent > line.indent
+                || matches!(segment, Segment::Key(_)) && next.indent == line.indent && is_item(next.text);
+            if !nested {
+                break;
+            }
+            children.push(*next);
+        }
+        if children.is_empty() {
+            return found;
+        }
+        region = children;
+    }
+
+    found
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Returns the key of a "key: value" line, without quotes
+fn key_of(text: &str) -> Option<&str> {
+    let (key, _) = text.split_once(':')?;
+    let key = key.trim();
+    Some(key.trim_matches(|c| c == '"' || c == '\''))
+}
+
+/// Collects diagnostics, locating each one in the source text
+struct Collector<'a> {
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Collector<'_> {
+    fn report(&mut self, path: &[Segment], label: String, message: String) {
+        self.diagnostics.push(Diagnostic {
+            location: locate(self.source, path),
+            path: label,
+            message,
+        });
+    }
+}
+
+/// Returns true for "uid" or "uid:gid" with numeric ids
+fn is_numeric_owner(owner: &str) -> bool {
+    let (uid, gid) = owner.split_once(':').map_or((owner, None), |(uid, gid)| (uid, Some(gid)));
+    uid.parse::<u32>().is_ok() && gid.is_none_or(|gid| gid.parse::<u32>().is_ok())
+}
+
+/// Checks a mount; `volumes` are the names declared in the top-level `volumes` section
+/// and `variables` those available to its inline content
+fn mount_problem(mount: &Mount, volumes: &HashSet<&str>, variables: &BTreeMap<String, String>) -> Option<String> {
+    let spec = match mount.parse() {
+        Ok(spec) => spec,
+        Err(problem) => return Some(problem),
+    };
+    let source = spec.source.as_deref().unwrap_or_default();
+    if spec.target.is_empty() || (spec.kind != MountType::Tmpfs && source.is_empty()) {
+        return Some(format!("mount '{}' has an empty source or target", mount));
+    }
+    if !spec.target.starts_with('/') {
+        return Some(format!("mount '{}' must use an absolute container path", mount));
+    }
+    match spec.kind {
+        kind if kind.is_bind() && !source.starts_with('/') => {
+            Some(format!("bind mount '{}' must use a host path starting with '/' (relative to data_path)", mount))
+        }
+        MountType::Volume if !volumes.contains(source) => {
+            Some(format!("volume '{}' is not declared in the top-level volumes section", source))
+        }
+        MountType::Tmpfs if spec.source.is_some() => Some(format!("tmpfs mount '{}' cannot have a source", mount)),
+        _ if spec.size.is_some() && spec.kind != MountType::Tmpfs => {
+            Some(format!("mount '{}' has a size, which only applies to tmpfs mounts", mount))
+        }
+        _ if spec.size.as_deref().is_some_and(|size| parse_memory(size).is_none_or(|bytes| bytes == 0)) => {
+            Some(format!("invalid tmpfs size '{}', expected e.g. 64m", spec.size.unwrap_or_default()))
+        }
+        MountType::Tmpfs if spec.selinux.is_some() => {
+            Some(format!("tmpfs mount '{}' cannot have an selinux label", mount))
+        }
+        kind @ (MountType::Volume | MountType::Tmpfs) if spec.owner.is_some() || spec.mode.is_some() => {
+            Some(format!("{} mount '{}' cannot have an owner or mode, which only apply to bind mounts", kind, mount))
+        }
+        _ if spec.mode.is_some() && spec.mode_bits().is_none() => {
+            Some(format!("invalid mode '{}', expected octal permissions like 0750", spec.mode.unwrap_or_default()))
+        }
+        _ if spec.owner.as_deref().is_some_and(|owner| !is_numeric_owner(owner)) => {
+            Some(format!("invalid owner '{}', expected uid[:gid]", spec.owner.unwrap_or_default()))
+        }
+        _ if spec.template.is_some() && spec.content.is_some() => {
+            Some(format!("mount '{}' has both a template and inline content", mount))
+        }
+        kind if (spec.template.is_some() || spec.content.is_some()) && (!kind.is_bind() || kind == MountType::Dir) => {
+            Some(format!("{} mount '{}' cannot be seeded from a template, which only applies to files", kind, mount))
+        }
+        _ if spec.overwrite && spec.template.is_none() && spec.content.is_none() => {
+            Some(format!("mount '{}' sets overwrite without a template or content", mount))
+        }
+        _ => spec.content.as_deref()
+            .and_then(|content| template::render(content, variables).err())
+            .map(|problem| format!("invalid content of mount '{}': {}", mount, problem)),
+    }
+}
+
+/// Checks that exactly one password source is set and that it is usable
+fn password_source_problem(source: &PasswordSource) -> Option<String> {
+    match (&source.env, &source.file, &source.command) {
+        (Some(variable), None, None) if !is_variable_name(variable) => {
+            Some(format!("invalid environment variable name '{}'", variable))
+        }
+        (Some(_), None, None) => None,
+        (None, Some(value), None) | (None, None, Some(value)) if value.trim().is_empty() => {
+            Some("password file or command is empty".to_string())
+        }
+        (None, Some(_), None) | (None, None, Some(_)) => None,
+        _ => Some("set exactly one of env, file or command".to_string()),
+    }
+}
+
+fn probe_problem(probe: &Probe) -> Option<String> {
+    match (&probe.command, &probe.http) {
+        (Some(command), None) if command.trim().is_empty() => Some("probe command is empty".to_string()),
+        (None, Some(url)) if probe::parse_http_url(url).is_none() => {
+            Some(format!("invalid probe URL '{}', expected http://host[:port][/path]", url))
+        }
+        (Some(_), None) | (None, Some(_)) => None,
+        _ => Some("set exactly one of command or http".to_string()),
+    }
+}
+
+/// Checks that a registry is given as it appears in image names, e.g. "ghcr.io" or "localhost:5000"
+fn registry_host_problem(host: &str) -> Option<String> {
+    if host.trim().is_empty() {
+        Some("registry host is empty".to_string())
+    } else if host.contains("://") || host.contains('/') || host.chars().any(char::is_whitespace) {
+        Some(format!("invalid registry host '{}', expected host[:port] without a scheme or path", host))
+    } else {
+        None
+    }
+}
+
+/// Validates the configuration, returning every problem found.
+///
+/// `source` is the text the configuration was parsed from and is only used
+/// to attach line and column numbers.
+pub fn validate(config: &Config, source: &str) -> Vec<Diagnostic> {
+    use Segment::{Index, Key};
+
+    let mut collector = Collector { source, diagnostics: Vec::new() };
+    let mut pod_names: HashMap<&str, usize> = HashMap::new();
+    let mut container_names: HashMap<&str, &str> = HashMap::new();
+    // Host binding (ip, port, protocol) -> index and name of the pod that publishes it
+    let mut host_ports: HashMap<(String, u16, String), (usize, &str)> = HashMap::new();
+
+    if config.pods.is_empty() {
+        collector.report(&[Key("pods")], "pods".to_string(), "no pods configured".to_string());
+    }
+
+    // The name ends up in file names and generated file headers
+    let name = &config.application_name;
+    if name.contains('/') || name.contains("..") || name.chars().any(char::is_control) {
+        collector.report(
+            &[Key("application_name")],
+            "application_name".to_string(),
+            format!("application name '{}' cannot contain '/', '..' or control characters", name.escape_debug()),
+        );
+    }
+
+    let mut volume_names = HashSet::new();
+    for (volume_index, volume) in config.volumes.iter().enumerate() {
+        let path = [Key("volumes"), Index(volume_index), Key("name")];
+        if volume.name.trim().is_empty() {
+            collector.report(&path, format!("volumes[{}]", volume_index), "volume name is empty".to_string());
+        } else if !volume_names.insert(volume.name.as_str()) {
+            collector.report(&path, format!("volume {}", volume.name), format!("duplicate volume name '{}'", volume.name));
+        }
+    }
+
+    if config.registry_password.is_some() && config.registry_password_from.is_some() {
+        collector.report(&[Key("registry_password")], "registry_password".to_string(), "set only one of registry_password and registry_password_from".to_string());
+    }
+    if let Some(problem) = config.registry_password_from.as_ref().and_then(password_source_problem) {
+        collector.report(&[Key("registry_password_from")], "registry_password_from".to_string(), problem);
+    }
+
+    let mut registry_hosts = HashSet::new();
+    for (registry_index, registry) in config.registries.iter().enumerate() {
+        let path = [Key("registries"), Index(registry_index)];
+        let label = if registry.host.trim().is_empty() { format!("registries[{}]", registry_index) } else { format!("registry {}", registry.host) };
+        if let Some(problem) = registry_host_problem(&registry.host) {
+            collector.report(&[Key("registries"), Index(registry_index), Key("host")], label.clone(), problem);
+        } else if !registry_hosts.insert(registry.host.as_str()) {
+            collector.report(&path, label.clone(), format!("duplicate registry '{}'", registry.host));
+        }
+        if registry.password_from.is_some() && registry.username.is_none() {
+            collector.report(&path, label.clone(), "password_from is set without a username".to_string());
+        }
+        if let Some(problem) = registry.password_from.as_ref().and_then(password_source_problem) {
+            collector.report(&[Key("registries"), Index(registry_index), Key("password_from")], label.clone(), problem);
+        }
+        for (mirror_index, mirror) in registry.mirrors.iter().enumerate() {
+            let host = mirror.split('/').next().unwrap_or_default();
+            if mirror.contains("://") || registry_host_problem(host).is_some() {
+                collector.report(
+                    &[Key("registries"), Index(registry_index), Key("mirrors"), Index(mirror_index)],
+                    format!("{} > mirrors[{}]", label, mirror_index),
+                    format!("invalid mirror '{}', expected host[:port][/path]", mirror),
+                );
+            }
+        }
+    }
+    if let Some(host) = &config.private_registry
+        && registry_hosts.contains(host.as_str())
+    {
+        collector.report(&[Key("private_registry")], "private_registry".to_string(), format!("registry '{}' is also listed in registries", host));
+    }
+    let blocked_registries: HashSet<&str> = config.registries.iter()
+        .filter(|registry| registry.blocked)
+        .map(|registry| registry.host.as_str())
+        .collect();
+
+    let mut search_registries = HashSet::new();
+    for (search_index, host) in config.unqualified_search_registries.iter().enumerate() {
+        let path = [Key("unqualified_search_registries"), Index(search_index)];
+        let label = format!("unqualified_search_registries[{}]", search_index);
+        if let Some(problem) = registry_host_problem(host) {
+            collector.report(&path, label, problem);
+        } else if !search_registries.insert(host.as_str()) {
+            collector.report(&path, label, format!("duplicate registry '{}'", host));
+        } else if blocked_registries.contains(host.as_str()) {
+            collector.report(&path, label, format!("registry '{}' is blocked", host));
+        }
+    }
+
+    let mut secret_names = HashSet::new();
+    for (secret_index, secret) in config.secrets.iter().enumerate() {
+        let path = [Key("secrets"), Index(secret_index)];
+        let label = if secret.name.trim().is_empty() { format!("secrets[{}]", secret_index) } else { format!("secret {}", secret.name) };
+        if secret.name.trim().is_empty() {
+            collector.report(&path, label.clone(), "secret name is empty".to_string());
+        } else if !secret_names.insert(secret.name.as_str()) {
+            collector.report(&path, label.clone(), format!("duplicate secret name '{}'", secret.name));
+        }
+        match (&secret.file, &secret.env) {
+            (Some(_), Some(_)) | (None, None) => {
+                collector.report(&path, label, "secret must have exactly one of file or env".to_string());
+            }
+            (None, Some(variable)) if !is_variable_name(variable) => {
+                collector.report(&path, label, format!("invalid environment variable name '{}'", variable));
+            }
+            _ => {}
+        }
+    }
+
+    for (pod_index, pod) in config.pods.iter().enumerate() {
+        let pod_path = [Key("pods"), Index(pod_index)];
+        let pod_label = format!("pod {}", pod.name);
+
+        if pod.name.trim().is_empty() {
+            collector.report(&[Key("pods"), Index(pod_index), Key("name")], format!("pods[{}]", pod_index), "pod name is empty".to_string());
+        } else if let Some(first) = pod_names.insert(&pod.name, pod_index) {
+            collector.report(
+                &[Key("pods"), Index(pod_index), Key("name")],
+                pod_label.clone(),
+                format!("duplicate pod name '{}' (also used by pods[{}])", pod.name, first),
+            );
+        }
+        if pod.containers.is_empty() {
+            collector.report(&pod_path, pod_label.clone(), "pod has no containers".to_string());
+        }
+
+        for (container_index, container) in pod.containers.iter().enumerate() {
+            let at = |rest: &[Segment<'static>]| {
+                let mut path = vec![Key("pods"), Index(pod_index), Key("containers"), Index(container_index)];
+                path.extend_from_slice(rest);
+                path
+            };
+            let label = format!("{} > container {}", pod_label, container.name);
+
+            if container.name.trim().is_empty() {
+                collector.report(&at(&[Key("name")]), format!("{} > containers[{}]", pod_label, container_index), "container name is empty".to_string());
+            } else if let Some(other_pod) = container_names.insert(&container.name, &pod.name) {
+                collector.report(
+                    &at(&[Key("name")]),
+                    label.clone(),
+                    format!("duplicate container name '{}' (also used in pod {})", container.name, other_pod),
+                );
+            }
+
+            if container.image.trim().is_empty() {
+                collector.report(&at(&[Key("image")]), label.clone(), "image is empty".to_string());
+            } else if blocked_registries.contains(image_registry(&container.image)) {
+                collector.report(&at(&[Key("image")]), label.clone(), format!("image '{}' is pulled from blocked registry '{}'", container.image, image_registry(&container.image)));
+            }
+
+            for (port_index, port) in container.ports.iter().enumerate() {
+                if PortMapping::parse(port).is_none() {
+                    collector.report(
+                        &at(&[Key("ports"), Index(port_index)]),
+                        format!("{} > ports[{}]", label, port_index),
+                        format!("invalid port mapping '{}', expected [host_ip:]host_port:container_port[/protocol]", port),
+                    );
+                }
+            }
+
+            let variables = template::variables(config, pod, container);
+            for (mount_index, mount) in container.mounts.iter().enumerate() {
+                if let Some(problem) = mount_problem(mount, &volume_names, &variables) {
+                    collector.report(&at(&[Key("mounts"), Index(mount_index)]), format!("{} > mounts[{}]", label, mount_index), problem);
+                }
+            }
+
+            for (secret_index, secret) in container.secrets.iter().enumerate() {
+                let spec = secret.spec();
+                let problem = if !secret_names.contains(spec.name.as_str()) {
+                    Some(format!("secret '{}' is not declared in the top-level secrets section", spec.name))
+                } else if spec.target.as_deref().is_some_and(str::is_empty) {
+                    Some(format!("secret '{}' has an empty target", spec.name))
+                } else if spec.kind == SecretType::Env && !is_variable_name(spec.target.as_deref().unwrap_or(&spec.name)) {
+                    Some(format!(
+                        "secret '{}' needs a target that is a valid environment variable name", spec.name
+                    ))
+                } else {
+                    None
+                };
+                if let Some(problem) = problem {
+                    collector.report(&at(&[Key("secrets"), Index(secret_index)]), format!("{} > secrets[{}]", label, secret_index), problem);
+                }
+            }
+
+            if let Some(health) = &container.healthcheck {
+                if health.command.trim().is_empty() {
+                    collector.report(&at(&[Key("healthcheck"), Key("command")]), format!("{} > healthcheck", label), "healthcheck command is empty".to_string());
+                }
+                let timings = [("interval", &health.interval), ("timeout", &health.timeout), ("start_period", &health.start_period)];
+                for (field, value) in timings {
+                    if let Some(value) = value
+                        && parse_duration(value).is_none()
+                    {
+                        collector.report(
+                            &at(&[Key("healthcheck"), Key(field)]),
+                            format!("{} > healthcheck.{}", label, field),
+                            format!("invalid duration '{}', expected e.g. 30s, 1m30s or 500ms", value),
+                        );
+                    }
+                }
+            }
+
+            if let Some(problem) = container.probe.as_ref().and_then(probe_problem) {
+                collector.report(&at(&[Key("probe")]), format!("{} > probe", label), problem);
+            }
+
+            if let Some(resources) = &container.resources {
+                if let Some(memory) = &resources.memory
+                    && parse_memory(memory).is_none_or(|bytes| bytes == 0)
+                {
+                    collector.report(
+                        &at(&[Key("resources"), Key("memory")]),
+                        format!("{} > resources.memory", label),
+                        format!("invalid memory limit '{}', expected e.g. 512m or 1g", memory),
+                    );
+                }
+                if let Some(cpus) = resources.cpus
+                    && cpus <= 0.0
+                {
+                    collector.report(&at(&[Key("resources"), Key("cpus")]), format!("{} > resources.cpus", label), "cpus must be greater than 0".to_string());
+                }
+                if let Some(pids_limit) = resources.pids_limit
+                    && (pids_limit == 0 || pids_limit < -1)
+                {
+                    collector.report(
+                        &at(&[Key("resources"), Key("pids_limit")]),
+                        format!("{} > resources.pids_limit", label),
+                        "pids_limit must be positive, or -1 for unlimited".to_string(),
+                    );
+                }
+            }
+
+            if let Some(workdir) = &container.workdir
+                && !workdir.starts_with('/')
+            {
+                collector.report(&at(&[Key("workdir")]), format!("{} > workdir", label), format!("workdir '{}' must be an absolute path", workdir));
+            }
+            if let Some(user) = &container.user
+                && user.split(':').any(|part| part.trim().is_empty())
+            {
+                collector.report(&at(&[Key("user")]), format!("{} > user", label), format!("invalid user '{}', expected user[:group]", user));
+            }
+        }
+
+        // Identical mappings in one pod are published once; any other reuse
+        // of a host port fails when the pod is created
+        for port in collect_pod_ports(pod) {
+            let Some(mapping) = PortMapping::parse(&port) else { continue };
+            let key = (mapping.host_ip.clone(), mapping.host_port, mapping.protocol.clone());
+            if let Some((other_index, other_pod)) = host_ports.insert(key, (pod_index, &pod.name)) {
+                let (container_index, port_index) = pod.containers.iter()
+                    .enumerate()
+                    .find_map(|(c, container)| container.ports.iter().position(|p| *p == port).map(|p| (c, p)))
+                    .unwrap_or_default();
+                let owner = if other_index == pod_index { "this pod".to_string() } else { format!("pod {}", other_pod) };
+                collector.report(
+                    &[Key("pods"), Index(pod_index), Key("containers"), Index(container_index), Key("ports"), Index(port_index)],
+                    format!("{} > container {} > ports[{}]", pod_label, pod.containers[container_index].name, port_index),
+                    format!("host port {}/{} is already published by {}", mapping.host_port, mapping.protocol, owner),
+                );
+            }
+        }
+    }
+
+    collector.diagnostics
+}
+
+/// Formats diagnostics as "file:line:column: path: message" lines
+pub fn report(config_path: &str, diagnostics: &[Diagnostic]) -> String {
+    let problems = if diagnostics.len() == 1 { "problem" } else { "problems" };
+    let mut out = format!("{} {} found", diagnostics.len(), problems);
+    for diagnostic in diagnostics {
+        let separator = if diagnostic.location.is_some() { ":" } else { ": " };
+        out.push_str(&format!("\n    {}{}{}", config_path, separator, diagnostic));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Segment::{Index, Key};
+
+    const HEADER: &str = "application_name: shop\nis_podman_installed: true\ndata_path: /srv/shop\n";
+
+    /// Validates `body` below the three header lines, so its first line is line 4
+    fn diagnostics(body: &str) -> Vec<String> {
+        let source = format!("{}{}", HEADER, body);
+        let config: Config = serde_yaml::from_str(&source).unwrap();
+        validate(&config, &source).iter().map(Diagnostic::to_string).collect()
+    }
+
+    const NESTED: &str = r#"application_name: shop
+pods:
+  - name: web-pod
+    containers:
+      - name: web
+        image: nginx
+        ports:
+          - "8080:80"
+          - "8443:443"
+      - name: sidecar
+        image: alpine
+# pods sharing the key's indent below
+  - name: api-pod
+    containers:
+    - name: api
+      ports:
+      - 9000:9000
+      mounts: ["/data:/data"]
+"#;
+
+    #[test]
+    fn locates_values_in_nested_pods_containers_and_ports() {
+        assert_eq!(locate(NESTED, &[Key("application_name")]), Some((1, 19)));
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(0), Key("containers"), Index(0), Key("ports"), Index(1)]), Some((9, 13)));
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(0), Key("containers"), Index(1), Key("image")]), Some((11, 16)));
+        // A key without a value points at the key itself
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(0), Key("containers")]), Some((4, 5)));
+    }
+
+    #[test]
+    fn locates_inline_items_and_sequences_at_the_key_indent() {
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(1)]), Some((13, 5)));
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(1), Key("name")]), Some((13, 11)));
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(1), Key("containers"), Index(0), Key("name")]), Some((15, 13)));
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(1), Key("containers"), Index(0), Key("ports"), Index(0)]), Some((17, 9)));
+    }
+
+    #[test]
+    fn falls_back_to_the_closest_parent() {
+        // Missing keys and items
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(0), Key("containers"), Index(0), Key("healthcheck")]), Some((5, 9)));
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(2), Key("name")]), Some((2, 1)));
+        assert_eq!(locate(NESTED, &[Key("volumes")]), None);
+        // Flow-style sequences are not entered
+        assert_eq!(locate(NESTED, &[Key("pods"), Index(1), Key("containers"), Index(0), Key("mounts"), Index(0)]), Some((18, 15)));
+    }
+
+    #[test]
+    fn reports_empty_and_duplicate_pod_and_container_names() {
+        assert_eq!(diagnostics("pods: []\n"), ["4:7: pods: no pods configured"]);
+        let problems = diagnostics(r#"pods:
+  - name: web
+    containers:
+      - name: app
+        image: nginx
+        env_vars: {}
+        ports: []
+        mounts: []
+  - name: web
+    containers:
+      - name: app
+        image: ""
+        env_vars: {}
+        ports: []
+        mounts: []
+  - name: empty
+    containers: []
+"#);
+        assert_eq!(problems, [
+            "12:11: pod web: duplicate pod name 'web' (also used by pods[0])",
+            "14:15: pod web > container app: duplicate container name 'app' (also used in pod web)",
+            "15:16: pod web > container app: image is empty",
+            "19:5: pod empty: pod has no containers",
+        ]);
+    }
+
+    #[test]
+    fn reports_invalid_and_conflicting_ports() {
+        let problems = diagnostics(r#"pods:
+  - name: web
+    containers:
+      - name: app
+        image: nginx
+        env_vars: {}
+        ports:
+          - "http:80"
+          - "8080:80"
+        mounts: []
+      - name: admin
+        image: nginx
+        env_vars: {}
+        ports:
+          - "8080:80"
+          - "8080:81"
+        mounts: []
+  - name: api
+    containers:
+      - name: api
+        image: nginx
+        env_vars: {}
+        ports:
+          - "8080:80"
+        mounts: []
+"#);
+        // Identical mappings within a pod are published once and not reported
+        assert_eq!(problems, [
+            "11:13: pod web > container app > ports[0]: invalid port mapping 'http:80', expected [host_ip:]host_port:container_port[/protocol]",
+            "19:13: pod web > container admin > ports[1]: host port 8080/tcp is already published by this pod",
+            "27:13: pod api > container api > ports[0]: host port 8080/tcp is already published by pod web",
+        ]);
+    }
+
+    #[test]
+    fn reports_invalid_mounts() {
+        let problems = diagnostics(r#"volumes:
+  - name: cache
+pods:
+  - name: web
+    containers:
+      - name: app
+        image: nginx
+        env_vars: {}
+        ports: []
+        mounts:
+          - "/html:/usr/share/nginx/html:ro"
+          - "html:relative"
+          - "missing:/data"
+          - "/logs:/logs:rw,ro"
+          - type: tmpfs
+            target: /tmp
+            mode: "0700"
+          - source: /conf
+            target: /etc/app
+            owner: nobody
+          - source: /app.conf
+            target: /etc/app.conf
+            content: "{{ unknown }}"
+"#);
+        assert_eq!(problems, [
+            "15:13: pod web > container app > mounts[1]: mount 'html:relative' must use an absolute container path",
+            "16:13: pod web > container app > mounts[2]: volume 'missing' is not declared in the top-level volumes section",
+            "17:13: pod web > container app > mounts[3]: mount '/logs:/logs:rw,ro' has conflicting options",
+            "18:13: pod web > container app > mounts[4]: tmpfs mount 'tmpfs /tmp' cannot have an owner or mode, which only apply to bind mounts",
+            "21:13: pod web > container app > mounts[5]: invalid owner 'nobody', expected uid[:gid]",
+            "24:13: pod web > container app > mounts[6]: invalid content of mount 'bind /app.conf:/etc/app.conf': unknown template variable 'unknown'",
+        ]);
+    }
+
+    #[test]
+    fn reports_volume_secret_and_container_secret_problems() {
+        let problems = diagnostics(r#"volumes:
+  - name: cache
+  - name: cache
+secrets:
+  - name: token
+    env: API-TOKEN
+  - name: key
+pods:
+  - name: web
+    containers:
+      - name: app
+        image: nginx
+        env_vars: {}
+        ports: []
+        mounts: []
+        secrets:
+          - token
+          - missing
+          - name: key
+            type: env
+            target: "1KEY"
+"#);
+        assert_eq!(problems, [
+            "6:11: volume cache: duplicate volume name 'cache'",
+            "8:5: secret token: invalid environment variable name 'API-TOKEN'",
+            "10:5: secret key: secret must have exactly one of file or env",
+            "21:13: pod web > container app > secrets[1]: secret 'missing' is not declared in the top-level secrets section",
+            "22:13: pod web > container app > secrets[2]: secret 'key' needs a target that is a valid environment variable name",
+        ]);
+    }
+
+    #[test]
+    fn reports_registry_problems() {
+        let problems = diagnostics(r#"private_registry: ghcr.io
+registries:
+  - host: https://registry.example.com
+  - host: ghcr.io
+    password_from:
+      env: GHCR_TOKEN
+  - host: ghcr.io
+    mirrors:
+      - "http://mirror.local"
+  - host: docker.io
+    blocked: true
+unqualified_search_registries: [docker.io]
+pods:
+  - name: web
+    containers:
+      - name: app
+        image: docker.io/library/nginx
+        env_vars: {}
+        ports: []
+        mounts: []
+"#);
+        assert_eq!(problems, [
+            "6:11: registry https://registry.example.com: invalid registry host 'https://registry.example.com', expected host[:port] without a scheme or path",
+            "7:5: registry ghcr.io: password_from is set without a username",
+            "10:5: registry ghcr.io: duplicate registry 'ghcr.io'",
+            "12:9: registry ghcr.io > mirrors[0]: invalid mirror 'http://mirror.local', expected host[:port][/path]",
+            "4:19: private_registry: registry 'ghcr.io' is also listed in registries",
+            "15:32: unqualified_search_registries[0]: registry 'docker.io' is blocked",
+            "20:16: pod web > container app: image 'docker.io/library/nginx' is pulled from blocked registry 'docker.io'",
+        ]);
+    }
+
+    #[test]
+    fn reports_healthcheck_probe_resource_and_process_problems() {
+        let problems = diagnostics(r#"pods:
+  - name: web
+    containers:
+      - name: app
+        image: nginx
+        env_vars: {}
+        ports: []
+        mounts: []
+        healthcheck:
+          command: " "
+          interval: 10 seconds
+        probe:
+          http: https://localhost/health
+        resources:
+          memory: 0
+          cpus: 0
+          pids_limit: -2
+        workdir: app
+        user: ":staff"
+"#);
+        assert_eq!(problems, [
+            "13:20: pod web > container app > healthcheck: healthcheck command is empty",
+            "14:21: pod web > container app > healthcheck.interval: invalid duration '10 seconds', expected e.g. 30s, 1m30s or 500ms",
+            "15:9: pod web > container app > probe: invalid probe URL 'https://localhost/health', expected http://host[:port][/path]",
+            "18:19: pod web > container app > resources.memory: invalid memory limit '0', expected e.g. 512m or 1g",
+            "19:17: pod web > container app > resources.cpus: cpus must be greater than 0",
+            "20:23: pod web > container app > resources.pids_limit: pids_limit must be positive, or -1 for unlimited",
+            "21:18: pod web > container app > workdir: workdir 'app' must be an absolute path",
+            "22:15: pod web > container app > user: invalid user ':staff', expected user[:group]",
+        ]);
+    }
+
+    #[test]
+    fn reports_unsafe_application_names() {
+        let source = "application_name: ../shop\nis_podman_installed: true\ndata_path: /srv/shop\npods: []\n";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let problems: Vec<String> = validate(&config, source).iter().map(Diagnostic::to_string).collect();
+        assert_eq!(problems, [
+            "4:7: pods: no pods configured",
+            "1:19: application_name: application name '../shop' cannot contain '/', '..' or control characters",
+        ]);
+    }
+
+    #[test]
+    fn formats_the_report_with_file_locations() {
+        let located = Diagnostic { location: Some((12, 5)), path: "pod web".to_string(), message: "pod has no containers".to_string() };
+        let unlocated = Diagnostic { location: None, path: "pods".to_string(), message: "no pods configured".to_string() };
+        assert_eq!(located.to_string(), "12:5: pod web: pod has no containers");
+        assert_eq!(unlocated.to_string(), "pods: no pods configured");
+        assert_eq!(
+            report("config.yaml", &[located.clone(), unlocated]),
+            "2 problems found\n    config.yaml:12:5: pod web: pod has no containers\n    config.yaml: pods: no pods configured"
+        );
+        assert_eq!(report("config.yaml", &[located]), "1 problem found\n    config.yaml:12:5: pod web: pod has no containers");
+    }
+}